@@ -0,0 +1,322 @@
+/*!
+`#[derive(DmxItem)]`, which implements
+[`dm_x::Item`](https://docs.rs/dm_x/latest/dm_x/trait.Item.html) for a
+struct or enum from annotations on its fields, so that types don't need
+`key_len()` and `line()` written out by hand.
+
+Use it through `dm_x`, with its `derive` feature enabled, rather than
+depending on this crate directly.
+*/
+
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Attribute, Data, DeriveInput, Error, Fields, Ident, LitStr};
+
+/*
+What a `#[dmx(...)]` attribute says about `key` and `desc`: `Some(None)`
+for a bare `key` (on a field), and `Some(Some(text))` for `key = "text"`
+(on a variant).
+*/
+#[derive(Default)]
+struct Marks {
+    key: Option<Option<LitStr>>,
+    desc: Option<Option<LitStr>>,
+}
+
+fn marks(attrs: &[Attribute]) -> syn::Result<Marks> {
+    let mut marks = Marks::default();
+    for attr in attrs.iter().filter(|a| a.path().is_ident("dmx")) {
+        attr.parse_nested_meta(|meta| {
+            let slot = if meta.path.is_ident("key") {
+                &mut marks.key
+            } else if meta.path.is_ident("desc") {
+                &mut marks.desc
+            } else {
+                return Err(meta.error("expected `key` or `desc`"));
+            };
+            if slot.is_some() {
+                return Err(meta.error("given more than once"));
+            }
+            *slot = Some(match meta.input.peek(syn::Token![=]) {
+                true => Some(meta.value()?.parse()?),
+                false => None,
+            });
+            Ok(())
+        })?;
+    }
+    Ok(marks)
+}
+
+/*
+One arm of the generated `match self`: a struct, or one variant of an enum.
+*/
+struct Arm {
+    path: TokenStream,
+    fields: Fields,
+    key: Option<Text>,
+    desc: Option<Text>,
+}
+
+/*
+Where the text of a key or description comes from.
+*/
+enum Text {
+    Field(usize),
+    Literal(LitStr),
+}
+
+fn binding(n: usize) -> Ident {
+    format_ident!("__dmx_{}", n)
+}
+
+impl Arm {
+    fn new(
+        path: TokenStream,
+        fields: &Fields,
+        attrs: &[Attribute],
+        variant: bool,
+    ) -> syn::Result<Arm> {
+        let mut arm = Arm {
+            path,
+            fields: fields.clone(),
+            key: None,
+            desc: None,
+        };
+
+        let own = marks(attrs)?;
+        for (mark, slot) in [(own.key, &mut arm.key), (own.desc, &mut arm.desc)] {
+            match mark {
+                Some(Some(text)) if variant => *slot = Some(Text::Literal(text)),
+                Some(_) => {
+                    let attr = attrs.iter().find(|a| a.path().is_ident("dmx"));
+                    let message = match variant {
+                        true => {
+                            "on a variant, give the text: #[dmx(key = \"...\", desc = \"...\")]"
+                        }
+                        false => "mark the fields of a struct, not the struct itself",
+                    };
+                    return Err(Error::new_spanned(attr, message));
+                }
+                None => {}
+            }
+        }
+
+        for (n, field) in fields.iter().enumerate() {
+            let field_marks = marks(&field.attrs)?;
+            for (mark, slot) in [
+                (field_marks.key, &mut arm.key),
+                (field_marks.desc, &mut arm.desc),
+            ] {
+                match mark {
+                    Some(None) if slot.is_none() => *slot = Some(Text::Field(n)),
+                    Some(None) => {
+                        return Err(Error::new_spanned(
+                            field,
+                            "the key and description can only come from one place each",
+                        ))
+                    }
+                    Some(Some(_)) => {
+                        return Err(Error::new_spanned(
+                            field,
+                            "on a field, just mark it: #[dmx(key)] or #[dmx(desc)]",
+                        ))
+                    }
+                    None => {}
+                }
+            }
+        }
+        Ok(arm)
+    }
+
+    /*
+    A pattern matching this arm, binding the fields in `bind` (by
+    position) to `binding(n)`.
+    */
+    fn pattern(&self, bind: &[usize]) -> TokenStream {
+        let path = &self.path;
+        match &self.fields {
+            Fields::Named(named) => {
+                let bound = named
+                    .named
+                    .iter()
+                    .enumerate()
+                    .filter(|(n, _)| bind.contains(n))
+                    .map(|(n, f)| {
+                        let name = &f.ident;
+                        let b = binding(n);
+                        quote!(#name: #b)
+                    });
+                quote!(#path { #(#bound,)* .. })
+            }
+            Fields::Unnamed(unnamed) => {
+                let bound = (0..unnamed.unnamed.len()).map(|n| match bind.contains(&n) {
+                    true => {
+                        let b = binding(n);
+                        quote!(#b)
+                    }
+                    false => quote!(_),
+                });
+                quote!(#path(#(#bound),*))
+            }
+            Fields::Unit => quote!(#path),
+        }
+    }
+
+    fn bound(text: &Option<Text>) -> Vec<usize> {
+        match text {
+            Some(Text::Field(n)) => vec![*n],
+            _ => Vec::new(),
+        }
+    }
+
+    fn text(text: &Option<Text>) -> TokenStream {
+        match text {
+            Some(Text::Field(n)) => {
+                let b = binding(*n);
+                quote!(::std::string::ToString::to_string(#b))
+            }
+            Some(Text::Literal(lit)) => quote!(::std::string::String::from(#lit)),
+            None => quote!(::std::string::String::new()),
+        }
+    }
+}
+
+fn derive(input: DeriveInput) -> syn::Result<TokenStream> {
+    let name = &input.ident;
+    let arms: Vec<Arm> = match &input.data {
+        Data::Struct(data) => {
+            let arm = Arm::new(quote!(Self), &data.fields, &input.attrs, false)?;
+            if arm.key.is_none() && arm.desc.is_none() {
+                return Err(Error::new(
+                    Span::call_site(),
+                    "DmxItem needs a field marked #[dmx(key)] or #[dmx(desc)]",
+                ));
+            }
+            vec![arm]
+        }
+        Data::Enum(data) if data.variants.is_empty() => {
+            return Err(Error::new_spanned(
+                name,
+                "DmxItem can't be derived for enums without any variants",
+            ));
+        }
+        Data::Enum(data) => data
+            .variants
+            .iter()
+            .map(|v| {
+                let ident = &v.ident;
+                let mut arm = Arm::new(quote!(Self::#ident), &v.fields, &v.attrs, true)?;
+                // A variant that says nothing about itself is shown as its name.
+                if arm.key.is_none() && arm.desc.is_none() {
+                    arm.desc = Some(Text::Literal(LitStr::new(&ident.to_string(), ident.span())));
+                }
+                Ok(arm)
+            })
+            .collect::<syn::Result<Vec<Arm>>>()?,
+        Data::Union(_) => {
+            return Err(Error::new(
+                Span::call_site(),
+                "DmxItem can't be derived for unions",
+            ));
+        }
+    };
+
+    // Without any keys, every line is just its description.
+    let keyed = arms.iter().any(|a| a.key.is_some());
+
+    let key_len = match keyed {
+        true => {
+            let cases = arms.iter().map(|arm| {
+                let pattern = arm.pattern(&Arm::bound(&arm.key));
+                let key = Arm::text(&arm.key);
+                quote!(#pattern => #key.chars().count())
+            });
+            quote!(match self { #(#cases,)* })
+        }
+        false => quote!(0),
+    };
+
+    let lines = arms.iter().map(|arm| {
+        let mut bind = Arm::bound(&arm.desc);
+        if keyed {
+            bind.extend(Arm::bound(&arm.key));
+        }
+        let pattern = arm.pattern(&bind);
+        let desc = Arm::text(&arm.desc);
+        match keyed {
+            true => {
+                let key = Arm::text(&arm.key);
+                quote!(#pattern => ::std::format!("{:w$}  {}", #key, #desc, w = key_len))
+            }
+            false => quote!(#pattern => #desc),
+        }
+    });
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::dm_x::Item for #name #ty_generics #where_clause {
+            fn key_len(&self) -> usize {
+                #key_len
+            }
+
+            fn line(&self, key_len: usize) -> ::std::vec::Vec<u8> {
+                let _ = key_len;
+                let line: ::std::string::String = match self { #(#lines,)* };
+                let mut line = line.trim_end().to_owned();
+                line.push('\n');
+                line.into_bytes()
+            }
+        }
+    })
+}
+
+/**
+Implement `dm_x::Item` for a struct or enum.
+
+On a struct, mark the field to show as the item's key with `#[dmx(key)]`,
+and the field to show as its description with `#[dmx(desc)]`; either can
+be left out. Both are shown with their `Display` implementations, lined
+up like the two-tuple implementation of `Item`.
+
+On an enum, fields of variants are marked the same way, and variants
+without any (like unit variants) can give their text instead, as
+`#[dmx(key = "...", desc = "...")]`. A variant that does neither is shown
+as its name. (An enum without any variants has no values to show, so it
+can't be an `Item`.)
+
+```
+use dm_x::{DmxItem, Item};
+
+#[derive(DmxItem)]
+struct Host {
+    #[dmx(key)]
+    alias: String,
+    #[dmx(desc)]
+    address: String,
+    port: u16,
+}
+
+let host = Host { alias: "web".into(), address: "192.0.2.7".into(), port: 22 };
+assert_eq!(host.line(3), b"web  192.0.2.7\n");
+
+#[derive(DmxItem)]
+enum Mode {
+    #[dmx(key = "a", desc = "Automatic")]
+    Auto,
+    Manual,
+}
+```
+
+```compile_fail
+# use dm_x::DmxItem;
+#[derive(DmxItem)]
+enum Never {}
+```
+*/
+#[proc_macro_derive(DmxItem, attributes(dmx))]
+pub fn derive_dmx_item(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    derive(input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
@@ -0,0 +1,64 @@
+use dm_x::{DmxItem, Item};
+
+#[derive(DmxItem)]
+struct Host {
+    #[dmx(key)]
+    alias: String,
+    #[dmx(desc)]
+    address: String,
+    #[allow(dead_code)]
+    port: u16,
+}
+
+#[derive(DmxItem)]
+struct Label(#[dmx(desc)] &'static str);
+
+#[derive(DmxItem)]
+enum Mode {
+    #[dmx(key = "a", desc = "Automatic")]
+    Auto,
+    Manual,
+    Every {
+        #[dmx(key)]
+        name: &'static str,
+        #[dmx(desc)]
+        minutes: u32,
+    },
+}
+
+#[test]
+fn structs() {
+    let host = Host {
+        alias: "web".to_owned(),
+        address: "192.0.2.7".to_owned(),
+        port: 22,
+    };
+    assert_eq!(host.key_len(), 3);
+    assert_eq!(host.line(5), b"web    192.0.2.7\n");
+
+    let label = Label("frogs");
+    assert_eq!(label.key_len(), 0);
+    assert_eq!(label.line(3), b"frogs\n");
+}
+
+#[test]
+fn enums() {
+    let modes = [
+        Mode::Auto,
+        Mode::Manual,
+        Mode::Every {
+            name: "hourly",
+            minutes: 60,
+        },
+    ];
+    let key_len = modes.iter().map(|m| m.key_len()).max().unwrap();
+    assert_eq!(key_len, 6);
+    let lines: Vec<String> = modes
+        .iter()
+        .map(|m| String::from_utf8(m.line(key_len)).unwrap())
+        .collect();
+    assert_eq!(
+        lines,
+        ["a       Automatic\n", "        Manual\n", "hourly  60\n"]
+    );
+}
@@ -6,6 +6,15 @@ Launch with
 cargo run --example launcher launcher.json
 ```
 
+Any further arguments are treated as the keys of a path of submenus, and
+the launcher will start out in that submenu, so
+
+```bash
+cargo run --example launcher launcher.json edit
+```
+
+will start with the "Other Text Editors" category.
+
 This is mainly meant to be a demonstration of implementing `Item` in a
 nontrivial way, and not an actual system program as you would use it,
 so there are a lot of `.unwrap()`s and `.expect()`s instead of actual
@@ -26,6 +35,7 @@ and contains its own `Vec` of entries.
 use once_cell::sync::OnceCell;
 use serde::{Deserialize, Serialize};
 
+use dm_x::menu::Nested;
 use dm_x::*;
 
 /**
@@ -252,6 +262,29 @@ impl Item for Entry {
     }
 }
 
+/**
+This is what lets `dm_x::menu::navigate()` present our `Entry`s as a
+hierarchical menu: selecting a `MenuDir` opens a submenu of its items,
+cancelling from a submenu returns to the menu above it, and the whole
+thing ends when the user selects a `MenuItem` (or cancels from the top
+level menu).
+*/
+impl Nested for Entry {
+    fn children(&self) -> Option<&[Entry]> {
+        match self {
+            Entry::Item(_) => None,
+            Entry::Dir(d) => Some(&d.items),
+        }
+    }
+
+    fn crumb(&self) -> String {
+        match self {
+            Entry::Item(m) => m.key.clone(),
+            Entry::Dir(d) => d.key.clone(),
+        }
+    }
+}
+
 /**
 Load the data file, which must be specified as the first argument on the
 command line.
@@ -270,43 +303,6 @@ fn load_data_file() -> Vec<Entry> {
     v
 }
 
-/**
-This function launches `dmenu` repeatedly until the user either chooses a
-`MenuItem` or cancels from the top level menu.
-*/
-fn recursive_select(dmx: &Dmx, prompt: &str, items: &[Entry]) -> Option<MenuItem> {
-    loop {
-        match dmx.select(prompt, items).unwrap() {
-            // This will cancel the process if returned from the highest-level
-            // menu, or re-display the next-higher-level menu if returned from
-            // below.
-            None => return None,
-            Some(n) => match &items[n] {
-                // If the user selects an item, return that; it will bubble up
-                // the stack of calls to `recursive_select()` and get returned
-                // to `main()`.
-                Entry::Item(m) => return Some(m.clone()),
-                // If the user selects a subcategory, call this function on
-                // the entries in that subcategory.
-                Entry::Dir(d) => {
-                    let new_prompt = format!("{}{}{}", prompt, &d.key, sep_str());
-                    // If the lower-level call returns a `MenuItem`, bubble
-                    // that back up the stack.
-                    //
-                    // Implicitly, too, because `Dmx::select()` is being called
-                    // in a `loop`, if the user cancels at the next lowest
-                    // level (and this call to `recursive_select()`)
-                    // returns `None`, the current level's category will
-                    // be redisplayed.
-                    if let Some(m) = recursive_select(dmx, &new_prompt, &d.items) {
-                        return Some(m);
-                    }
-                }
-            },
-        }
-    }
-}
-
 /**
 Launch a program from the given `chunks` of command line.
 
@@ -365,12 +361,21 @@ fn main() {
     let dmx = Dmx::automagiconf();
     SEPARATOR.set(("/".to_owned(), 1)).unwrap();
 
-    match recursive_select(&dmx, "", &items) {
+    // Keep the deepest levels of the hierarchy visible in the prompt.
+    let prompt = Breadcrumbs::new("").separator(sep_str()).max_width(24);
+
+    // Any arguments after the data file name are a path to a submenu.
+    let start: Vec<String> = std::env::args().skip(2).collect();
+
+    match menu::navigate_at(&dmx, prompt, &items, &start).unwrap() {
+        Some(Entry::Item(m)) => {
+            dmx.audit_launch(&m.exec).unwrap();
+            exec(&m.exec);
+        }
+        // `navigate_at()` only returns leaves, and `Entry::Dir`s are never leaves.
+        Some(Entry::Dir(_)) => unreachable!(),
         None => {
             println!("Nothing selected!");
         }
-        Some(m) => {
-            exec(&m.exec);
-        }
     }
 }
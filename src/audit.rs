@@ -0,0 +1,63 @@
+/*!
+Audit logging of the commands `Dmx` runs.
+
+See the documentation of `Dmx::audit_log` for the format of the log.
+*/
+
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::DmxError;
+
+/*
+Quote `arg` for a POSIX shell, if necessary.
+*/
+fn quote(arg: &str) -> String {
+    let safe = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:=@%+,".contains(c));
+    if safe {
+        arg.to_owned()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+/*
+Append a record of one event to the audit log at `path`.
+*/
+pub(crate) fn record<S: AsRef<str>>(
+    path: &Path,
+    event: &str,
+    result: &str,
+    n_items: Option<usize>,
+    argv: &[S],
+) -> Result<(), DmxError> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let n_items = match n_items {
+        Some(n) => n.to_string(),
+        None => "-".to_owned(),
+    };
+    let argv: Vec<String> = argv.iter().map(|a| quote(a.as_ref())).collect();
+    let line = format!(
+        "{}.{:03}\t{}\t{}\t{}\t{}\n",
+        now.as_secs(),
+        now.subsec_millis(),
+        event,
+        result,
+        n_items,
+        argv.join(" ")
+    );
+
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut f| f.write_all(line.as_bytes()))
+        .map_err(|e| DmxError::io(format!("Error writing audit log \"{}\"", path.display()), e))
+}
@@ -0,0 +1,1173 @@
+/*!
+The programs `Dmx` knows how to drive as menus.
+
+`dmenu` is the default (and the reason this crate exists), but it only
+runs under X11. `Dmx::backends` holds an ordered list of `Backend`s;
+`Dmx::select()` will try each in turn until one of them can actually be
+started, so the same program can show its menus on an X11 desktop and
+in a Wayland session.
+
+In a configuration file, the list of backends looks like
+
+```toml
+backends = ["fuzzel", "rofi", "dmenu", ["mymenu", "-p", "{prompt}"]]
+```
+
+where strings name built-in backends, and arrays of strings are command
+line templates, as described for `Dmx::command`.
+
+Pickers that aren't programs driven like `dmenu` at all (or programs that
+need more than a command line template) can still be used to show `Menu`s,
+by implementing the `MenuBackend` trait and calling `Menu::show_with()`.
+`Dmx` is itself the implementation used by `Menu::show()`.
+*/
+
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::str::FromStr;
+
+#[cfg(feature = "config")]
+use serde::{Deserialize, Serialize};
+
+use crate::{expand_template, probe, Color, Dmx, DmxError, FlagMap};
+
+/*
+Command line templates for built-in backends that are driven by a fixed
+set of arguments. See `Dmx::command` for the meaning of the placeholders.
+*/
+const BEMENU: &[&str] = &[
+    "bemenu",
+    "-p",
+    "{prompt}",
+    "-l",
+    "{lines}",
+    "--fn",
+    "{font}",
+    "--nb",
+    "{normal_bg}",
+    "--nf",
+    "{normal_fg}",
+    "--hb",
+    "{select_bg}",
+    "--hf",
+    "{select_fg}",
+];
+const FUZZEL: &[&str] = &[
+    "fuzzel", "--dmenu", "--prompt", "{prompt}", "--lines", "{lines}",
+];
+const FZF: &[&str] = &["fzf", "--prompt", "{prompt} ", "--layout", "reverse"];
+const ZENITY: &[&str] = &[
+    "zenity",
+    "--list",
+    "--title={prompt}",
+    "--text={prompt}",
+    "--column={prompt}",
+    "--hide-header",
+];
+const YAD: &[&str] = &[
+    "yad",
+    "--list",
+    "--title={prompt}",
+    "--text={prompt}",
+    "--column={prompt}",
+    "--no-headers",
+];
+const KDIALOG: &[&str] = &["kdialog", "--title", "{prompt}", "--menu", "{prompt}"];
+const WOFI: &[&str] = &[
+    "wofi", "--dmenu", "--prompt", "{prompt}", "--lines", "{lines}",
+];
+
+/**
+Something that can show the user a menu of lines and report which one
+they chose.
+
+A menu is shown in three steps: `spawn()` starts the picker (with a
+`Session` holding whatever's needed to keep talking to it), `feed()`
+gives it the lines to display, and `read_selection()` waits for the user
+to choose. `choose()` does all three; implementations that need to do
+something more involved (like retrying when the picker fails to start)
+can override it.
+
+The lines are already formatted by `Item::line()` (and each ends with a
+newline); which one was chosen is worked out by comparing the returned
+line to them, so a backend should return exactly the line it was fed
+(though the trailing newline is optional).
+*/
+pub trait MenuBackend {
+    /// Whatever's needed to keep track of a running picker.
+    type Session;
+
+    /**
+    Start showing a menu with the given prompt and (maximum) number of
+    lines visible at once.
+    */
+    fn spawn(&self, prompt: &str, lines: usize) -> Result<Self::Session, DmxError>;
+
+    /**
+    Give the running picker the lines to choose from.
+    */
+    fn feed(&self, session: &mut Self::Session, input: &[Vec<u8>]) -> Result<(), DmxError>;
+
+    /**
+    Wait for the user to choose, and return the line they chose, or `None`
+    if they dismissed the menu without choosing anything.
+    */
+    fn read_selection(&self, session: Self::Session) -> Result<Option<Vec<u8>>, DmxError>;
+
+    /**
+    Show a menu of `input` and return the line chosen. The default
+    implementation just calls `spawn()`, `feed()`, and `read_selection()`.
+    */
+    fn choose(
+        &self,
+        prompt: &str,
+        lines: usize,
+        input: &[Vec<u8>],
+    ) -> Result<Option<Vec<u8>>, DmxError> {
+        let mut session = self.spawn(prompt, lines)?;
+        self.feed(&mut session, input)?;
+        self.read_selection(session)
+    }
+
+    /**
+    Like `choose()`, but with `hints` about how the menu could be shown
+    (which `Menu::show_with()` provides). The default implementation
+    ignores them and calls `choose()`.
+    */
+    fn choose_hinted(
+        &self,
+        prompt: &str,
+        lines: usize,
+        input: &[Vec<u8>],
+        hints: &MenuHints,
+    ) -> Result<Option<Vec<u8>>, DmxError> {
+        let _ = hints;
+        self.choose(prompt, lines, input)
+    }
+}
+
+/**
+Extras a `MenuBackend` may use to show a menu, if it's able to; they can
+always be ignored.
+*/
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MenuHints {
+    /// The icon for each line (see `Item::icon()`), where it has one.
+    /// This is either empty or as long as the lines.
+    pub icons: Vec<Option<String>>,
+    /// The Pango markup to display for each line instead of its text (see
+    /// `Item::markup()`), where it has some. This is either empty or as
+    /// long as the lines.
+    pub markup: Vec<Option<String>>,
+    /// A message to display above the lines (see `Menu::message()`).
+    pub message: Option<String>,
+    /// If `true`, the user may choose more than one line (see
+    /// `Menu::show_multi()`); the lines chosen are returned together, each
+    /// ending with a newline.
+    pub multi: bool,
+    /// If `true`, the user may enter text that isn't one of the lines,
+    /// which is returned as is (see `Menu::allow_custom()`).
+    pub custom: bool,
+    /// The line to highlight when the menu opens (see
+    /// `Menu::preselect()`, which also puts it first, for backends that
+    /// can't highlight a line).
+    pub selected: Option<usize>,
+    /// Text to start with in the menu's input, filtering the lines (see
+    /// `Menu::query()`).
+    pub query: Option<String>,
+    /// Key bindings (like `"Alt+p"`) that choose a line in a way of their
+    /// own (see `Menu::show_pinning()`). rofi is given up to 19 of them,
+    /// as its custom key bindings; other backends ignore them.
+    pub keys: Vec<String>,
+    /// Set by the backend, when a line is chosen with one of `keys`, to
+    /// that key's index in them.
+    pub key: Cell<Option<usize>>,
+}
+
+impl MenuHints {
+    /*
+    Whether there are any icons to show.
+    */
+    fn has_icons(&self) -> bool {
+        self.icons.iter().any(|i| i.is_some())
+    }
+
+    /*
+    Whether there's any markup to show.
+    */
+    fn has_markup(&self) -> bool {
+        self.markup.iter().any(|m| m.is_some())
+    }
+}
+
+/**
+A program that can be used to display a menu.
+*/
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "config", derive(Deserialize, Serialize))]
+#[cfg_attr(
+    feature = "config",
+    serde(try_from = "BackendSpec", into = "BackendSpec")
+)]
+pub enum Backend {
+    /// Suckless `dmenu` (or whatever is at `Dmx::dmenu`), for X11. Options
+    /// that a patched or forked binary doesn't accept (according to its
+    /// usage message) are left out.
+    Dmenu,
+    /// [`dmenu-wl`](https://github.com/nyyManni/dmenu-wayland), a port of
+    /// `dmenu` to Wayland. It takes (most of) the same options, but its font
+    /// is in Pango's format, and options it doesn't understand are left out.
+    /// A `Backend::Dmenu` whose `Dmx::dmenu` is a `dmenu-wl` binary is
+    /// treated as this.
+    DmenuWl,
+    /// [`bemenu`](https://github.com/Cloudef/bemenu), which runs under
+    /// X11, Wayland, or in a terminal.
+    Bemenu,
+    /// [`rofi`](https://github.com/davatorium/rofi) in `-dmenu` mode. The
+    /// colors are passed as theme overrides, and the font is translated
+    /// to the Pango format rofi expects.
+    Rofi,
+    /// [`fuzzel`](https://codeberg.org/dnkl/fuzzel) in `--dmenu` mode,
+    /// for Wayland. (The font and colors are left to fuzzel's own
+    /// configuration.)
+    Fuzzel,
+    /// [`wofi`](https://hg.sr.ht/~scoopta/wofi) in `--dmenu` mode, for
+    /// Wayland. (wofi is styled with CSS, so the font and colors are left
+    /// to its own stylesheet.)
+    Wofi,
+    /// [`fzf`](https://github.com/junegunn/fzf), in the controlling
+    /// terminal. Put this after the graphical backends (like `backends =
+    /// ["dmenu", "fzf"]`) to get the same menus over SSH as on the desktop.
+    /// Matching is exact unless `Dmx::fuzzy` is set.
+    Fzf,
+    /// A [`zenity`](https://gitlab.gnome.org/GNOME/zenity) list dialog,
+    /// for desktops without a keyboard-driven menu program. (The font and
+    /// colors are left to the GTK theme.)
+    Zenity,
+    /// A [`yad`](https://github.com/v1cont/yad) list dialog, like
+    /// `Backend::Zenity`.
+    Yad,
+    /// A [`kdialog`](https://invent.kde.org/utilities/kdialog) menu dialog,
+    /// for KDE. (The font and colors are left to the Qt theme.)
+    Kdialog,
+    /// [`choose`](https://github.com/chipsenkbeil/choose), a fuzzy menu
+    /// for macOS. The font and background and highlight colors are passed
+    /// along (choose has no options for the text colors).
+    Choose,
+    /// A numbered list printed in the terminal, from which the user picks
+    /// by typing a number. This runs inside the program itself (see the
+    /// `numbered` module), so it works anywhere there's a terminal; put it
+    /// last, as the fallback when nothing else can run.
+    Numbered,
+    /// An arbitrary command line template, like `Dmx::command`. The
+    /// program is expected to exit like `dmenu` does: 0 if something
+    /// was chosen, 1 if the user cancelled, and anything else on error.
+    Command(Vec<String>),
+}
+
+/*
+What a menu program means by exiting with a given status.
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ExitMeaning {
+    /// The user chose something, which will be on stdout.
+    Selected,
+    /// The user dismissed the menu without choosing anything.
+    Cancelled,
+    /// The user chose something (which will be on stdout) with the `n`th
+    /// custom key binding, counting from 1.
+    Key(u8),
+    /// Something went wrong.
+    Failed,
+}
+
+/*
+Exit statuses for each backend, and what they mean. Any status not in a
+backend's table (or a termination by signal) means `ExitMeaning::Failed`.
+*/
+const DMENU_EXITS: &[(i32, ExitMeaning)] =
+    &[(0, ExitMeaning::Selected), (1, ExitMeaning::Cancelled)];
+// rofi also exits with 10 to 28 when something is chosen with one of its
+// custom key bindings (`-kb-custom-1` to `-kb-custom-19`); see
+// `Backend::exit_meaning()`.
+const ROFI_EXITS: &[(i32, ExitMeaning)] =
+    &[(0, ExitMeaning::Selected), (1, ExitMeaning::Cancelled)];
+const FUZZEL_EXITS: &[(i32, ExitMeaning)] =
+    &[(0, ExitMeaning::Selected), (2, ExitMeaning::Cancelled)];
+// fzf exits with 1 if nothing matches, and 130 if interrupted (with Esc).
+const FZF_EXITS: &[(i32, ExitMeaning)] = &[
+    (0, ExitMeaning::Selected),
+    (1, ExitMeaning::Cancelled),
+    (130, ExitMeaning::Cancelled),
+];
+// zenity exits with 5 when it times out.
+const ZENITY_EXITS: &[(i32, ExitMeaning)] = &[
+    (0, ExitMeaning::Selected),
+    (1, ExitMeaning::Cancelled),
+    (5, ExitMeaning::Cancelled),
+];
+// yad exits with 252 when closed with Escape (or the window manager), and
+// 70 when it times out.
+const YAD_EXITS: &[(i32, ExitMeaning)] = &[
+    (0, ExitMeaning::Selected),
+    (1, ExitMeaning::Cancelled),
+    (70, ExitMeaning::Cancelled),
+    (252, ExitMeaning::Cancelled),
+];
+
+impl Backend {
+    /**
+    The name by which this backend is specified in a configuration file
+    (or the name of the program for a `Backend::Command`).
+    */
+    pub fn name(&self) -> &str {
+        match self {
+            Backend::Dmenu => "dmenu",
+            Backend::DmenuWl => "dmenu-wl",
+            Backend::Bemenu => "bemenu",
+            Backend::Rofi => "rofi",
+            Backend::Fuzzel => "fuzzel",
+            Backend::Wofi => "wofi",
+            Backend::Fzf => "fzf",
+            Backend::Zenity => "zenity",
+            Backend::Yad => "yad",
+            Backend::Kdialog => "kdialog",
+            Backend::Choose => "choose",
+            Backend::Numbered => "numbered",
+            Backend::Command(argv) => argv.first().map(|s| s.as_str()).unwrap_or(""),
+        }
+    }
+
+    /*
+    Return an error explaining why this backend can't possibly run in the
+    environment `dmx` will give it, if that's the case.
+    */
+    pub(crate) fn check(&self, dmx: &Dmx) -> Result<(), DmxError> {
+        if *self == Backend::Dmenu && is_dmenu_wl(dmx) {
+            return Backend::DmenuWl.check(dmx);
+        }
+        let unavailable = |reason: String| DmxError::Unavailable {
+            backend: self.to_string(),
+            reason,
+        };
+        let no_display = |reason: String| DmxError::NoDisplay {
+            backend: self.to_string(),
+            reason,
+        };
+        if dmx.fuzzy && dmx.fuzzy_required && !self.supports_fuzzy(dmx) {
+            return Err(unavailable(format!(
+                "{} doesn't support fuzzy matching.",
+                self
+            )));
+        }
+
+        match self {
+            Backend::Dmenu => {
+                if dmx.child_var("DISPLAY").is_some() {
+                    Ok(())
+                } else if dmx.child_var("WAYLAND_DISPLAY").is_some() {
+                    // XWayland would have set $DISPLAY, so it isn't running.
+                    Err(no_display(
+                        "This is a Wayland session without XWayland, and dmenu \
+                        only speaks X11. Use a Wayland menu program instead by \
+                        setting the `backends` configuration option (e.g. \
+                        `backends = [\"wofi\", \"fuzzel\", \"bemenu\"]`)."
+                            .to_owned(),
+                    ))
+                } else {
+                    Err(no_display(
+                        "No X display available: $DISPLAY is not set (or not \
+                        passed through), and dmenu can't run without one. If \
+                        this program was started by a hotkey daemon or service, \
+                        make sure it inherits DISPLAY and XAUTHORITY from your \
+                        X session (e.g. with `systemctl --user \
+                        import-environment DISPLAY XAUTHORITY`)."
+                            .to_owned(),
+                    ))
+                }
+            }
+            Backend::DmenuWl | Backend::Fuzzel | Backend::Wofi => {
+                match dmx.child_var("WAYLAND_DISPLAY") {
+                    Some(_) => Ok(()),
+                    None => Err(no_display(format!(
+                        "{} needs a Wayland session, but $WAYLAND_DISPLAY is not \
+                    set (or not passed through).",
+                        self
+                    ))),
+                }
+            }
+            // These run under X11 (and, in rofi's case, a popular fork runs
+            // under Wayland; GTK and Qt dialogs run under either).
+            Backend::Rofi | Backend::Zenity | Backend::Yad | Backend::Kdialog => {
+                match dmx
+                    .child_var("DISPLAY")
+                    .or(dmx.child_var("WAYLAND_DISPLAY"))
+                {
+                    Some(_) => Ok(()),
+                    None => Err(no_display(format!(
+                        "{} needs a display, but neither $DISPLAY nor \
+                        $WAYLAND_DISPLAY is set (or passed through).",
+                        self
+                    ))),
+                }
+            }
+            Backend::Fzf => match std::fs::File::open("/dev/tty") {
+                Ok(_) => Ok(()),
+                Err(e) => Err(unavailable(format!(
+                    "fzf needs a terminal, but there isn't one: {}",
+                    &e
+                ))),
+            },
+            // Something else called `choose` (a `cut` replacement) is
+            // common elsewhere.
+            Backend::Choose => {
+                if cfg!(target_os = "macos") {
+                    Ok(())
+                } else {
+                    Err(unavailable("choose only runs on macOS.".to_owned()))
+                }
+            }
+            Backend::Numbered => {
+                use std::io::IsTerminal;
+                if std::io::stdin().is_terminal() {
+                    Ok(())
+                } else {
+                    Err(unavailable(
+                        "numbered needs a terminal, but standard input isn't one.".to_owned(),
+                    ))
+                }
+            }
+            Backend::Bemenu | Backend::Command(_) => Ok(()),
+        }
+    }
+
+    /*
+    Whether this backend can do fuzzy matching. A `Command` template is
+    assumed to be able to do whatever it's been told.
+    */
+    pub(crate) fn supports_fuzzy(&self, dmx: &Dmx) -> bool {
+        match self {
+            Backend::Dmenu => dmenu_fuzzy_flag(dmx).is_some(),
+            // fuzzel's default matching is already fuzzy.
+            Backend::Rofi
+            | Backend::Fuzzel
+            | Backend::Wofi
+            | Backend::Fzf
+            | Backend::Choose
+            | Backend::Command(_) => true,
+            Backend::DmenuWl
+            | Backend::Bemenu
+            | Backend::Zenity
+            | Backend::Yad
+            | Backend::Kdialog
+            | Backend::Numbered => false,
+        }
+    }
+
+    /*
+    Translate the menu program's exit status into what it means.
+    */
+    pub(crate) fn exit_meaning(&self, code: Option<i32>) -> ExitMeaning {
+        let table = match self {
+            Backend::Rofi => match code {
+                Some(c @ 10..=28) => return ExitMeaning::Key(c as u8 - 9),
+                _ => ROFI_EXITS,
+            },
+            Backend::Fuzzel => FUZZEL_EXITS,
+            Backend::Fzf => FZF_EXITS,
+            Backend::Zenity => ZENITY_EXITS,
+            Backend::Yad => YAD_EXITS,
+            // bemenu and custom commands are assumed to follow dmenu's lead.
+            Backend::Dmenu
+            | Backend::DmenuWl
+            | Backend::Bemenu
+            | Backend::Wofi
+            | Backend::Kdialog
+            | Backend::Choose
+            | Backend::Numbered
+            | Backend::Command(_) => DMENU_EXITS,
+        };
+        code.and_then(|c| table.iter().find(|(n, _)| *n == c))
+            .map(|(_, meaning)| *meaning)
+            .unwrap_or(ExitMeaning::Failed)
+    }
+
+    /*
+    Add any arguments the menu program needs to show what's in `hints`.
+    */
+    pub(crate) fn apply_hints(&self, cmd: &mut Command, hints: &MenuHints) {
+        if *self == Backend::Rofi && hints.has_icons() {
+            cmd.arg("-show-icons");
+        }
+        if *self == Backend::Rofi && hints.has_markup() {
+            cmd.arg("-markup-rows");
+        }
+        if *self == Backend::Rofi {
+            for (n, key) in hints.keys.iter().take(19).enumerate() {
+                cmd.arg(format!("-kb-custom-{}", n + 1)).arg(key);
+            }
+        }
+        if hints.multi {
+            match self {
+                Backend::Rofi => {
+                    cmd.arg("-multi-select");
+                }
+                Backend::Fzf => {
+                    cmd.arg("--multi");
+                }
+                Backend::Zenity => {
+                    cmd.args(["--multiple", "--separator=\n"]);
+                }
+                Backend::Yad => {
+                    cmd.arg("--multiple");
+                }
+                // dmenu (and most of its imitators) print each line chosen
+                // with Ctrl-Enter and stay open, without being asked.
+                _ => {}
+            }
+        }
+        if self.prints_query(hints) {
+            cmd.arg("--print-query");
+        }
+        if let Some(n) = hints.selected.filter(|_| *self == Backend::Rofi) {
+            cmd.args(["-selected-row", &n.to_string()]);
+        }
+        if let Some(query) = &hints.query {
+            match self {
+                Backend::Rofi => {
+                    cmd.args(["-filter", query]);
+                }
+                Backend::Fzf => {
+                    cmd.args(["--query", query]);
+                }
+                Backend::Wofi => {
+                    cmd.args(["--search", query]);
+                }
+                // Only with the initialtext patch, which stock dmenu
+                // doesn't have (so it has to be probed for).
+                Backend::Dmenu => {
+                    let flags = probe::flags(Path::new(cmd.get_program()));
+                    if flags.map(|f| f.contains("it")).unwrap_or(false) {
+                        cmd.args(["-it", query]);
+                    }
+                }
+                _ => {}
+            }
+        }
+        if let Some(message) = &hints.message {
+            match self {
+                Backend::Rofi => {
+                    cmd.args(["-mesg", &crate::escape_markup(message)]);
+                }
+                Backend::Fzf => {
+                    cmd.args(["--header", message]);
+                }
+                // This replaces the prompt given in the template.
+                Backend::Zenity | Backend::Yad => {
+                    cmd.arg(format!("--text={}", crate::escape_markup(message)));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /*
+    Whether the menu program prints what was typed before what was chosen
+    (which fzf has to be asked to do, in `apply_hints()`, for it to return
+    text that doesn't match any line).
+    */
+    pub(crate) fn prints_query(&self, hints: &MenuHints) -> bool {
+        hints.custom && *self == Backend::Fzf
+    }
+
+    /*
+    Take what was typed out of the `choice` of a menu program that
+    `prints_query()`: what's left, if anything was chosen, or else what
+    was typed.
+    */
+    pub(crate) fn strip_query(&self, choice: Vec<u8>, hints: &MenuHints) -> Vec<u8> {
+        if !self.prints_query(hints) {
+            return choice;
+        }
+        match choice.iter().position(|&b| b == b'\n') {
+            Some(n) if n + 1 < choice.len() => choice[n + 1..].to_vec(),
+            _ => choice,
+        }
+    }
+
+    /*
+    Whether this backend can display `MenuHints::message` on its own (in
+    `apply_hints()`). Otherwise it's added as the first line.
+    */
+    fn shows_message(&self) -> bool {
+        matches!(
+            self,
+            Backend::Rofi | Backend::Fzf | Backend::Zenity | Backend::Yad
+        )
+    }
+
+    /*
+    Add the information in `hints` to the lines to be fed to the menu
+    program, in whatever way it understands. rofi reads an icon name
+    following each line, as `\0icon\x1f<name>`, and with markup turned on,
+    takes every line as markup (so those without any are escaped). A
+    message the backend can't display otherwise goes in a line of its own
+    at the top.
+    */
+    pub(crate) fn annotate<'a>(
+        &self,
+        output: &'a [Vec<u8>],
+        hints: &MenuHints,
+    ) -> Cow<'a, [Vec<u8>]> {
+        let message = hints.message.as_ref().filter(|_| !self.shows_message());
+        let rofi = *self == Backend::Rofi;
+        let (icons, markup) = (rofi && hints.has_icons(), rofi && hints.has_markup());
+        if message.is_none() && !icons && !markup {
+            return Cow::Borrowed(output);
+        }
+        let message = message.map(|m| format!("{}\n", m.replace('\n', " ")).into_bytes());
+        let annotated = output.iter().enumerate().map(|(n, line)| {
+            let text = line.strip_suffix(b"\n").unwrap_or(line);
+            let mut line = match hints.markup.get(n) {
+                Some(Some(m)) if markup => m.as_bytes().to_vec(),
+                _ if markup => crate::escape_markup(&String::from_utf8_lossy(text)).into_bytes(),
+                _ => text.to_vec(),
+            };
+            if let Some(Some(icon)) = hints.icons.get(n).filter(|_| icons) {
+                line.extend_from_slice(b"\0icon\x1f");
+                line.extend_from_slice(icon.as_bytes());
+            }
+            line.push(b'\n');
+            line
+        });
+        Cow::Owned(message.into_iter().chain(annotated).collect())
+    }
+
+    /*
+    Undo `annotate()`: turn the `choice` the menu program returned (which
+    is one of the lines it was `fed`, minus any metadata) back into the
+    line of `output` it came from. Anything else (including the message
+    line) is returned as is.
+    */
+    pub(crate) fn unannotate(
+        &self,
+        choice: Vec<u8>,
+        output: &[Vec<u8>],
+        fed: &[Vec<u8>],
+    ) -> Vec<u8> {
+        if fed == output {
+            return choice;
+        }
+        let text = |line: &[u8]| -> Vec<u8> {
+            let line = line.strip_suffix(b"\n").unwrap_or(line);
+            line.split(|&b| b == 0).next().unwrap_or(line).to_vec()
+        };
+        let chosen = text(&choice);
+        // Skip the message line, if there is one.
+        let skip = fed.len() - output.len();
+        match fed[skip..].iter().position(|line| text(line) == chosen) {
+            Some(n) => output[n].clone(),
+            None => choice,
+        }
+    }
+
+    /*
+    Whether this backend runs inside this process, rather than as a
+    program.
+    */
+    pub(crate) fn in_process(&self) -> bool {
+        *self == Backend::Numbered
+    }
+
+    /*
+    Whether this backend takes its items on the command line (each as a
+    tag, its index, followed by the line), and prints the tag of the one
+    chosen, rather than reading them from stdin.
+    */
+    pub(crate) fn items_as_args(&self) -> bool {
+        *self == Backend::Kdialog
+    }
+
+    /*
+    Turn what the menu program printed when something was chosen into the
+    line that was chosen, given the lines it was `output`. Returns `None` if
+    it doesn't correspond to a line.
+    */
+    pub(crate) fn clean_choice(&self, mut choice: Vec<u8>, output: &[Vec<u8>]) -> Option<Vec<u8>> {
+        if self.items_as_args() {
+            let tag = String::from_utf8_lossy(&choice);
+            return tag
+                .trim()
+                .parse::<usize>()
+                .ok()
+                .and_then(|n| output.get(n).cloned());
+        }
+        // yad follows each column it prints with a `|`.
+        if *self == Backend::Yad {
+            if choice.ends_with(b"|\n") {
+                choice.truncate(choice.len() - 2);
+            } else if choice.ends_with(b"|") {
+                choice.pop();
+            }
+        }
+        Some(choice)
+    }
+
+    /*
+    The program this backend runs, found without running anything (unlike
+    `command()`, which may probe `dmenu`).
+    */
+    pub(crate) fn program(&self, dmx: &Dmx) -> PathBuf {
+        match self {
+            Backend::Dmenu => dmx.dmenu.clone(),
+            Backend::DmenuWl if is_dmenu_wl(dmx) => dmx.dmenu.clone(),
+            Backend::DmenuWl => PathBuf::from("dmenu-wl"),
+            _ => PathBuf::from(self.command(dmx, "", 1).get_program()),
+        }
+    }
+
+    /*
+    Generate the program and arguments to run this backend.
+    */
+    pub(crate) fn command(&self, dmx: &Dmx, prompt: &str, lines: usize) -> Command {
+        match self {
+            Backend::Dmenu if is_dmenu_wl(dmx) => Backend::DmenuWl.command(dmx, prompt, lines),
+            Backend::DmenuWl => {
+                let program = self.program(dmx);
+                let font = pango_font(dmx.font.name());
+                // dmenu-wl doesn't take all of dmenu's options, so it's
+                // always probed.
+                let flags = FlagMap::default();
+                let probe = may_probe(dmx, &program);
+                let mut c = dmenu_cmd(&program, &flags, probe, dmx, prompt, lines, &font);
+                c.args(&dmx.extra_args);
+                c
+            }
+            Backend::Dmenu => {
+                let version = Some(&dmx.dmenu)
+                    .filter(|program| may_probe(dmx, program))
+                    .and_then(|program| probe::version(program));
+                let font = match version.as_deref().and_then(probe::parse_version) {
+                    // Before 4.6, dmenu used X core fonts rather than Xft.
+                    Some(("dmenu", major, minor)) if (major, minor) < (4, 6) => {
+                        xlfd_font(dmx.font.name())
+                    }
+                    _ => dmx.font.to_string(),
+                };
+                let lines = match dmenu_columns(dmx) {
+                    Some(columns) => lines.div_ceil(columns as usize),
+                    None => lines,
+                };
+                let probe = dmx.flag_map != FlagMap::default() && may_probe(dmx, &dmx.dmenu);
+                let mut c = dmenu_cmd(&dmx.dmenu, &dmx.flag_map, probe, dmx, prompt, lines, &font);
+                if dmx.fuzzy {
+                    c.args(dmenu_fuzzy_flag(dmx));
+                }
+                dmenu_patch_options(&mut c, dmx);
+                c.args(&dmx.extra_args);
+                c
+            }
+            Backend::Rofi => {
+                let mut c = Command::new("rofi");
+                c.args([
+                    "-dmenu",
+                    "-p",
+                    prompt,
+                    "-l",
+                    &lines.to_string(),
+                    "-font",
+                    &pango_font(dmx.font.name()),
+                ]);
+                match dmx
+                    .backend_overrides
+                    .get("rofi")
+                    .and_then(|o| o.theme.as_ref())
+                {
+                    Some(theme) => c.args(["-theme", theme]),
+                    None => c.args(["-theme-str", &rofi_theme(dmx)]),
+                };
+                if dmx.fuzzy {
+                    c.args(["-matching", "fuzzy"]);
+                }
+                if let Some(monitor) = dmx.monitor {
+                    c.args(["-monitor", &monitor.to_string()]);
+                }
+                c
+            }
+            Backend::Fzf => {
+                let mut c = template_cmd(dmx, FZF, prompt, lines);
+                // Room for the prompt and fzf's info line, too.
+                c.args(["--height", &(lines + 2).to_string()]);
+                if !dmx.fuzzy {
+                    c.arg("--exact");
+                }
+                c
+            }
+            Backend::Bemenu => {
+                let mut c = template_cmd(dmx, BEMENU, prompt, lines);
+                if let Some(monitor) = dmx.monitor {
+                    c.args(["-m", &monitor.to_string()]);
+                }
+                c
+            }
+            Backend::Zenity => template_cmd(dmx, ZENITY, prompt, lines),
+            Backend::Yad => template_cmd(dmx, YAD, prompt, lines),
+            Backend::Kdialog => template_cmd(dmx, KDIALOG, prompt, lines),
+            Backend::Fuzzel => template_cmd(dmx, FUZZEL, prompt, lines),
+            Backend::Wofi => {
+                let mut c = template_cmd(dmx, WOFI, prompt, lines);
+                if dmx.fuzzy {
+                    c.args(["--matching", "fuzzy"]);
+                }
+                c
+            }
+            Backend::Choose => {
+                // choose wants its colors as six bare hex digits, like
+                // `222222`.
+                let hex = |color: &Color| color.to_string().trim_start_matches('#').to_owned();
+                let (family, size) = split_font(dmx.font.name());
+                let mut c = Command::new("choose");
+                c.args([
+                    "-p",
+                    prompt,
+                    "-n",
+                    &lines.to_string(),
+                    "-f",
+                    family,
+                    "-b",
+                    &hex(&dmx.normal_bg),
+                    "-c",
+                    &hex(&dmx.select_bg),
+                ]);
+                if let Some(size) = size {
+                    c.args(["-s", size]);
+                }
+                c
+            }
+            // This is never run (see `Backend::in_process()`).
+            Backend::Numbered => Command::new(self.name()),
+            Backend::Command(argv) => template_cmd(dmx, argv, prompt, lines),
+        }
+    }
+}
+
+/*
+The command line for the `dmenu`-like program at `program`, with the
+options named by `flags`. If `probe` is set, any it doesn't accept
+(according to `probe::flags()`) are left out, since it would refuse to
+run at all if given one; if it can't be probed, every option is passed.
+*/
+fn dmenu_cmd(
+    program: &Path,
+    flags: &FlagMap,
+    probe: bool,
+    dmx: &Dmx,
+    prompt: &str,
+    lines: usize,
+    font: &str,
+) -> Command {
+    let lines = lines.to_string();
+    let [nb, nf, sb, sf] = [
+        &dmx.normal_bg,
+        &dmx.normal_fg,
+        &dmx.select_bg,
+        &dmx.select_fg,
+    ]
+    .map(|color| color.to_string());
+    let options = [
+        (&flags.lines, lines.as_str()),
+        (&flags.prompt, prompt),
+        (&flags.font, font),
+        (&flags.normal_bg, &nb),
+        (&flags.normal_fg, &nf),
+        (&flags.select_bg, &sb),
+        (&flags.select_fg, &sf),
+    ];
+    let supported = if probe { probe::flags(program) } else { None };
+
+    let mut c = Command::new(program);
+    for (flag, value) in options.iter() {
+        if flag.is_empty() {
+            continue;
+        }
+        // The usage message is parsed into flags without their first `-`.
+        let name = flag.strip_prefix('-').unwrap_or(flag);
+        if supported.as_ref().map(|s| s.contains(name)).unwrap_or(true) {
+            c.arg(flag.as_str()).arg(value);
+        }
+    }
+    c
+}
+
+/*
+Generate a program and arguments from a command line template.
+*/
+fn template_cmd<S: AsRef<str>>(dmx: &Dmx, template: &[S], prompt: &str, lines: usize) -> Command {
+    let lines = lines.to_string();
+    let dmenu = dmx.dmenu.to_string_lossy();
+    let [nb, nf, sb, sf] = [
+        &dmx.normal_bg,
+        &dmx.normal_fg,
+        &dmx.select_bg,
+        &dmx.select_fg,
+    ]
+    .map(|color| color.to_string());
+    let lookup = |key: &str| -> Option<&str> {
+        match key {
+            "prompt" => Some(prompt),
+            "lines" => Some(&lines),
+            "dmenu" => Some(&dmenu),
+            "font" => Some(dmx.font.name()),
+            "normal_bg" => Some(&nb),
+            "normal_fg" => Some(&nf),
+            "select_bg" => Some(&sb),
+            "select_fg" => Some(&sf),
+            _ => None,
+        }
+    };
+
+    let mut argv = template.iter().map(|a| expand_template(a.as_ref(), lookup));
+    let mut c = Command::new(argv.next().unwrap_or_default());
+    c.args(argv);
+    c
+}
+
+/*
+Add the optional options (mostly from patches to `dmenu`) that are set in
+`dmx`, if its `dmenu` accepts them (or can't be probed to find out).
+*/
+fn dmenu_patch_options(c: &mut Command, dmx: &Dmx) {
+    // Probing runs `dmenu`, which isn't worth doing if none are set.
+    let requested = dmx.center
+        || dmx.border_width.is_some()
+        || dmx.width.is_some()
+        || dmx.columns.is_some()
+        || dmx.embed_window.is_some()
+        || dmx.monitor.is_some();
+    if !requested || !may_probe(dmx, &dmx.dmenu) {
+        return;
+    }
+    let flags = probe::flags(&dmx.dmenu);
+    let switches = probe::switches(&dmx.dmenu);
+    let accepts = |flag: &str| flags.as_ref().map(|f| f.contains(flag)).unwrap_or(true);
+    let takes_arg =
+        |flag: &str| accepts(flag) && !switches.as_ref().map(|s| s.contains(flag)).unwrap_or(false);
+
+    if dmx.center && accepts("c") {
+        c.arg("-c");
+    }
+    if let Some(bw) = dmx.border_width.filter(|_| takes_arg("bw")) {
+        c.args(["-bw", &bw.to_string()]);
+    }
+    // The xyw patch's `-z` is a width; others use it for fuzzy matching.
+    if let Some(width) = dmx.width.filter(|_| takes_arg("z")) {
+        c.args(["-z", &width.to_string()]);
+    }
+    if let Some(columns) = dmenu_columns(dmx) {
+        c.args(["-g", &columns.to_string()]);
+    }
+    if let Some(window) = dmx.embed_window.filter(|_| takes_arg("w")) {
+        c.args(["-w", &window.to_string()]);
+    }
+    if let Some(monitor) = dmx.monitor.filter(|_| takes_arg("m")) {
+        c.args(["-m", &monitor.to_string()]);
+    }
+}
+
+/*
+The number of columns `dmx`'s `dmenu` will lay its items out in, if it's
+been asked for (more than one) and `dmenu` has the grid patch (or can't
+be probed).
+*/
+fn dmenu_columns(dmx: &Dmx) -> Option<u32> {
+    let columns = dmx.columns.filter(|&n| n > 1)?;
+    if !may_probe(dmx, &dmx.dmenu) {
+        return Some(columns);
+    }
+    match probe::flags(&dmx.dmenu) {
+        Some(flags) if !flags.contains("g") => None,
+        _ => Some(columns),
+    }
+}
+
+/*
+The switch that turns on fuzzy matching in `dmx`'s `dmenu`, if it has
+one: `-F` from the fuzzymatch patch, or `-z` from some others. (Patches
+that take an argument with `-z`, like a width, don't count.)
+*/
+fn dmenu_fuzzy_flag(dmx: &Dmx) -> Option<&'static str> {
+    if !may_probe(dmx, &dmx.dmenu) {
+        return None;
+    }
+    let switches = probe::switches(&dmx.dmenu)?;
+    ["F", "z"]
+        .iter()
+        .find(|s| switches.contains(**s))
+        .map(|s| if *s == "F" { "-F" } else { "-z" })
+}
+
+/*
+Whether `dmx`'s `dmenu` is `dmenu-wl`, by its name or (if it may be
+probed) the version it reports.
+*/
+fn is_dmenu_wl(dmx: &Dmx) -> bool {
+    let path = &dmx.dmenu;
+    let named = path
+        .file_name()
+        .map(|n| n.to_string_lossy().starts_with("dmenu-wl"))
+        .unwrap_or(false);
+    named
+        || (may_probe(dmx, path)
+            && probe::version(path)
+                .and_then(|v| probe::parse_version(&v).map(|(name, _, _)| name == "dmenu-wl"))
+                .unwrap_or(false))
+}
+
+/*
+Whether `program` may be run to probe it: with `Dmx::absolute_binary`
+set, a bare name isn't run at all, since `$PATH` might find anything.
+*/
+pub(crate) fn may_probe(dmx: &Dmx, program: &Path) -> bool {
+    !dmx.absolute_binary || program.is_absolute()
+}
+
+/*
+Split an Xft font name, like `LiberationMono-12` or `Terminus:size=12`,
+into its family and size. If no size can be found, the whole name is
+taken to be the family.
+*/
+pub(crate) fn split_font(font: &str) -> (&str, Option<&str>) {
+    let (family, size) = if let Some((family, rest)) = font.split_once(":size=") {
+        (family, rest.split(':').next().unwrap_or(""))
+    } else if let Some((family, size)) = font.rsplit_once('-') {
+        (family, size)
+    } else {
+        return (font, None);
+    };
+    let size_ok = !size.is_empty() && size.chars().all(|c| c.is_ascii_digit() || c == '.');
+    if size_ok {
+        (family, Some(size))
+    } else {
+        (font, None)
+    }
+}
+
+/*
+Translate an Xft font name to an X logical font description, like
+`-*-terminus-*-r-*-*-*-120-*-*-*-*-*-*`. Anything already starting with
+`-` (or with no size) is left alone.
+*/
+fn xlfd_font(font: &str) -> String {
+    if font.starts_with('-') {
+        return font.to_owned();
+    }
+    match split_font(font) {
+        (family, Some(size)) => {
+            let points: f64 = size.parse().unwrap_or(12.0);
+            format!(
+                "-*-{}-*-r-*-*-*-{}-*-*-*-*-*-*",
+                family.to_lowercase(),
+                (points * 10.0).round() as u32
+            )
+        }
+        (_, None) => font.to_owned(),
+    }
+}
+
+/*
+Translate an Xft font name to Pango's format, like `LiberationMono 12`.
+Anything else is assumed to be in Pango's format already.
+*/
+fn pango_font(font: &str) -> String {
+    match split_font(font) {
+        (family, Some(size)) => format!("{} {}", family, size),
+        (_, None) => font.to_owned(),
+    }
+}
+
+/*
+Theme overrides that apply `dmx`'s colors to rofi's window, input line,
+and list elements.
+*/
+fn rofi_theme(dmx: &Dmx) -> String {
+    format!(
+        "window {{ background-color: {nb}; border-color: {sb}; }} \
+         mainbox, inputbar, listview {{ background-color: {nb}; }} \
+         prompt, entry, case-indicator {{ background-color: {nb}; text-color: {nf}; }} \
+         element normal.normal, element alternate.normal {{ background-color: {nb}; text-color: {nf}; }} \
+         element selected.normal {{ background-color: {sb}; text-color: {sf}; }} \
+         element-text {{ background-color: inherit; text-color: inherit; }}",
+        nb = &dmx.normal_bg,
+        nf = &dmx.normal_fg,
+        sb = &dmx.select_bg,
+        sf = &dmx.select_fg,
+    )
+}
+
+impl Display for Backend {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Backend {
+    type Err = DmxError;
+
+    fn from_str(s: &str) -> Result<Backend, DmxError> {
+        match s {
+            "dmenu" => Ok(Backend::Dmenu),
+            "dmenu-wl" => Ok(Backend::DmenuWl),
+            "bemenu" => Ok(Backend::Bemenu),
+            "rofi" => Ok(Backend::Rofi),
+            "fuzzel" => Ok(Backend::Fuzzel),
+            "wofi" => Ok(Backend::Wofi),
+            "fzf" => Ok(Backend::Fzf),
+            "zenity" => Ok(Backend::Zenity),
+            "yad" => Ok(Backend::Yad),
+            "kdialog" => Ok(Backend::Kdialog),
+            "choose" => Ok(Backend::Choose),
+            "numbered" => Ok(Backend::Numbered),
+            _ => Err(DmxError::ConfigParse(format!("Unknown backend: \"{}\"", s))),
+        }
+    }
+}
+
+/*
+How a `Backend` appears in a configuration file: either the name of a
+built-in backend, or a command line template.
+*/
+#[cfg(feature = "config")]
+#[derive(Deserialize, Serialize)]
+#[serde(untagged)]
+enum BackendSpec {
+    Name(String),
+    Command(Vec<String>),
+}
+
+#[cfg(feature = "config")]
+impl TryFrom<BackendSpec> for Backend {
+    type Error = DmxError;
+
+    fn try_from(spec: BackendSpec) -> Result<Backend, DmxError> {
+        match spec {
+            BackendSpec::Name(name) => name.parse(),
+            BackendSpec::Command(argv) if argv.is_empty() => Err(DmxError::ConfigParse(
+                "Backend command must not be empty.".to_owned(),
+            )),
+            BackendSpec::Command(argv) => Ok(Backend::Command(argv)),
+        }
+    }
+}
+
+#[cfg(feature = "config")]
+impl From<Backend> for BackendSpec {
+    fn from(b: Backend) -> BackendSpec {
+        match b {
+            Backend::Command(argv) => BackendSpec::Command(argv),
+            b => BackendSpec::Name(b.name().to_owned()),
+        }
+    }
+}
@@ -0,0 +1,49 @@
+/*!
+`dmx`, for setting up the configuration that programs using `dm_x` share.
+
+```text
+dmx init-config [PATH]
+```
+
+writes a configuration file with every setting explained in it, to `PATH`
+or to `dmx.toml` in `$XDG_CONFIG_HOME` (or `~/.config`).
+*/
+
+use std::process::ExitCode;
+
+use dm_x::Dmx;
+
+const USAGE: &str = "usage: dmx init-config [PATH]
+
+Write a commented configuration file to PATH, or to dmx.toml in
+$XDG_CONFIG_HOME (or ~/.config). An existing file is left alone.";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    let written = match args[..] {
+        ["init-config"] => Dmx::write_default_config(),
+        ["init-config", path] if !path.starts_with('-') => {
+            Dmx::write_default_config_to(path).map(|_| path.into())
+        }
+        ["help" | "-h" | "--help"] => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        _ => {
+            eprintln!("{}", USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    match written {
+        Ok(path) => {
+            println!("Wrote {}", path.display());
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("dmx: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,350 @@
+/*!
+Choosing one of the user's browser bookmarks.
+
+Bookmarks are read from Firefox's `places.sqlite` (with the `sqlite3`
+command line program, so it needs to be installed) and from the
+`Bookmarks` JSON files kept by Chromium and its derivatives. Each is
+displayed as its title, prefixed with the folders it's in, followed by
+its URL.
+
+```no_run
+# use dm_x::{bookmarks, Dmx};
+let dmx = Dmx::default();
+if let Some(url) = bookmarks::pick(&dmx, &bookmarks::all()).unwrap() {
+    dmx.open(url).unwrap();
+}
+```
+*/
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::{Breadcrumbs, Dmx, DmxError, Item, Menu};
+
+/*
+Field and record separators for the output of `sqlite3`, chosen so as
+not to turn up in titles or URLs.
+*/
+const FIELD_SEP: &str = "\x1f";
+const RECORD_SEP: &str = "\x1e";
+
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+const FIREFOX_QUERY: &str =
+    "SELECT b.id, b.parent, b.type, IFNULL(b.title, ''), IFNULL(p.url, '') \
+     FROM moz_bookmarks b LEFT JOIN moz_places p ON b.fk = p.id;";
+
+/**
+A bookmark, from whatever browser.
+*/
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Bookmark {
+    /// The title of the bookmark (which may be empty).
+    pub title: String,
+    /// The URL it points to.
+    pub url: String,
+    /// The names of the folders it's in, outermost first.
+    pub folders: Vec<String>,
+}
+
+impl Bookmark {
+    /*
+    The title, prefixed with the folders, like `Toolbar/News/BBC`.
+    */
+    fn label(&self) -> String {
+        let mut crumbs = Breadcrumbs::new("");
+        for folder in self.folders.iter() {
+            crumbs.push(folder);
+        }
+        let title = if self.title.is_empty() {
+            &self.url
+        } else {
+            &self.title
+        };
+        format!("{}{}", &crumbs, title)
+    }
+}
+
+/**
+Bookmarks display their folders and title, then their URL, lined up like
+the two-tuple implementation.
+*/
+impl Item for Bookmark {
+    fn key_len(&self) -> usize {
+        self.label().chars().count()
+    }
+
+    fn line(&self, key_len: usize) -> Vec<u8> {
+        format!(
+            "{:kwidth$}  {}\n",
+            &self.label(),
+            &self.url,
+            kwidth = key_len
+        )
+        .into_bytes()
+    }
+
+    fn id(&self) -> String {
+        self.url.clone()
+    }
+}
+
+/**
+Read the bookmarks from a Firefox profile's `places.sqlite`.
+
+Firefox keeps this database locked while it's running, so it's copied
+(along with its write-ahead log, which holds recent changes) to a
+temporary directory to be read.
+*/
+pub fn firefox<P: AsRef<Path>>(places: P) -> Result<Vec<Bookmark>, DmxError> {
+    let places = places.as_ref();
+    let tmp = private_dir()?;
+    let result = firefox_copy(places, &tmp);
+    let _ = std::fs::remove_dir_all(&tmp);
+    result
+}
+
+/*
+Create a new temporary directory only the current user can get into (since
+the copy of the history in it is private). One that already exists (that
+someone else might have made, or planted symlinks in) is never used;
+instead, the next name is tried.
+*/
+pub(crate) fn private_dir() -> Result<PathBuf, DmxError> {
+    let mut builder = std::fs::DirBuilder::new();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+
+    loop {
+        let tmp = std::env::temp_dir().join(format!(
+            "dmx-places-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        match builder.create(&tmp) {
+            Ok(()) => return Ok(tmp),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => {
+                return Err(DmxError::io(
+                    format!("Error creating \"{}\"", tmp.display()),
+                    e,
+                ))
+            }
+        }
+    }
+}
+
+/*
+Copy `places` into `tmp`, and read the bookmarks from the copy.
+*/
+fn firefox_copy(places: &Path, tmp: &Path) -> Result<Vec<Bookmark>, DmxError> {
+    let copy = tmp.join("places.sqlite");
+    std::fs::copy(places, &copy)
+        .map_err(|e| DmxError::io(format!("Error copying \"{}\"", places.display()), e))?;
+    let mut wal = places.as_os_str().to_owned();
+    wal.push("-wal");
+    if Path::new(&wal).exists() {
+        std::fs::copy(&wal, tmp.join("places.sqlite-wal")).map_err(|e| {
+            DmxError::io(
+                format!("Error copying \"{}\"", Path::new(&wal).display()),
+                e,
+            )
+        })?;
+    }
+
+    let output = Command::new("sqlite3")
+        .args(["-readonly", "-separator", FIELD_SEP, "-newline", RECORD_SEP])
+        .arg(&copy)
+        .arg(FIREFOX_QUERY)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| DmxError::spawn("sqlite3", e))?;
+    if !output.status.success() {
+        return Err(DmxError::CommandFailed {
+            program: "sqlite3".to_owned(),
+            status: output.status,
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+        });
+    }
+
+    Ok(parse_firefox(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/*
+Turn the rows produced by `FIREFOX_QUERY` into bookmarks.
+*/
+pub(crate) fn parse_firefox(rows: &str) -> Vec<Bookmark> {
+    struct Row<'a> {
+        id: &'a str,
+        parent: &'a str,
+        kind: &'a str,
+        title: &'a str,
+        url: &'a str,
+    }
+
+    let rows: Vec<Row> = rows
+        .split(RECORD_SEP)
+        .filter_map(|r| {
+            let mut fields = r.split(FIELD_SEP);
+            Some(Row {
+                id: fields.next()?,
+                parent: fields.next()?,
+                kind: fields.next()?,
+                title: fields.next()?,
+                url: fields.next()?,
+            })
+        })
+        .collect();
+    let parent_of = |id: &str| rows.iter().find(|r| r.id == id);
+
+    rows.iter()
+        // Type 1 is a bookmark (2 is a folder, 3 a separator); `place:`
+        // URLs are saved searches, like "Recently Bookmarked".
+        .filter(|r| r.kind == "1" && !r.url.is_empty() && !r.url.starts_with("place:"))
+        .map(|r| {
+            let mut folders: Vec<String> = Vec::new();
+            let mut parent = parent_of(r.parent);
+            while let Some(p) = parent {
+                // The root folder (whose parent is 0) has no name.
+                if p.parent == "0" || folders.len() > 64 {
+                    break;
+                }
+                folders.insert(0, p.title.to_owned());
+                parent = parent_of(p.parent);
+            }
+            Bookmark {
+                title: r.title.to_owned(),
+                url: r.url.to_owned(),
+                folders,
+            }
+        })
+        .collect()
+}
+
+/**
+Read the bookmarks from a Chromium (or Chrome, or Brave, &c.) profile's
+`Bookmarks` file.
+*/
+pub fn chromium<P: AsRef<Path>>(bookmarks: P) -> Result<Vec<Bookmark>, DmxError> {
+    let bookmarks = bookmarks.as_ref();
+    let text = std::fs::read(bookmarks)
+        .map_err(|e| DmxError::io(format!("Error reading \"{}\"", bookmarks.display()), e))?;
+    let json: serde_json::Value = serde_json::from_slice(&text).map_err(|e| {
+        DmxError::ConfigParse(format!("Error parsing \"{}\": {}", bookmarks.display(), &e))
+    })?;
+
+    let mut marks: Vec<Bookmark> = Vec::new();
+    if let Some(roots) = json.get("roots").and_then(|r| r.as_object()) {
+        for root in roots.values() {
+            walk_chromium(root, &mut Vec::new(), &mut marks);
+        }
+    }
+    Ok(marks)
+}
+
+/*
+Collect the bookmarks in `node` (and any folders under it) in `marks`.
+*/
+fn walk_chromium(node: &serde_json::Value, folders: &mut Vec<String>, marks: &mut Vec<Bookmark>) {
+    let name = node.get("name").and_then(|n| n.as_str()).unwrap_or("");
+    match node.get("type").and_then(|t| t.as_str()) {
+        Some("url") => {
+            if let Some(url) = node.get("url").and_then(|u| u.as_str()) {
+                marks.push(Bookmark {
+                    title: name.to_owned(),
+                    url: url.to_owned(),
+                    folders: folders.clone(),
+                });
+            }
+        }
+        Some("folder") => {
+            folders.push(name.to_owned());
+            if let Some(children) = node.get("children").and_then(|c| c.as_array()) {
+                for child in children.iter() {
+                    walk_chromium(child, folders, marks);
+                }
+            }
+            folders.pop();
+        }
+        _ => {}
+    }
+}
+
+/*
+The files called `name` in each subdirectory of each of `dirs`.
+*/
+fn profile_files(dirs: &[PathBuf], name: &str) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = dirs
+        .iter()
+        .filter_map(|d| std::fs::read_dir(d).ok())
+        .flatten()
+        .filter_map(|e| e.ok())
+        .map(|e| e.path().join(name))
+        .filter(|p| p.is_file())
+        .collect();
+    files.sort();
+    files
+}
+
+/**
+Return the `places.sqlite` of every Firefox profile in `~/.mozilla/firefox`.
+*/
+pub fn firefox_profiles() -> Vec<PathBuf> {
+    let home = match std::env::var_os("HOME") {
+        Some(home) => PathBuf::from(home),
+        None => return Vec::new(),
+    };
+    profile_files(&[home.join(".mozilla").join("firefox")], "places.sqlite")
+}
+
+/**
+Return the `Bookmarks` file of every profile of Chromium, Google Chrome,
+and Brave in `$XDG_CONFIG_HOME` (or `~/.config`).
+*/
+pub fn chromium_profiles() -> Vec<PathBuf> {
+    use std::env::var_os;
+
+    let config = match var_os("XDG_CONFIG_HOME").filter(|v| !v.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => match var_os("HOME") {
+            Some(home) => PathBuf::from(home).join(".config"),
+            None => return Vec::new(),
+        },
+    };
+    let browsers = ["chromium", "google-chrome", "BraveSoftware/Brave-Browser"];
+    let dirs: Vec<PathBuf> = browsers.iter().map(|b| config.join(b)).collect();
+    profile_files(&dirs, "Bookmarks")
+}
+
+/**
+Return the bookmarks from every Firefox and Chromium profile that can be
+read, skipping duplicate URLs. (Profiles that can't be read are skipped.)
+*/
+pub fn all() -> Vec<Bookmark> {
+    let firefox = firefox_profiles().into_iter().map(firefox);
+    let chromium = chromium_profiles().into_iter().map(chromium);
+
+    let mut marks: Vec<Bookmark> = Vec::new();
+    for mark in firefox.chain(chromium).filter_map(|r| r.ok()).flatten() {
+        if !marks.iter().any(|m| m.url == mark.url) {
+            marks.push(mark);
+        }
+    }
+    marks
+}
+
+/**
+Let the user choose one of `marks`, and return its URL (or `None` if
+the menu is cancelled). `Dmx::open()` will open it in the default
+browser.
+*/
+pub fn pick(dmx: &Dmx, marks: &[Bookmark]) -> Result<Option<String>, DmxError> {
+    Ok(Menu::new("bookmark:")
+        .items(marks)
+        .show(dmx)?
+        .map(|n| marks[n].url.clone()))
+}
@@ -0,0 +1,175 @@
+/*!
+Building prompts that show where the user is in a hierarchy of menus.
+*/
+
+use std::fmt::{Display, Formatter};
+
+/**
+A prompt made of a fixed root followed by a stack of "crumbs", each
+followed by a separator, like `launch:ssh/work/`.
+
+The nested-menu functions in the `menu` module use this to generate
+their prompts, but it's just as useful for programs that manage their
+own hierarchies.
+
+```
+# use dm_x::Breadcrumbs;
+let mut crumbs = Breadcrumbs::new("launch:").separator(" > ").max_width(14);
+crumbs.push("ssh");
+assert_eq!(&crumbs.to_string(), "launch:ssh > ");
+crumbs.push("work");
+assert_eq!(&crumbs.to_string(), "…ssh > work > ");
+crumbs.pop();
+assert_eq!(&crumbs.to_string(), "launch:ssh > ");
+```
+
+With a template, the path can go anywhere in the prompt, alongside the
+name of the current menu and (once it's shown by a `Menu` with
+`Menu::template(true)`, as `navigate()` does) the number of items in it:
+
+```
+# use dm_x::Breadcrumbs;
+let mut crumbs = Breadcrumbs::new("launch:").template("{title} ({count}) {path}");
+crumbs.push("ssh");
+assert_eq!(&crumbs.to_string(), "ssh ({count}) launch:ssh/");
+```
+*/
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Breadcrumbs {
+    root: String,
+    crumbs: Vec<String>,
+    separator: String,
+    max_width: Option<usize>,
+    template: Option<String>,
+}
+
+impl Breadcrumbs {
+    /**
+    Start with just the given root, no crumbs, a separator of `/`, and
+    no maximum width.
+    */
+    pub fn new<S: AsRef<str>>(root: S) -> Breadcrumbs {
+        Breadcrumbs {
+            root: root.as_ref().to_owned(),
+            crumbs: Vec::new(),
+            separator: "/".to_owned(),
+            max_width: None,
+            template: None,
+        }
+    }
+
+    /**
+    Set the string that follows each crumb.
+    */
+    pub fn separator<S: AsRef<str>>(mut self, separator: S) -> Breadcrumbs {
+        self.separator = separator.as_ref().to_owned();
+        self
+    }
+
+    /**
+    Set the maximum width (in `char`s) of the generated prompt. Longer
+    prompts will have characters removed from the beginning (and be
+    prefixed with `…`), so that the deepest crumbs stay visible.
+    */
+    pub fn max_width(mut self, width: usize) -> Breadcrumbs {
+        self.max_width = Some(width);
+        self
+    }
+
+    /**
+    Lay out the prompt according to `template`, in which `{path}` is
+    replaced with the root and crumbs (as they'd be shown without a
+    template, so limited to the maximum width), and `{title}` with the
+    last crumb (or the root, at the top). Other placeholders, like
+    `{count}`, are left for `Menu` to fill in (see `Menu::template()`).
+    */
+    pub fn template<S: AsRef<str>>(mut self, template: S) -> Breadcrumbs {
+        self.template = Some(template.as_ref().to_owned());
+        self
+    }
+
+    /*
+    Whether the prompt is laid out by a template (and so may have
+    placeholders left in it for `Menu` to fill in).
+    */
+    pub(crate) fn is_template(&self) -> bool {
+        self.template.is_some()
+    }
+
+    /**
+    Add a crumb to the end.
+    */
+    pub fn push<S: AsRef<str>>(&mut self, crumb: S) {
+        self.crumbs.push(crumb.as_ref().to_owned());
+    }
+
+    /**
+    Remove and return the last crumb.
+    */
+    pub fn pop(&mut self) -> Option<String> {
+        self.crumbs.pop()
+    }
+
+    /**
+    Return the current crumbs, not including the root.
+    */
+    pub fn crumbs(&self) -> &[String] {
+        &self.crumbs
+    }
+}
+
+impl Display for Breadcrumbs {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut full = self.root.clone();
+        for crumb in self.crumbs.iter() {
+            full.push_str(crumb);
+            full.push_str(&self.separator);
+        }
+
+        let path = match self.max_width {
+            Some(width) if full.chars().count() > width => {
+                let skip = full.chars().count() + 1 - width;
+                let tail: String = full.chars().skip(skip).collect();
+                format!("…{}", &tail)
+            }
+            _ => full,
+        };
+
+        let template = match &self.template {
+            Some(template) => template,
+            None => return write!(f, "{}", &path),
+        };
+        let title = self.crumbs.last().unwrap_or(&self.root);
+        // `{{` is passed through as is, so that it's still escaped for
+        // the menu that expands the rest.
+        let mut rest = template.as_str();
+        while let Some(n) = rest.find('{') {
+            f.write_str(&rest[..n])?;
+            rest = &rest[n..];
+            let (text, len) = if rest.starts_with("{{") {
+                ("{{", 2)
+            } else if rest.starts_with("{path}") {
+                (path.as_str(), "{path}".len())
+            } else if rest.starts_with("{title}") {
+                (title.as_str(), "{title}".len())
+            } else {
+                ("{", 1)
+            };
+            f.write_str(text)?;
+            rest = &rest[len..];
+        }
+        f.write_str(rest)
+    }
+}
+
+impl From<&str> for Breadcrumbs {
+    fn from(root: &str) -> Breadcrumbs {
+        Breadcrumbs::new(root)
+    }
+}
+
+impl From<String> for Breadcrumbs {
+    fn from(root: String) -> Breadcrumbs {
+        Breadcrumbs::new(root)
+    }
+}
@@ -0,0 +1,154 @@
+/*!
+Building a `Dmx` one setting at a time, with the settings checked before
+it's used.
+*/
+
+use std::path::PathBuf;
+
+use crate::{Backend, Dmx, DmxError, Font};
+
+/**
+Builds a `Dmx`, starting from `Dmx::default()`, and checks the settings
+given before handing it over, rather than leaving a typo in a color to
+be discovered when `dmenu` refuses to start.
+
+```
+# use dm_x::DmxBuilder;
+let dmx = DmxBuilder::new()
+    .font("Terminus-12")
+    .colors("#222", "#aaa", "#888", "#aff")
+    .lines(20)
+    .build()
+    .unwrap();
+assert_eq!(dmx.max_lines, Some(20));
+
+assert!(DmxBuilder::new().colors("#222", "#aaa", "#88", "#aff").build().is_err());
+```
+*/
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DmxBuilder {
+    dmx: Dmx,
+    // The first color given that couldn't be parsed.
+    bad_color: Option<String>,
+}
+
+impl DmxBuilder {
+    /**
+    Start from the default settings.
+    */
+    pub fn new() -> DmxBuilder {
+        DmxBuilder::default()
+    }
+
+    /**
+    Set the path to the `dmenu` binary (see `Dmx::dmenu`).
+    */
+    pub fn dmenu_path<P: Into<PathBuf>>(mut self, path: P) -> DmxBuilder {
+        self.dmx.dmenu = path.into();
+        self
+    }
+
+    /**
+    Set the font (see `Dmx::font`).
+    */
+    pub fn font<S: AsRef<str>>(mut self, font: S) -> DmxBuilder {
+        self.dmx.font = Font::new(font.as_ref());
+        self
+    }
+
+    /**
+    Set the normal background and foreground colors, then the selected
+    item's background and foreground colors (see `Color` for what they
+    can be).
+    */
+    pub fn colors<S: AsRef<str>>(
+        mut self,
+        normal_bg: S,
+        normal_fg: S,
+        select_bg: S,
+        select_fg: S,
+    ) -> DmxBuilder {
+        let slots = [
+            (&mut self.dmx.normal_bg, normal_bg),
+            (&mut self.dmx.normal_fg, normal_fg),
+            (&mut self.dmx.select_bg, select_bg),
+            (&mut self.dmx.select_fg, select_fg),
+        ];
+        for (slot, text) in slots {
+            match text.as_ref().parse() {
+                Ok(color) => *slot = color,
+                Err(_) => {
+                    self.bad_color
+                        .get_or_insert_with(|| text.as_ref().to_owned());
+                }
+            }
+        }
+        self
+    }
+
+    /**
+    Set the most lines any menu will show (see `Dmx::max_lines`).
+    */
+    pub fn lines(mut self, n: usize) -> DmxBuilder {
+        self.dmx.max_lines = Some(n);
+        self
+    }
+
+    /**
+    Make every menu as tall as the most lines set with `lines()` (see
+    `Dmx::fixed_lines`).
+    */
+    pub fn fixed_lines(mut self, fixed: bool) -> DmxBuilder {
+        self.dmx.fixed_lines = fixed;
+        self
+    }
+
+    /**
+    Set the menu programs to try, in order (see `Dmx::backends`).
+    */
+    pub fn backends<B: Into<Vec<Backend>>>(mut self, backends: B) -> DmxBuilder {
+        self.dmx.backends = backends.into();
+        self
+    }
+
+    /**
+    Turn fuzzy matching on or off (see `Dmx::fuzzy`).
+    */
+    pub fn fuzzy(mut self, fuzzy: bool) -> DmxBuilder {
+        self.dmx.fuzzy = fuzzy;
+        self
+    }
+
+    /**
+    Check the settings, and return the `Dmx` if they're all usable.
+    */
+    pub fn build(self) -> Result<Dmx, DmxError> {
+        if let Some(color) = self.bad_color {
+            return Err(DmxError::ConfigParse(format!(
+                "Invalid color \"{}\".",
+                color
+            )));
+        }
+        let dmx = self.dmx;
+        if dmx.dmenu.as_os_str().is_empty() {
+            return Err(DmxError::ConfigParse(
+                "The dmenu path must not be empty.".to_owned(),
+            ));
+        }
+        let font = dmx.font.name();
+        if font.trim().is_empty() || font.contains('\n') {
+            return Err(DmxError::ConfigParse(format!("Invalid font \"{}\".", font)));
+        }
+        if dmx.max_lines == Some(0) {
+            return Err(DmxError::ConfigParse(
+                "The number of lines must be at least 1.".to_owned(),
+            ));
+        }
+        if dmx.backends.is_empty() && dmx.command.is_none() {
+            return Err(DmxError::ConfigParse(
+                "At least one backend is needed.".to_owned(),
+            ));
+        }
+        Ok(dmx)
+    }
+}
@@ -0,0 +1,113 @@
+/*!
+Putting text on the clipboard, like whatever was chosen from a menu.
+
+This uses `wl-copy` in a Wayland session, and `xclip` (or `xsel`) under
+X11; one of them has to be installed.
+
+```no_run
+# use dm_x::Dmx;
+let emoji = &["🐸", "🐈", "🐀"];
+Dmx::default().select_and_copy("emoji:", emoji).unwrap();
+```
+*/
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use crate::{Dmx, DmxError, Item};
+
+/*
+Whether this is a Wayland session.
+*/
+pub(crate) fn wayland() -> bool {
+    std::env::var_os("WAYLAND_DISPLAY")
+        .filter(|v| !v.is_empty())
+        .is_some()
+}
+
+/*
+Run the first of `programs` that's installed, writing `text` to its
+standard input.
+*/
+pub(crate) fn pipe_to(programs: &[&[&str]], text: &str) -> Result<(), DmxError> {
+    for argv in programs.iter() {
+        // Clipboard programs hang around to serve the selection, so don't
+        // wait on any of their output.
+        let mut child = match Command::new(argv[0])
+            .args(&argv[1..])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+        {
+            Ok(child) => child,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(DmxError::spawn(argv[0], e)),
+        };
+
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin
+                .write_all(text.as_bytes())
+                .map_err(|e| DmxError::io(format!("Error writing to {}", argv[0]), e))?;
+        }
+        let status = child
+            .wait()
+            .map_err(|e| DmxError::io(format!("Error waiting for {}", argv[0]), e))?;
+        if !status.success() {
+            return Err(DmxError::CommandFailed {
+                program: argv[0].to_owned(),
+                status,
+                stderr: String::new(),
+            });
+        }
+        return Ok(());
+    }
+
+    Err(DmxError::NoneInstalled(
+        programs.iter().map(|p| p[0].to_owned()).collect(),
+    ))
+}
+
+/**
+Put `text` on the clipboard.
+*/
+pub fn copy(text: &str) -> Result<(), DmxError> {
+    let wl_copy: &[&str] = &["wl-copy"];
+    let xclip: &[&str] = &["xclip", "-selection", "clipboard"];
+    let xsel: &[&str] = &["xsel", "--clipboard", "--input"];
+
+    if wayland() {
+        pipe_to(&[wl_copy, xclip, xsel], text)
+    } else {
+        pipe_to(&[xclip, xsel, wl_copy], text)
+    }
+}
+
+/*
+The text of `item`'s line, as it's shown in the menu.
+*/
+pub(crate) fn line_text<I: Item>(item: &I, key_len: usize) -> String {
+    let line = item.line(key_len);
+    String::from_utf8_lossy(line.strip_suffix(b"\n").unwrap_or(&line)).into_owned()
+}
+
+impl Dmx {
+    /**
+    Let the user choose one of `items`, and put the chosen item's line (as
+    it was shown in the menu) on the clipboard. Returns the index of the
+    item chosen (or `None` if the menu was cancelled).
+    */
+    pub fn select_and_copy<S, I>(&self, prompt: S, items: &[I]) -> Result<Option<usize>, DmxError>
+    where
+        S: AsRef<str>,
+        I: Item,
+    {
+        let n = match self.select(prompt, items)? {
+            Some(n) => n,
+            None => return Ok(None),
+        };
+        let key_len = items.iter().map(|x| x.key_len()).max().unwrap_or(0);
+        copy(&line_text(&items[n], key_len))?;
+        Ok(Some(n))
+    }
+}
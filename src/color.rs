@@ -0,0 +1,97 @@
+/*!
+Colors for the menu, checked when they're set rather than when `dmenu`
+refuses to start.
+*/
+
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+#[cfg(feature = "config")]
+use serde::{Deserialize, Serialize};
+
+use crate::DmxError;
+
+/**
+A color, as `dmenu` (and X11) understands them: `#` and three, six, or
+twelve hex digits, or the name of a color, like `SteelBlue` or `gray50`.
+
+```
+# use dm_x::Color;
+let teal: Color = "#088".parse().unwrap();
+assert_eq!(teal, Color::Rgb(0x00, 0x88, 0x88));
+assert_eq!(&teal.to_string(), "#008888");
+assert_eq!(&"gray50".parse::<Color>().unwrap().to_string(), "gray50");
+assert!("#0888".parse::<Color>().is_err());
+```
+
+Names are only checked for looking like names (letters, digits, and
+spaces); which ones exist is up to the X server.
+*/
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "config", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "config", serde(try_from = "String", into = "String"))]
+pub enum Color {
+    /// A color given by its red, green, and blue components.
+    Rgb(u8, u8, u8),
+    /// A color given by name.
+    Named(String),
+}
+
+impl Display for Color {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Color::Rgb(r, g, b) => write!(f, "#{:02x}{:02x}{:02x}", r, g, b),
+            Color::Named(name) => f.write_str(name),
+        }
+    }
+}
+
+impl FromStr for Color {
+    type Err = DmxError;
+
+    fn from_str(s: &str) -> Result<Color, DmxError> {
+        let invalid = || DmxError::ConfigParse(format!("Invalid color \"{}\".", s));
+
+        let digits = match s.strip_prefix('#') {
+            Some(digits) => digits,
+            None if !s.trim().is_empty()
+                && s.chars().all(|c| c.is_ascii_alphanumeric() || c == ' ') =>
+            {
+                return Ok(Color::Named(s.to_owned()));
+            }
+            None => return Err(invalid()),
+        };
+        if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(invalid());
+        }
+        // Each component is the same number of digits, of which only the
+        // first two matter (and a single digit is doubled).
+        let width = match digits.len() {
+            3 | 6 | 12 => digits.len() / 3,
+            _ => return Err(invalid()),
+        };
+        let component = |n: usize| {
+            let digits = &digits[n * width..(n + 1) * width];
+            let byte = match width {
+                1 => digits.repeat(2),
+                _ => digits[..2].to_owned(),
+            };
+            u8::from_str_radix(&byte, 16).map_err(|_| invalid())
+        };
+        Ok(Color::Rgb(component(0)?, component(1)?, component(2)?))
+    }
+}
+
+impl TryFrom<String> for Color {
+    type Error = DmxError;
+
+    fn try_from(s: String) -> Result<Color, DmxError> {
+        s.parse()
+    }
+}
+
+impl From<Color> for String {
+    fn from(color: Color) -> String {
+        color.to_string()
+    }
+}
@@ -6,28 +6,567 @@ The use case for this feature is a user having several programs that use
 `dmenu` in all of those programs to be configured with a single
 configuration file.
 */
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 
-use serde::{Deserialize};
+use serde::de::{self, Visitor};
+use serde::Deserialize;
+use toml::value::{Table, Value};
+
+use crate::{
+    Backend, BackendOverrides, Color, DmxError, Encoding, FlagMap, Font, RetryPolicy, StderrMode,
+};
+
+/*
+How many files deep `include`s can go, so that files including each
+other are caught.
+*/
+pub const MAX_INCLUDE_DEPTH: usize = 16;
+
+/*
+Keys whose values are lists, which in the INI format are separated by
+commas.
+*/
+const LIST_KEYS: &[&str] = &[
+    "extra_args",
+    "command",
+    "backends",
+    "env_allowlist",
+    "env_remove",
+    "args",
+];
+
+/*
+The names of the built-in backends, which `[backend.<name>]` sections can
+be named after.
+*/
+const BACKENDS: &[&str] = &[
+    "dmenu", "dmenu-wl", "bemenu", "rofi", "fuzzel", "wofi", "fzf", "zenity", "yad", "kdialog",
+    "choose", "numbered",
+];
+
+/*
+Sections of the INI format whose values are always strings.
+*/
+const STRING_SECTIONS: &[&str] = &["env", "flag_map"];
 
 #[derive(Deserialize)]
 pub struct ConfigFile {
+    pub include: Option<PathBuf>,
+    pub app: Option<BTreeMap<String, ConfigFile>>,
     pub dmenu: Option<PathBuf>,
-    pub font: Option<String>,
-    pub normal_bg: Option<String>,
-    pub normal_fg: Option<String>,
-    pub select_bg: Option<String>,
-    pub select_fg: Option<String>,
+    pub font: Option<Font>,
+    pub normal_bg: Option<Color>,
+    pub normal_fg: Option<Color>,
+    pub select_bg: Option<Color>,
+    pub select_fg: Option<Color>,
+    pub max_lines: Option<usize>,
+    pub fixed_lines: Option<bool>,
+    pub center: Option<bool>,
+    pub border_width: Option<u32>,
+    pub width: Option<u32>,
+    pub columns: Option<u32>,
+    pub embed_window: Option<u64>,
+    pub monitor: Option<u32>,
+    pub extra_args: Option<Vec<String>>,
+    pub command: Option<Vec<String>>,
+    pub backends: Option<Vec<Backend>>,
+    pub fuzzy: Option<bool>,
+    pub fuzzy_required: Option<bool>,
+    pub encoding: Option<Encoding>,
+    pub env_allowlist: Option<Vec<String>>,
+    pub env: Option<BTreeMap<String, String>>,
+    pub env_remove: Option<Vec<String>>,
+    pub spool_threshold: Option<usize>,
+    pub stderr: Option<StderrMode>,
+    pub retry: Option<RetryPolicy>,
+    pub flag_map: Option<FlagMap>,
+    pub pin_binary: Option<bool>,
+    pub check_font: Option<bool>,
+    pub audit_log: Option<PathBuf>,
+    pub notify_errors: Option<bool>,
+    pub strict: Option<bool>,
+    /*
+    Each backend's overrides, in its `[backend.<name>]` section.
+    */
+    pub backend: Option<BTreeMap<String, BackendOverrides>>,
+    /*
+    The keys that were ignored because they don't mean anything (as the
+    path of keys leading to each), for `strict` to complain about.
+    */
+    #[serde(skip)]
+    pub ignored: Vec<Vec<String>>,
 }
 
 impl ConfigFile {
-    pub fn from<S>(s: S) -> Result<ConfigFile, String>
+    pub fn from<S>(s: S) -> Result<ConfigFile, DmxError>
     where
         S: AsRef<[u8]>,
     {
         let s = s.as_ref();
-        let cfgfile = toml::from_slice(s)
-            .map_err(|e| format!("Error deserializing Dmx config: {}", &e))?;
+        // No TOML document starts with `{`, so one that does is JSON.
+        if s.iter().find(|b| !b.is_ascii_whitespace()) == Some(&b'{') {
+            return ConfigFile::from_json(s);
+        }
+        let error = |e: &dyn std::fmt::Display| {
+            DmxError::ConfigParse(format!("Error deserializing Dmx config: {}", e))
+        };
+        let text = std::str::from_utf8(s).map_err(|e| error(&e))?;
+        ConfigFile::deserialize_noting(&mut toml::Deserializer::new(text)).map_err(|e| error(&e))
+    }
+
+    pub fn from_json(s: &[u8]) -> Result<ConfigFile, DmxError> {
+        let error = |e: serde_json::Error| {
+            DmxError::ConfigParse(format!("Error deserializing Dmx config: {}", &e))
+        };
+        let mut de = serde_json::Deserializer::from_slice(s);
+        let cfgfile = ConfigFile::deserialize_noting(&mut de).map_err(error)?;
+        de.end().map_err(error)?;
         Ok(cfgfile)
     }
-}
\ No newline at end of file
+
+    /*
+    Deserialize a `ConfigFile` from `de`, noting the keys it ignores in
+    `ignored`.
+    */
+    fn deserialize_noting<'de, D>(de: D) -> Result<ConfigFile, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let mut ignored = Vec::new();
+        let mut cfgfile: ConfigFile =
+            serde_ignored::deserialize(de, |path| ignored.push(key_path(&path)))?;
+        cfgfile.ignored = ignored;
+        Ok(cfgfile)
+    }
+
+    /*
+    Parse the INI format: `key = value` lines, with `[section]` headers for
+    the keys that are tables in TOML, and `#` or `;` starting comments.
+    Values are unquoted, and taken to be booleans or numbers if they look
+    like them (unless they're in quotes), or lists for the keys that are
+    lists.
+    */
+    pub fn from_ini(s: &[u8]) -> Result<ConfigFile, DmxError> {
+        let text = std::str::from_utf8(s).map_err(|e| {
+            DmxError::ConfigParse(format!("Error deserializing Dmx config: {}", &e))
+        })?;
+
+        let mut root = Table::new();
+        let mut section: Option<&str> = None;
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(['#', ';']) {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = Some(name.trim());
+                continue;
+            }
+            let (key, value) = line.split_once('=').ok_or_else(|| {
+                DmxError::ConfigParse(format!(
+                    "Error deserializing Dmx config: line {} isn't `key = value`.",
+                    n + 1
+                ))
+            })?;
+            let (key, value) = (key.trim(), value.trim());
+
+            let (table, value) = match section {
+                None if LIST_KEYS.contains(&key) => (&mut root, ini_list(value)),
+                None => (&mut root, ini_value(value)),
+                Some(name) => {
+                    // A section like `[app.emoji]` is a table in a table.
+                    let last = name.rsplit('.').next().unwrap_or(name);
+                    let value = match STRING_SECTIONS.contains(&last) {
+                        true => Value::String(unquote(value).to_owned()),
+                        false if LIST_KEYS.contains(&key) => ini_list(value),
+                        false => ini_value(value),
+                    };
+                    let mut table = &mut root;
+                    for part in name.split('.').map(str::trim) {
+                        table = match table
+                            .entry(part.to_owned())
+                            .or_insert_with(|| Value::Table(Table::new()))
+                        {
+                            Value::Table(table) => table,
+                            _ => {
+                                return Err(DmxError::ConfigParse(format!(
+                                    "Error deserializing Dmx config: [{}] is already a value.",
+                                    name
+                                )))
+                            }
+                        };
+                    }
+                    (table, value)
+                }
+            };
+            table.insert(key.to_owned(), value);
+        }
+
+        ConfigFile::deserialize_noting(Value::Table(root))
+            .map_err(|e| DmxError::ConfigParse(format!("Error deserializing Dmx config: {}", &e)))
+    }
+
+    #[cfg(feature = "yaml")]
+    pub fn from_yaml(s: &[u8]) -> Result<ConfigFile, DmxError> {
+        // An empty document is `null`, rather than a mapping with no keys.
+        if s.iter().all(|b| b.is_ascii_whitespace()) {
+            return ConfigFile::from(s);
+        }
+        ConfigFile::deserialize_noting(serde_yaml::Deserializer::from_slice(s))
+            .map_err(|e| DmxError::ConfigParse(format!("Error deserializing Dmx config: {}", &e)))
+    }
+
+    /*
+    If the configuration sets `strict`, an error for every key in it that
+    doesn't mean anything (and so would otherwise be ignored), saying
+    where it is in `text` (the file it was read from), and what was meant
+    if that's a good guess. A `theme` for any backend but rofi doesn't
+    mean anything either.
+    */
+    pub fn check_strict(&self, text: &[u8]) -> Result<(), DmxError> {
+        if self.strict != Some(true) {
+            return Ok(());
+        }
+        let mut programs: Vec<&str> = match &self.command {
+            Some(argv) => argv.iter().take(1).map(String::as_str).collect(),
+            None => Vec::new(),
+        };
+        for backend in self.backends.iter().flatten() {
+            if let Backend::Command(_) = backend {
+                programs.push(backend.name());
+            }
+        }
+        let mut unknown: Vec<(String, &'static [&'static str])> = self
+            .ignored
+            .iter()
+            .map(|path| (path.join("."), known_keys(path)))
+            .collect();
+        let mut themes = Vec::new();
+        self.unknown_backends("", &programs, &mut unknown, &mut themes);
+        if unknown.is_empty() && themes.is_empty() {
+            return Ok(());
+        }
+
+        let text = String::from_utf8_lossy(text);
+        let at = |key: &str| {
+            let keys: Vec<&str> = key.split('.').collect();
+            match locate(&text, &keys) {
+                Some((line, column)) => format!(" at line {}, column {}", line, column),
+                None => String::new(),
+            }
+        };
+        let mut messages: Vec<String> = unknown
+            .iter()
+            .map(|(key, known)| {
+                let name = key.rsplit('.').next().unwrap_or(key);
+                let mut message = format!("Unknown key `{}`{}", key, at(key));
+                match suggest(name, known) {
+                    Some(meant) => message.push_str(&format!("; did you mean `{}`?", meant)),
+                    None => message.push('.'),
+                }
+                message
+            })
+            .collect();
+        for key in themes.iter() {
+            messages.push(format!(
+                "`{}`{} only means anything for rofi.",
+                key,
+                at(key)
+            ));
+        }
+        Err(DmxError::ConfigParse(messages.join("\n")))
+    }
+
+    /*
+    Add the `[backend.<name>]` sections in this configuration (whose own
+    keys start with `prefix`) that aren't named after a built-in backend
+    or one of the `programs` of `Backend::Command`s to `unknown`, and the
+    `theme` keys in those for backends other than rofi to `themes`.
+    */
+    fn unknown_backends(
+        &self,
+        prefix: &str,
+        programs: &[&str],
+        unknown: &mut Vec<(String, &'static [&'static str])>,
+        themes: &mut Vec<String>,
+    ) {
+        for (name, overrides) in self.backend.iter().flatten() {
+            let key = format!("{}backend.{}", prefix, name);
+            if !BACKENDS.contains(&name.as_str()) && !programs.contains(&name.as_str()) {
+                unknown.push((key, BACKENDS));
+            } else if overrides.theme.is_some() && name != "rofi" {
+                themes.push(format!("{}.theme", key));
+            }
+        }
+        for (name, app) in self.app.iter().flatten() {
+            app.unknown_backends(
+                &format!("{}app.{}.", prefix, name),
+                programs,
+                unknown,
+                themes,
+            );
+        }
+    }
+}
+
+/*
+The keys, in order, leading to the value at `path`.
+*/
+fn key_path(path: &serde_ignored::Path) -> Vec<String> {
+    use serde_ignored::Path;
+
+    match path {
+        Path::Root => Vec::new(),
+        Path::Seq { parent, index } => {
+            let mut keys = key_path(parent);
+            keys.push(index.to_string());
+            keys
+        }
+        Path::Map { parent, key } => {
+            let mut keys = key_path(parent);
+            keys.push(key.clone());
+            keys
+        }
+        Path::Some { parent }
+        | Path::NewtypeStruct { parent }
+        | Path::NewtypeVariant { parent } => key_path(parent),
+    }
+}
+
+/*
+The keys that could have been meant by the last of the keys in `path`.
+*/
+fn known_keys(path: &[String]) -> &'static [&'static str] {
+    match path {
+        [_] => field_names::<ConfigFile>(),
+        [app, _, rest @ ..] if app == "app" && !rest.is_empty() => known_keys(rest),
+        [backend, _, _] if backend == "backend" => field_names::<BackendOverrides>(),
+        [retry, _] if retry == "retry" => field_names::<RetryPolicy>(),
+        [flag_map, _] if flag_map == "flag_map" => field_names::<FlagMap>(),
+        _ => &[],
+    }
+}
+
+/*
+The names of the fields of the struct `T`, as its `Deserialize` impl
+gives them to a deserializer.
+*/
+fn field_names<'de, T: Deserialize<'de>>() -> &'static [&'static str] {
+    let mut fields: &'static [&'static str] = &[];
+    let _ = T::deserialize(FieldNames(&mut fields));
+    fields
+}
+
+/*
+A deserializer that has nothing to give, but notes the names of the
+fields it's asked for.
+*/
+struct FieldNames<'a>(&'a mut &'static [&'static str]);
+
+impl<'de, 'a> de::Deserializer<'de> for FieldNames<'a> {
+    type Error = de::value::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _: V) -> Result<V::Value, Self::Error> {
+        Err(de::Error::custom("only field names are wanted"))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        *self.0 = fields;
+        self.deserialize_any(visitor)
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes
+        byte_buf option unit unit_struct newtype_struct seq tuple tuple_struct map
+        enum identifier ignored_any
+    }
+}
+
+/*
+The line and column (counting from 1) where the last of `keys` is first
+used as a key in `text`, after each of the ones before it (the tables it's
+in): `key = ...` or `[key]` (TOML and INI), `key: ...` (YAML), or
+`"key": ...` (JSON). Keys that are indices into lists are skipped.
+*/
+fn locate(text: &str, keys: &[&str]) -> Option<(usize, usize)> {
+    let lines: Vec<&str> = text.lines().collect();
+    // Where the search for the next key starts: a line, and a byte in it.
+    let (mut line, mut from) = (0, 0);
+    let mut found = None;
+    for key in keys
+        .iter()
+        .filter(|k| !k.bytes().all(|b| b.is_ascii_digit()))
+    {
+        let (n, i) = (line..lines.len()).find_map(|n| {
+            let start = if n == line { from } else { 0 };
+            lines[n].match_indices(key).find_map(|(i, _)| {
+                let text = lines[n];
+                let before = text[..i].trim_end_matches(['"', '\'']).trim_start();
+                let after = text[i + key.len()..]
+                    .trim_start_matches(['"', '\''])
+                    .trim_start();
+                let is_key = (before.is_empty() || before.ends_with(['[', '.', '{', ',']))
+                    && after.starts_with(['=', ':', ']', '.']);
+                (i >= start && is_key).then_some((n, i))
+            })
+        })?;
+        (line, from) = (n, i + key.len());
+        found = Some((n + 1, lines[n][..i].chars().count() + 1));
+    }
+    found
+}
+
+/*
+The one of the `known` keys that `key` is most likely a misspelling of,
+if any is close enough.
+*/
+fn suggest<'a>(key: &str, known: &[&'a str]) -> Option<&'a str> {
+    known
+        .iter()
+        .map(|k| (distance(key, k), *k))
+        .filter(|(d, k)| *d <= 2.max(k.len() / 3))
+        .min_by_key(|(d, _)| *d)
+        .map(|(_, k)| k)
+}
+
+/*
+The edit distance between `a` and `b`: how many characters have to be
+inserted, removed, or changed to make one into the other.
+*/
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitute = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitute.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/*
+A path from a configuration file, with a leading `~` replaced by the
+home directory, and `$VAR` or `${VAR}` by the value of `VAR`.
+*/
+pub(crate) fn expand_path(path: &Path) -> Result<PathBuf, DmxError> {
+    expand_path_with(path, |var| std::env::var(var).ok())
+}
+
+/*
+`expand_path()`, with variables' values given by `lookup`.
+*/
+pub(crate) fn expand_path_with<F>(path: &Path, lookup: F) -> Result<PathBuf, DmxError>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    // A path that isn't UTF-8 can't have come from a config file.
+    let text = match path.to_str() {
+        Some(text) => text,
+        None => return Ok(path.to_owned()),
+    };
+    let value = |var: &str| {
+        lookup(var).ok_or_else(|| {
+            DmxError::ConfigParse(format!(
+                "Unable to expand \"{}\": ${} is not set.",
+                text, var
+            ))
+        })
+    };
+
+    let mut expanded = String::with_capacity(text.len());
+    let mut rest = text;
+    if rest == "~" || rest.starts_with("~/") {
+        expanded.push_str(&value("HOME")?);
+        rest = &rest[1..];
+    }
+    while let Some(n) = rest.find('$') {
+        expanded.push_str(&rest[..n]);
+        let after = &rest[n + 1..];
+        let (var, tail) = match after.strip_prefix('{') {
+            Some(braced) => braced.split_once('}').ok_or_else(|| {
+                DmxError::ConfigParse(format!("Unable to expand \"{}\": missing '}}'.", text))
+            })?,
+            None => {
+                let end = after
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(after.len());
+                after.split_at(end)
+            }
+        };
+        // A `$` that doesn't start a name is just a `$`.
+        if var.is_empty() {
+            expanded.push('$');
+            rest = after;
+            continue;
+        }
+        expanded.push_str(&value(var)?);
+        rest = tail;
+    }
+    expanded.push_str(rest);
+    Ok(PathBuf::from(expanded))
+}
+
+/*
+`value` without the double quotes around it, if it has them.
+*/
+fn unquote(value: &str) -> &str {
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(value)
+}
+
+/*
+The value an INI `value` stands for.
+*/
+fn ini_value(value: &str) -> Value {
+    if unquote(value) != value {
+        return Value::String(unquote(value).to_owned());
+    }
+    match value {
+        "true" => Value::Boolean(true),
+        "false" => Value::Boolean(false),
+        _ => match value.parse::<i64>() {
+            Ok(n) => Value::Integer(n),
+            Err(_) => Value::String(value.to_owned()),
+        },
+    }
+}
+
+/*
+The list an INI `value` stands for: its items are separated by commas,
+except those inside double quotes.
+*/
+fn ini_list(value: &str) -> Value {
+    let mut items: Vec<&str> = Vec::new();
+    let (mut start, mut quoted) = (0, false);
+    for (i, c) in value.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ',' if !quoted => {
+                items.push(&value[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    items.push(&value[start..]);
+    Value::Array(
+        items
+            .into_iter()
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(|v| Value::String(unquote(v).to_owned()))
+            .collect(),
+    )
+}
@@ -0,0 +1,598 @@
+/*!
+Finding the applications installed on the system, by reading their
+[desktop entries](https://specifications.freedesktop.org/desktop-entry-spec/latest/)
+(the `.desktop` files in the `applications` subdirectory of each XDG data
+directory).
+
+There tend to be hundreds of these, and parsing them all every time a
+launcher starts is noticeably slow, so `entries()` keeps an index of them
+in `$XDG_CACHE_HOME/dm_x/`, along with the modification times of the
+directories they came from. The index is rebuilt whenever a file is added
+to or removed from one of those directories (which is how package managers
+install and update things). A desktop entry edited in place doesn't change
+its directory's modification time, though; call `rebuild()` to pick up
+changes like that.
+
+Names are localized according to `current_locale()`, so a launcher shows
+"Dateien" instead of "Files" on a German system.
+
+```no_run
+# use dm_x::{desktop, Dmx};
+let apps: Vec<_> = desktop::entries()
+    .into_iter()
+    .filter(|e| !e.no_display)
+    .collect();
+
+let dmx = Dmx::default();
+if let Some(n) = dmx.select("run:", &apps).unwrap() {
+    apps[n].launch(&dmx).unwrap();
+}
+```
+*/
+
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::store::{cache_file, dir_stamp, escape_field, read_lines, unescape_field, write_lines};
+use crate::{Dmx, DmxError, Item};
+
+/*
+The first line of the cache file; a cache file that doesn't start with
+this is from some other version of this module, and is ignored.
+*/
+const CACHE_HEADER: &str = "dm_x desktop entries 2";
+
+/**
+An application, as described by its desktop entry.
+*/
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DesktopEntry {
+    /// The desktop file ID (like `org.gnome.Nautilus.desktop`), which
+    /// identifies the application no matter which directory it's in.
+    pub id: String,
+    /// The `.desktop` file this was read from.
+    pub path: PathBuf,
+    /// The name of the application, like "Firefox".
+    pub name: String,
+    /// What sort of application it is, like "Web Browser".
+    pub generic_name: Option<String>,
+    /// The command line to run, including any `%f`-style field codes.
+    pub exec: String,
+    /// The application's icon: a name from the icon theme, or a path.
+    pub icon: Option<String>,
+    /// The MIME types the application can open.
+    pub mime_types: Vec<String>,
+    /// If `true`, the application shouldn't be shown in menus of
+    /// applications (but may still be used to open files).
+    pub no_display: bool,
+}
+
+/**
+Entries display their name, followed by their generic name (if they have
+one), lined up like the two-tuple implementation.
+*/
+impl Item for DesktopEntry {
+    fn key_len(&self) -> usize {
+        self.name.chars().count()
+    }
+
+    fn line(&self, key_len: usize) -> Vec<u8> {
+        match &self.generic_name {
+            Some(generic) => format!("{:kwidth$}  {}\n", &self.name, generic, kwidth = key_len),
+            None => format!("{}\n", &self.name),
+        }
+        .into_bytes()
+    }
+
+    fn id(&self) -> String {
+        self.id.clone()
+    }
+
+    fn icon(&self) -> Option<String> {
+        self.icon.clone()
+    }
+}
+
+/*
+Split an `Exec` value into arguments: they're separated by spaces, and
+may be enclosed in double quotes, inside which a backslash escapes the
+next character.
+*/
+fn split_exec(exec: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut arg: Option<String> = None;
+    let mut quoted = false;
+    let mut chars = exec.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                arg.get_or_insert_with(String::new);
+            }
+            '\\' if quoted => {
+                if let Some(c) = chars.next() {
+                    arg.get_or_insert_with(String::new).push(c);
+                }
+            }
+            c if c.is_whitespace() && !quoted => args.extend(arg.take()),
+            c => arg.get_or_insert_with(String::new).push(c),
+        }
+    }
+    args.extend(arg);
+    args
+}
+
+impl DesktopEntry {
+    /**
+    The command line to run the application with no files (or URLs), from
+    its `Exec` key: split into arguments, with the field codes for files
+    and URLs removed, and `%i`, `%c`, and `%k` filled in with the icon,
+    the name, and the path of the desktop entry.
+    */
+    pub fn argv(&self) -> Vec<String> {
+        let mut argv = Vec::new();
+        for arg in split_exec(&self.exec) {
+            if arg == "%i" {
+                if let Some(icon) = &self.icon {
+                    argv.push("--icon".to_owned());
+                    argv.push(icon.clone());
+                }
+                continue;
+            }
+
+            let mut expanded = String::with_capacity(arg.len());
+            let mut chars = arg.chars();
+            while let Some(c) = chars.next() {
+                if c != '%' {
+                    expanded.push(c);
+                    continue;
+                }
+                match chars.next() {
+                    Some('%') => expanded.push('%'),
+                    Some('c') => expanded.push_str(&self.name),
+                    Some('k') => expanded.push_str(&self.path.to_string_lossy()),
+                    // Files, URLs, and deprecated codes: nothing to fill in.
+                    _ => {}
+                }
+            }
+            // An argument that was nothing but field codes goes away.
+            if !expanded.is_empty() || !arg.contains('%') {
+                argv.push(expanded);
+            }
+        }
+        argv
+    }
+
+    /**
+    Start the application (with `argv()`), in its own process group, with
+    no standard input or output, so it outlives this program. The launch
+    is recorded in the audit log (see `Dmx::audit_log`), and the
+    environment is restricted as `dmx` says (see `Dmx::env_allowlist`).
+    */
+    pub fn launch(&self, dmx: &Dmx) -> Result<(), DmxError> {
+        let argv = self.argv();
+        if argv.is_empty() {
+            return Err(DmxError::ConfigParse(format!(
+                "{} has nothing to run.",
+                &self.id
+            )));
+        }
+
+        let mut c = Command::new(&argv[0]);
+        c.args(&argv[1..])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .process_group(0);
+        dmx.restrict_env(&mut c);
+        let mut child = c.spawn().map_err(|e| DmxError::spawn(&argv[0], e))?;
+        dmx.audit_launched(&argv);
+        // Reap it whenever it's done.
+        std::thread::spawn(move || child.wait());
+        Ok(())
+    }
+}
+
+/**
+Return the directories searched for desktop entries, in order of
+precedence: `$XDG_DATA_HOME/applications`, then the `applications`
+subdirectory of each directory in `$XDG_DATA_DIRS`.
+*/
+pub fn application_dirs() -> Vec<PathBuf> {
+    application_dirs_in(|var| std::env::var_os(var))
+}
+
+/*
+`application_dirs()`, with the environment given by `var_os`.
+*/
+pub(crate) fn application_dirs_in<F>(var_os: F) -> Vec<PathBuf>
+where
+    F: Fn(&str) -> Option<std::ffi::OsString>,
+{
+    let home = match var_os("XDG_DATA_HOME").filter(|v| !v.is_empty()) {
+        Some(dir) => Some(PathBuf::from(dir)),
+        None => var_os("HOME").map(|h| PathBuf::from(h).join(".local").join("share")),
+    };
+    let data_dirs = var_os("XDG_DATA_DIRS")
+        .filter(|v| !v.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".into());
+
+    home.into_iter()
+        .chain(std::env::split_paths(&data_dirs))
+        .map(|d| d.join("applications"))
+        .collect()
+}
+
+/**
+Return the current locale for messages, from `$LC_ALL`, `$LC_MESSAGES`, or
+`$LANG` (the first one that's set), like `de_DE.UTF-8`. Returns `"C"` if
+none of them are set.
+*/
+pub fn current_locale() -> String {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|val| !val.is_empty())
+        .unwrap_or_else(|| "C".to_owned())
+}
+
+/*
+The locale suffixes (as in `Name[de_DE]`) that match `locale`, from most
+to least specific. A locale of the form `lang_COUNTRY.ENCODING@MODIFIER`
+matches `lang_COUNTRY@MODIFIER`, `lang_COUNTRY`, `lang@MODIFIER`, and
+`lang`, in that order; the encoding is ignored.
+*/
+fn locale_suffixes(locale: &str) -> Vec<String> {
+    let (rest, modifier) = match locale.split_once('@') {
+        Some((rest, modifier)) => (rest, Some(modifier)),
+        None => (locale, None),
+    };
+    let rest = rest.split('.').next().unwrap_or("");
+    let (lang, country) = match rest.split_once('_') {
+        Some((lang, country)) => (lang, Some(country)),
+        None => (rest, None),
+    };
+    if lang.is_empty() || lang == "C" || lang == "POSIX" {
+        return Vec::new();
+    }
+
+    let mut suffixes = Vec::new();
+    if let (Some(country), Some(modifier)) = (country, modifier) {
+        suffixes.push(format!("{}_{}@{}", lang, country, modifier));
+    }
+    if let Some(country) = country {
+        suffixes.push(format!("{}_{}", lang, country));
+    }
+    if let Some(modifier) = modifier {
+        suffixes.push(format!("{}@{}", lang, modifier));
+    }
+    suffixes.push(lang.to_owned());
+    suffixes
+}
+
+/*
+Undo the escaping allowed in desktop entry string values.
+*/
+fn unescape_value(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('s') => out.push(' '),
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some('r') => out.push('\r'),
+            Some(c) => {
+                out.push('\\');
+                out.push(c);
+            }
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+/*
+Parse the `[Desktop Entry]` group of the desktop entry at `path`, using
+the names localized for the locale with the given suffixes (see
+`locale_suffixes()`), where there are any. Returns `None` if it can't be
+read, isn't an application, is hidden (which means "deleted"), or is
+missing required keys.
+*/
+fn parse(id: String, path: &Path, suffixes: &[String]) -> Option<DesktopEntry> {
+    let text = std::fs::read_to_string(path).ok()?;
+
+    let mut in_group = false;
+    let mut entry = DesktopEntry {
+        id,
+        path: path.to_owned(),
+        name: String::new(),
+        generic_name: None,
+        exec: String::new(),
+        icon: None,
+        mime_types: Vec::new(),
+        no_display: false,
+    };
+    let mut is_app = false;
+    // How well the localized `Name` and `GenericName` chosen so far match
+    // (as indices into `suffixes`, so lower is better); the unlocalized
+    // values rank below any match.
+    let mut name_rank = usize::MAX;
+    let mut generic_rank = usize::MAX;
+
+    for line in text.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_group = line == "[Desktop Entry]";
+            continue;
+        }
+        if !in_group || line.starts_with('#') {
+            continue;
+        }
+        let (key, value) = match line.split_once('=') {
+            Some((key, value)) => (key.trim(), value.trim()),
+            None => continue,
+        };
+        let (key, rank) = match key.split_once('[') {
+            Some((key, suffix)) => {
+                let suffix = suffix.strip_suffix(']').unwrap_or(suffix);
+                match suffixes.iter().position(|s| s == suffix) {
+                    Some(rank) => (key, rank),
+                    None => continue,
+                }
+            }
+            None => (key, suffixes.len()),
+        };
+        match key {
+            "Type" => is_app = value == "Application",
+            "Name" if rank <= name_rank => {
+                entry.name = unescape_value(value);
+                name_rank = rank;
+            }
+            "GenericName" if rank <= generic_rank => {
+                entry.generic_name = Some(unescape_value(value));
+                generic_rank = rank;
+            }
+            _ if rank < suffixes.len() => {}
+            "Exec" => entry.exec = unescape_value(value),
+            "Icon" if !value.is_empty() => entry.icon = Some(unescape_value(value)),
+            "MimeType" => {
+                entry.mime_types = value
+                    .split(';')
+                    .filter(|t| !t.is_empty())
+                    .map(|t| t.to_owned())
+                    .collect();
+            }
+            "NoDisplay" => entry.no_display = value == "true",
+            "Hidden" if value == "true" => return None,
+            _ => {}
+        }
+    }
+
+    if is_app && !entry.name.is_empty() && !entry.exec.is_empty() {
+        Some(entry)
+    } else {
+        None
+    }
+}
+
+/*
+Every directory in the tree under `dir` (including `dir` itself), in a
+consistent order.
+*/
+fn subdirs(dir: &Path) -> Vec<PathBuf> {
+    let mut dirs = vec![dir.to_owned()];
+    let mut n = 0;
+    while n < dirs.len() {
+        if let Ok(contents) = std::fs::read_dir(&dirs[n]) {
+            let mut children: Vec<PathBuf> = contents
+                .filter_map(|e| e.ok())
+                .filter(|e| e.file_type().map(|t| t.is_dir()).unwrap_or(false))
+                .map(|e| e.path())
+                .collect();
+            children.sort();
+            dirs.extend(children);
+        }
+        n += 1;
+    }
+    dirs
+}
+
+/*
+Read all the desktop entries in the given application directories, with
+names for the given locale. When more than one has the same desktop file
+ID, the one from the earliest directory wins.
+*/
+pub(crate) fn scan_dirs(app_dirs: &[PathBuf], locale: &str) -> Vec<DesktopEntry> {
+    let suffixes = locale_suffixes(locale);
+    let mut entries: Vec<DesktopEntry> = Vec::new();
+    let mut seen: std::collections::HashSet<String> = std::collections::HashSet::new();
+
+    for top in app_dirs.iter() {
+        for dir in subdirs(top).iter() {
+            let mut files: Vec<PathBuf> = match std::fs::read_dir(dir) {
+                Ok(contents) => contents
+                    .filter_map(|e| e.ok())
+                    .map(|e| e.path())
+                    .filter(|p| p.extension().map(|x| x == "desktop").unwrap_or(false))
+                    .collect(),
+                Err(_) => continue,
+            };
+            files.sort();
+
+            for path in files.iter() {
+                // The ID of `applications/foo/bar.desktop` is `foo-bar.desktop`.
+                let id = match path.strip_prefix(top) {
+                    Ok(rel) => rel.to_string_lossy().replace('/', "-"),
+                    Err(_) => continue,
+                };
+                if !seen.insert(id.clone()) {
+                    continue;
+                }
+                if let Some(entry) = parse(id, path, &suffixes) {
+                    entries.push(entry);
+                }
+            }
+        }
+    }
+
+    entries
+}
+
+/**
+Read all the installed applications' desktop entries (with their names
+localized for `current_locale()`), without consulting (or updating) the
+index. Unreadable or invalid entries are skipped.
+*/
+pub fn scan() -> Vec<DesktopEntry> {
+    scan_dirs(&application_dirs(), &current_locale())
+}
+
+/*
+The lines identifying the state of the given application directories:
+the locale the names are for, then one line per directory (and
+subdirectory) with its modification time. Any change to these means the
+cache is stale.
+*/
+fn stamps(app_dirs: &[PathBuf], locale: &str) -> Vec<String> {
+    let locale = format!("locale\t{}", escape_field(locale));
+    std::iter::once(locale)
+        .chain(
+            app_dirs
+                .iter()
+                .flat_map(|top| subdirs(top))
+                .map(|dir| dir_stamp(&dir)),
+        )
+        .collect()
+}
+
+fn entry_to_line(e: &DesktopEntry) -> String {
+    [
+        "entry",
+        &escape_field(&e.id),
+        &escape_field(&e.path.to_string_lossy()),
+        &escape_field(&e.name),
+        &escape_field(e.generic_name.as_deref().unwrap_or("")),
+        &escape_field(&e.exec),
+        &escape_field(e.icon.as_deref().unwrap_or("")),
+        &escape_field(&e.mime_types.join(";")),
+        if e.no_display { "1" } else { "0" },
+    ]
+    .join("\t")
+}
+
+fn entry_from_line(line: &str) -> Option<DesktopEntry> {
+    let fields: Vec<String> = line.split('\t').map(unescape_field).collect();
+    match fields.as_slice() {
+        [tag, id, path, name, generic, exec, icon, mimes, no_display] if tag == "entry" => {
+            Some(DesktopEntry {
+                id: id.clone(),
+                path: PathBuf::from(path),
+                name: name.clone(),
+                generic_name: Some(generic.clone()).filter(|g| !g.is_empty()),
+                exec: exec.clone(),
+                icon: Some(icon.clone()).filter(|i| !i.is_empty()),
+                mime_types: mimes
+                    .split(';')
+                    .filter(|t| !t.is_empty())
+                    .map(|t| t.to_owned())
+                    .collect(),
+                no_display: no_display == "1",
+            })
+        }
+        _ => None,
+    }
+}
+
+/*
+Read the index at `cache`, if it's still accurate for `app_dirs` in the
+given locale.
+*/
+fn read_cache(cache: &PathBuf, app_dirs: &[PathBuf], locale: &str) -> Option<Vec<DesktopEntry>> {
+    let lines = read_lines(cache).ok()?;
+    let mut lines = lines.iter();
+    if lines.next().map(|l| l.as_str()) != Some(CACHE_HEADER) {
+        return None;
+    }
+
+    let stamps = stamps(app_dirs, locale);
+    let mut lines = lines.peekable();
+    for stamp in stamps.iter() {
+        if lines.next() != Some(stamp) {
+            return None;
+        }
+    }
+    if lines
+        .peek()
+        .map(|l| l.starts_with("dir\t"))
+        .unwrap_or(false)
+    {
+        // A directory that has since been removed.
+        return None;
+    }
+
+    lines.map(|l| entry_from_line(l)).collect()
+}
+
+/*
+Read the entries in `app_dirs` (for the given locale), from the index at
+`cache` unless it's stale (or `force` is set), in which case they're
+scanned and the index is rewritten. The entries are returned along with
+whether that worked.
+*/
+pub(crate) fn load_dirs(
+    app_dirs: &[PathBuf],
+    locale: &str,
+    cache: &PathBuf,
+    force: bool,
+) -> (Vec<DesktopEntry>, Result<(), DmxError>) {
+    if !force {
+        if let Some(entries) = read_cache(cache, app_dirs, locale) {
+            return (entries, Ok(()));
+        }
+    }
+
+    // Take the stamps first, so that anything that changes during the
+    // scan makes the new index stale.
+    let stamps = stamps(app_dirs, locale);
+    let entries = scan_dirs(app_dirs, locale);
+
+    let mut lines = vec![CACHE_HEADER.to_owned()];
+    lines.extend(stamps);
+    lines.extend(entries.iter().map(entry_to_line));
+    let written = write_lines(cache, &lines);
+
+    (entries, written)
+}
+
+/**
+Return all the installed applications' desktop entries (with their names
+localized for `current_locale()`), using the index
+if it's up to date (and rebuilding it if it isn't).
+
+Failing to write the index isn't an error; it just means the next call
+will have to scan again. Use `rebuild()` to find out why.
+*/
+pub fn entries() -> Vec<DesktopEntry> {
+    let app_dirs = application_dirs();
+    let locale = current_locale();
+    match cache_file("desktop-entries") {
+        Ok(cache) => load_dirs(&app_dirs, &locale, &cache, false).0,
+        Err(_) => scan_dirs(&app_dirs, &locale),
+    }
+}
+
+/**
+Scan all the installed applications' desktop entries and rewrite the
+index, whether it looks stale or not.
+*/
+pub fn rebuild() -> Result<Vec<DesktopEntry>, DmxError> {
+    let cache = cache_file("desktop-entries")?;
+    let (entries, written) = load_dirs(&application_dirs(), &current_locale(), &cache, true);
+    written.map(|_| entries)
+}
@@ -0,0 +1,87 @@
+/*!
+Transcoding of the text exchanged with `dmenu`.
+
+`Item::line()` produces UTF-8, and most builds of `dmenu` expect UTF-8, but
+some older or heavily-patched builds (and some other menu programs) only
+understand a single-byte encoding. For those, the item lines need to be
+transcoded before they're piped to the menu, and the menu's output needs
+to be decoded accordingly.
+*/
+
+#[cfg(feature = "config")]
+use serde::{Deserialize, Serialize};
+
+/**
+The text encoding the menu program expects on its standard input (and
+produces on its standard output).
+*/
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "config", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "config", serde(rename_all = "lowercase"))]
+pub enum Encoding {
+    /// Pass lines through unchanged (the default).
+    #[default]
+    #[cfg_attr(feature = "config", serde(alias = "utf-8"))]
+    Utf8,
+    /// ISO-8859-1. Characters that can't be represented are replaced
+    /// with `?`.
+    #[cfg_attr(feature = "config", serde(alias = "iso-8859-1"))]
+    Latin1,
+    /// Use whichever of the above is specified by the locale's character
+    /// set (as determined by `$LC_ALL`, `$LC_CTYPE`, or `$LANG`), falling
+    /// back to UTF-8 if it can't be determined or isn't supported.
+    Locale,
+}
+
+impl Encoding {
+    /*
+    Resolve `Encoding::Locale` to a concrete encoding.
+    */
+    fn resolve(self) -> Encoding {
+        if self != Encoding::Locale {
+            return self;
+        }
+
+        let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+            .iter()
+            .filter_map(|v| std::env::var(v).ok())
+            .find(|v| !v.is_empty())
+            .unwrap_or_default();
+        // A locale looks like `language_TERRITORY.codeset@modifier`.
+        let codeset = locale
+            .split_once('.')
+            .map(|(_, rest)| rest.split('@').next().unwrap_or(rest))
+            .unwrap_or("")
+            .to_ascii_lowercase()
+            .replace(['-', '_'], "");
+
+        match codeset.as_str() {
+            "iso88591" | "latin1" => Encoding::Latin1,
+            _ => Encoding::Utf8,
+        }
+    }
+
+    /**
+    Transcode a chunk of UTF-8 (as produced by `Item::line()`) into this
+    encoding.
+    */
+    pub fn encode(self, utf8: &[u8]) -> Vec<u8> {
+        match self.resolve() {
+            Encoding::Latin1 => String::from_utf8_lossy(utf8)
+                .chars()
+                .map(|c| u8::try_from(u32::from(c)).unwrap_or(b'?'))
+                .collect(),
+            _ => utf8.to_vec(),
+        }
+    }
+
+    /**
+    Decode a chunk of text in this encoding (like the menu's output).
+    */
+    pub fn decode(self, bytes: &[u8]) -> String {
+        match self.resolve() {
+            Encoding::Latin1 => bytes.iter().map(|&b| char::from(b)).collect(),
+            _ => String::from_utf8_lossy(bytes).into_owned(),
+        }
+    }
+}
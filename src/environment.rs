@@ -0,0 +1,101 @@
+/*!
+Settings from environment variables, for changing how menus look for one
+shell session (or one command) without a configuration file.
+*/
+
+use std::str::FromStr;
+
+use crate::{Backend, Dmx, DmxError, Font};
+
+/*
+The error for `$var` having a value that can't be used.
+*/
+fn invalid(var: &str, value: &str) -> DmxError {
+    DmxError::ConfigParse(format!("Invalid ${} \"{}\".", var, value))
+}
+
+/*
+Parse `value`, the value of `$var`.
+*/
+fn parse<T: FromStr>(var: &str, value: &str) -> Result<T, DmxError> {
+    value.parse().map_err(|_| invalid(var, value))
+}
+
+/*
+Override the settings in `dmx` with those in the environment, as given
+by `lookup` (which returns a variable's value, if it's set).
+*/
+pub(crate) fn apply<F>(dmx: &mut Dmx, lookup: F) -> Result<(), DmxError>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let var = |name: &str| lookup(name).filter(|v| !v.is_empty());
+
+    if let Some(dmenu) = var("DMX_DMENU") {
+        dmx.dmenu = dmenu.into();
+    }
+    if let Some(font) = var("DMX_FONT") {
+        dmx.font = Font::new(font);
+    }
+    for (name, color) in [
+        ("DMX_NB", &mut dmx.normal_bg),
+        ("DMX_NF", &mut dmx.normal_fg),
+        ("DMX_SB", &mut dmx.select_bg),
+        ("DMX_SF", &mut dmx.select_fg),
+    ] {
+        if let Some(value) = var(name) {
+            *color = parse(name, &value)?;
+        }
+    }
+    if let Some(lines) = var("DMX_LINES") {
+        dmx.max_lines = Some(parse("DMX_LINES", &lines)?);
+    }
+    if let Some(monitor) = var("DMX_MONITOR") {
+        dmx.monitor = Some(parse("DMX_MONITOR", &monitor)?);
+    }
+    if let Some(fuzzy) = var("DMX_FUZZY") {
+        dmx.fuzzy = match fuzzy.to_ascii_lowercase().as_str() {
+            "1" | "true" | "yes" | "on" => true,
+            "0" | "false" | "no" | "off" => false,
+            _ => return Err(invalid("DMX_FUZZY", &fuzzy)),
+        };
+    }
+    if let Some(backends) = var("DMX_BACKENDS") {
+        dmx.backends = backends
+            .split([',', ' '])
+            .filter(|name| !name.is_empty())
+            .map(Backend::from_str)
+            .collect::<Result<Vec<Backend>, DmxError>>()?;
+    }
+    Ok(())
+}
+
+impl Dmx {
+    /**
+    Return a `Dmx` with the default settings, except for any set by these
+    environment variables (which are ignored if they're empty):
+
+      * `DMX_DMENU`: the `dmenu` binary
+      * `DMX_FONT`: the font
+      * `DMX_NB`, `DMX_NF`, `DMX_SB`, `DMX_SF`: the colors, named after
+        `dmenu`'s options for them
+      * `DMX_LINES`: the most lines a menu shows (`Dmx::max_lines`)
+      * `DMX_MONITOR`: the monitor to show menus on
+      * `DMX_FUZZY`: `1` or `0` (or `true` or `false`), to turn fuzzy
+        matching on or off
+      * `DMX_BACKENDS`: the menu programs to try, separated by commas,
+        like `fuzzel,rofi,dmenu`
+
+    This needs neither the `config` feature nor a file, so it's the
+    simplest way for a shell session to theme its menus:
+
+    ```sh
+    export DMX_FONT="Terminus-12" DMX_NB="#002b36" DMX_SB="#268bd2"
+    ```
+    */
+    pub fn from_env() -> Result<Dmx, DmxError> {
+        let mut dmx = Dmx::default();
+        apply(&mut dmx, |var| std::env::var(var).ok())?;
+        Ok(dmx)
+    }
+}
@@ -0,0 +1,204 @@
+/*!
+The errors this crate can return.
+*/
+
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+use std::process::ExitStatus;
+
+/**
+Everything that can go wrong showing a menu (or doing anything else this
+crate does).
+
+Matching on the variant tells apart the problems a program might want to
+handle differently, like the menu program not being installed at all
+(`BinaryNotFound`) and the pipe to it breaking (`Io`); `Display` gives a
+message fit to show the user.
+*/
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum DmxError {
+    /// A program that's needed (like the menu program) isn't installed,
+    /// or isn't in `$PATH`.
+    BinaryNotFound(PathBuf),
+    /// `Dmx::absolute_binary` is set, and a backend's program wasn't given
+    /// by an absolute path, so it wasn't run (not even to probe it).
+    NotAbsolute { backend: String, program: PathBuf },
+    /// A program that's needed was found, but isn't an executable file.
+    NotExecutable(PathBuf),
+    /// None of the programs that could do something (like copying to the
+    /// clipboard) is installed.
+    NoneInstalled(Vec<String>),
+    /// A program was found, but couldn't be started.
+    SpawnFailed {
+        program: String,
+        source: std::io::Error,
+    },
+    /// Reading or writing something (a pipe to the menu program, or a
+    /// file) failed.
+    Io {
+        context: String,
+        source: std::io::Error,
+    },
+    /// A file or directory couldn't be found because an environment
+    /// variable it's found with (`var`, like `$HOME`) isn't set; `what`
+    /// says what was being looked for.
+    EnvNotSet { var: String, what: String },
+    /// Something that was asked for (like a submenu, or an application to
+    /// open a file with) doesn't exist; this says what.
+    NotFound(String),
+    /// Something that should have been UTF-8 wasn't.
+    Utf8 {
+        context: String,
+        source: std::string::FromUtf8Error,
+    },
+    /// A configuration file (or some other file this crate reads) couldn't
+    /// be parsed, or had a value in it that can't be used.
+    ConfigParse(String),
+    /// A backend can't be used here (like a terminal menu without a
+    /// terminal).
+    Unavailable { backend: String, reason: String },
+    /// A graphical backend has no display to show its menu on (like
+    /// `dmenu` run from a service that wasn't given `$DISPLAY`, or a
+    /// Wayland-only menu program outside a Wayland session); `reason` says
+    /// what to do about it.
+    NoDisplay { backend: String, reason: String },
+    /// The menu was cancelled. Nothing in this crate returns this (a
+    /// cancelled menu is `Ok(None)`); it's for programs that would rather
+    /// treat cancelling as an error:
+    ///
+    /// ```no_run
+    /// # use dm_x::{Dmx, DmxError};
+    /// # fn main() -> Result<(), DmxError> {
+    /// let n = Dmx::default().select("pick:", &["a", "b"])?.ok_or(DmxError::Cancelled)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    Cancelled,
+    /// The menu program didn't accept the arguments it was given, which
+    /// usually means `dmenu` is some other program, or an unusual fork.
+    MenuRejected { program: String, stderr: String },
+    /// The menu program failed for some other reason.
+    MenuFailed {
+        program: String,
+        status: ExitStatus,
+        stderr: String,
+    },
+    /// A program other than the menu program (like a clipboard tool, or
+    /// `sqlite3`) failed.
+    CommandFailed {
+        program: String,
+        status: ExitStatus,
+        stderr: String,
+    },
+    /// None of several backends could be started; here's why, for each.
+    NoBackend(Vec<DmxError>),
+    /// A function was given something it can't use, like a menu id with a
+    /// `/` in it, or a session that was never given any items.
+    InvalidArgument(String),
+    /// `dm_x::init()` was called more than once.
+    AlreadyInitialized,
+}
+
+impl Display for DmxError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            DmxError::BinaryNotFound(path) => {
+                write!(f, "Unable to find \"{}\".", path.display())
+            }
+            DmxError::NotAbsolute { backend, program } => write!(
+                f,
+                "{}: Refusing to launch \"{}\": not an absolute path.",
+                backend,
+                program.display()
+            ),
+            DmxError::NotExecutable(path) => {
+                write!(f, "\"{}\" isn't an executable file.", path.display())
+            }
+            DmxError::NoneInstalled(programs) => {
+                write!(f, "None of {} is installed.", programs.join(", "))
+            }
+            DmxError::SpawnFailed { program, source } => {
+                write!(f, "Unable to launch {}: {}", program, source)
+            }
+            DmxError::Io { context, source } => write!(f, "{}: {}", context, source),
+            DmxError::EnvNotSet { var, what } => {
+                write!(f, "Unable to find {}: ${} is not set.", what, var)
+            }
+            DmxError::NotFound(what) => write!(f, "Unable to find {}.", what),
+            DmxError::Utf8 { context, source } => write!(f, "{}: {}", context, source),
+            DmxError::ConfigParse(msg) => f.write_str(msg),
+            DmxError::Unavailable { backend, reason } | DmxError::NoDisplay { backend, reason } => {
+                write!(f, "{}: {}", backend, reason)
+            }
+            DmxError::Cancelled => f.write_str("The menu was cancelled."),
+            DmxError::MenuRejected { program, stderr } => write!(
+                f,
+                "{} didn't accept its arguments (try setting `dmenu` to \
+                the right binary, or using another backend): {}",
+                program, stderr
+            ),
+            DmxError::MenuFailed {
+                program,
+                status,
+                stderr,
+            }
+            | DmxError::CommandFailed {
+                program,
+                status,
+                stderr,
+            } => match stderr.as_str() {
+                "" => write!(f, "{} failed ({}).", program, status),
+                stderr => write!(f, "{} failed ({}): {}", program, status, stderr),
+            },
+            DmxError::NoBackend(errors) => match errors.as_slice() {
+                [] => f.write_str("No menu backends configured."),
+                errors => {
+                    f.write_str("Unable to launch any menu backend:")?;
+                    for e in errors.iter() {
+                        write!(f, "\n  {}", e)?;
+                    }
+                    Ok(())
+                }
+            },
+            DmxError::InvalidArgument(msg) => f.write_str(msg),
+            DmxError::AlreadyInitialized => f.write_str("Global Dmx has already been initialized."),
+        }
+    }
+}
+
+impl std::error::Error for DmxError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DmxError::SpawnFailed { source, .. } | DmxError::Io { source, .. } => Some(source),
+            DmxError::Utf8 { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl DmxError {
+    /**
+    An `Io` error, for when `source` happened while doing `context`.
+    */
+    pub fn io<S: Into<String>>(context: S, source: std::io::Error) -> DmxError {
+        DmxError::Io {
+            context: context.into(),
+            source,
+        }
+    }
+
+    /**
+    The error for trying to run `program`, which failed with `source`:
+    `BinaryNotFound` if it isn't there, otherwise `SpawnFailed`.
+    */
+    pub fn spawn<S: AsRef<str>>(program: S, source: std::io::Error) -> DmxError {
+        match source.kind() {
+            std::io::ErrorKind::NotFound => DmxError::BinaryNotFound(program.as_ref().into()),
+            _ => DmxError::SpawnFailed {
+                program: program.as_ref().to_owned(),
+                source,
+            },
+        }
+    }
+}
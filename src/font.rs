@@ -0,0 +1,157 @@
+/*!
+The font for the menu, which can be checked against the fonts fontconfig
+knows about before `dmenu` is asked to use it.
+*/
+
+use std::fmt::{Display, Formatter};
+use std::process::{Command, Stdio};
+
+#[cfg(feature = "config")]
+use serde::{Deserialize, Serialize};
+
+use crate::backend::split_font;
+
+/*
+Names fontconfig always resolves to some installed font.
+*/
+const GENERIC_FAMILIES: &[&str] = &["monospace", "mono", "sans-serif", "sans", "serif"];
+
+/**
+A font name, as `dmenu` takes it: an Xft (fontconfig) name like
+`Terminus-12` or `DejaVu Sans Mono:size=10`, or (for very old versions) an
+X logical font description. The other backends are given the same font,
+translated to their format where that's needed.
+
+A font that isn't installed is a common reason for `dmenu` refusing to
+start, so `Font::is_installed()` asks fontconfig (with `fc-match`)
+whether it is, and `Font::or_fallback()` swaps a missing font for a
+generic one.
+
+```no_run
+# use dm_x::{Dmx, Font};
+let dmx = Dmx {
+    font: Font::new("Terminus-12").or_fallback(),
+    ..Default::default()
+};
+```
+*/
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "config", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "config", serde(transparent))]
+pub struct Font(String);
+
+impl Font {
+    /**
+    The font with the given name (which isn't checked).
+    */
+    pub fn new<S: Into<String>>(name: S) -> Font {
+        Font(name.into())
+    }
+
+    /**
+    The font's name, as it was given.
+    */
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+
+    /*
+    The font's family and size, from an Xft name (`Terminus-12`,
+    `Terminus:size=12`) or a Pango one (`Terminus 12`).
+    */
+    pub(crate) fn family_and_size(&self) -> (&str, Option<&str>) {
+        let (family, size) = split_font(&self.0);
+        let family = family.split(':').next().unwrap_or(family);
+        match (size, family.rsplit_once(' ')) {
+            (None, Some((name, size))) if size.parse::<f64>().is_ok() => (name, Some(size)),
+            _ => (family, size),
+        }
+    }
+
+    /**
+    Whether fontconfig has the font's family installed, or `None` if that
+    can't be found out (because `fc-match` can't be run, or the name is
+    an X logical font description rather than a fontconfig one).
+    */
+    pub fn is_installed(&self) -> Option<bool> {
+        if self.0.starts_with('-') {
+            return None;
+        }
+        let (family, _) = self.family_and_size();
+        // fontconfig ignores case and spaces in family names.
+        let squash = |name: &str| -> String {
+            name.chars()
+                .filter(|c| !c.is_whitespace())
+                .flat_map(char::to_lowercase)
+                .collect()
+        };
+        if GENERIC_FAMILIES.contains(&squash(family).as_str()) {
+            return Some(true);
+        }
+
+        // fc-match always finds something, so it's a match only if it
+        // found the family asked for.
+        let output = Command::new("fc-match")
+            .args(["--format=%{family}", family])
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+            .ok()
+            .filter(|o| o.status.success())?;
+        let found = String::from_utf8_lossy(&output.stdout);
+        Some(found.split(',').any(|f| squash(f) == squash(family)))
+    }
+
+    /**
+    If fontconfig is sure this font isn't installed, the font to use
+    instead: `monospace`, in the same size.
+    */
+    pub fn fallback(&self) -> Option<Font> {
+        if self.is_installed() != Some(false) {
+            return None;
+        }
+        Some(match self.family_and_size() {
+            (_, Some(size)) => Font(format!("monospace-{}", size)),
+            (_, None) => Font("monospace".to_owned()),
+        })
+    }
+
+    /**
+    This font, or its `Font::fallback()` if it isn't installed. (With the
+    `tracing` feature, the swap is logged; to tell the user about it some
+    other way, use `Font::fallback()` instead.)
+    */
+    pub fn or_fallback(self) -> Font {
+        match self.fallback() {
+            Some(fallback) => {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(
+                    target: "dm_x",
+                    "Font \"{}\" isn't installed; using \"{}\".",
+                    self,
+                    fallback
+                );
+                fallback
+            }
+            None => self,
+        }
+    }
+}
+
+impl Display for Font {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<&str> for Font {
+    fn from(name: &str) -> Font {
+        Font::new(name)
+    }
+}
+
+impl From<String> for Font {
+    fn from(name: String) -> Font {
+        Font::new(name)
+    }
+}
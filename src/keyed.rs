@@ -0,0 +1,76 @@
+/*!
+An `Item` that carries a value along with the text it's shown as.
+*/
+
+use crate::{Dmx, DmxError, Item};
+
+/**
+A key and description to show in a menu, like a two-tuple of strings,
+along with the value choosing it stands for, so that what an item looks
+like is kept apart from what it does without defining a type for it.
+
+```no_run
+# use dm_x::{Dmx, Keyed};
+let sizes = vec![
+    Keyed::new("s", "Small (8 oz)", 8),
+    Keyed::new("m", "Medium (12 oz)", 12),
+    Keyed::new("l", "Large (16 oz)", 16),
+];
+if let Some(ounces) = Dmx::default().select_keyed("size:", sizes).unwrap() {
+    println!("{} ounces of coffee, coming up.", ounces);
+}
+```
+*/
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Keyed<T> {
+    /// The short key shown first (and lined up with the others).
+    pub key: String,
+    /// The description shown after the key.
+    pub desc: String,
+    /// What choosing this item means.
+    pub value: T,
+}
+
+impl<T> Keyed<T> {
+    /**
+    An item shown as `key` and `desc`, standing for `value`.
+    */
+    pub fn new<K, D>(key: K, desc: D, value: T) -> Keyed<T>
+    where
+        K: Into<String>,
+        D: Into<String>,
+    {
+        Keyed {
+            key: key.into(),
+            desc: desc.into(),
+            value,
+        }
+    }
+}
+
+/**
+`Keyed` items are displayed just like two-tuples of their key and
+description.
+*/
+impl<T> Item for Keyed<T> {
+    fn key_len(&self) -> usize {
+        (&self.key, &self.desc).key_len()
+    }
+
+    fn line(&self, key_len: usize) -> Vec<u8> {
+        (&self.key, &self.desc).line(key_len)
+    }
+}
+
+impl Dmx {
+    /**
+    Launch `dmenu` to select one of `items`, and return its value (or
+    `None` if cancelled).
+    */
+    pub fn select_keyed<S, T>(&self, prompt: S, items: Vec<Keyed<T>>) -> Result<Option<T>, DmxError>
+    where
+        S: AsRef<str>,
+    {
+        Ok(self.select_owned(prompt, items)?.map(|k| k.value))
+    }
+}
@@ -32,6 +32,9 @@ static REGRESSION_TYPES: &[(&str, &str)] = &[
 ];
 
 let dmx = Dmx::default();
+# // Doctests shouldn't depend on being run in an X session.
+# let display = std::env::var("DISPLAY").unwrap_or(":0".to_owned());
+# let dmx = Dmx { env: [("DISPLAY".to_owned(), display)].into(), ..dmx };
 
 match dmx.select("method:", REGRESSION_TYPES).unwrap() {
     Some(n) => {
@@ -44,15 +47,22 @@ match dmx.select("method:", REGRESSION_TYPES).unwrap() {
 
 ```
 
+Errors are `DmxError`s, whose variants tell apart the problems a program
+might handle differently (like `dmenu` not being installed at all, which
+is `DmxError::BinaryNotFound`); displayed, they're messages fit for the
+user.
+
 # Features
 
-`dm_x` has one optional feature, `config`, which provides the ability to
+`dm_x` has a couple of optional features. The most substantial is
+`config`, which provides the ability to
 deserialize a `Dmx` configuration from some .toml. This gets
 [`serde`](https://serde.rs) (and [`toml`](https://crates.io/crates/toml))
 involved, which is kind of a large dependency for an otherwise
 dependency-free (save the `dmenu` binary) crate (hence the feature gate).
 
 ```
+# use dm_x::Dmx;
 #[cfg(feature = "config")]
 {
     const CHOICES: &[(&str, &str)] = &[
@@ -64,6 +74,9 @@ dependency-free (save the `dmenu` binary) crate (hence the feature gate).
     ];
     
     let dmx = Dmx::from_file("test/dmx_conf.toml").unwrap();
+#     // Doctests shouldn't depend on being run in an X session.
+#     let display = std::env::var("DISPLAY").unwrap_or(":0".to_owned());
+#     let dmx = Dmx { env: [("DISPLAY".to_owned(), display)].into(), ..dmx };
     
     match dmx.select("->", CHOICES).unwrap() {
         None => {
@@ -92,21 +105,354 @@ select_bg = "#422"
 select_fg = "#88cccc"
 ```
 
+For a file with every setting in it, explained, run `dmx init-config`
+(the binary this crate installs with the `config` feature), or call
+`Dmx::write_default_config()`; either writes `DEFAULT_CONFIG` to
+`dmx.toml` in `$XDG_CONFIG_HOME` (or in `~/.config`, if that isn't set).
+
+The same settings can be written as JSON instead, in a file whose name
+ends in `.json`:
+
+```json
+{ "font": "Terminus-12", "normal_bg": "#88cccc", "backends": ["rofi", "dmenu"] }
+```
+
+With the `yaml` feature, files whose names end in `.yaml` or `.yml` are
+read as YAML, too. And for minimal setups, files whose names end in
+`.ini` can use a flatter format, without TOML's quotes and brackets:
+
+```ini
+font = Terminus-12
+normal_bg = #88cccc
+backends = rofi, dmenu
+
+[env]
+DISPLAY = :1
+```
+
+(A list item with a comma in it goes in double quotes.)
+
+A file can build on another with `include`, naming a file (of any of
+these formats) whose settings it starts from and then overrides, so
+that one theme can be shared between machines with tweaks for each:
+
+```toml
+include = "theme.toml"   # found next to this file
+font    = "Terminus-14"  # this machine's screen is bigger
+```
+
+Likewise, a distribution can put a theme in `/etc/xdg/dmx.toml`, whose
+settings everyone's own file starts from, and only has to override.
+
+When `backends` lists more than one program, the ones that style menus
+differently can be given settings of their own, in a `[backend.<name>]`
+section, which are used instead while that backend shows a menu:
+
+```toml
+font = "Terminus-12"
+backends = ["rofi", "dmenu"]
+
+[backend.rofi]
+theme = "gruvbox-dark"   # a rofi theme, rather than the colors
+max_lines = 15
+
+[backend.dmenu]
+font = "Terminus-10"
+```
+
+Programs sharing one file can each have a section of their own, in
+`[app.<name>]`, whose settings `Dmx::for_app("<name>")` applies on top of
+the rest:
+
+```toml
+font = "Terminus-12"
+
+[app.emoji]
+font = "Noto Color Emoji-16"
+max_lines = 8
+```
+
+Keys a file has that don't mean anything are ignored, unless it sets
+`strict = true`; then they're an error, which says where each one is and
+what it was probably meant to be:
+
+```text
+Unknown key `normal_gb` at line 3, column 1; did you mean `normal_bg`?
+```
+
+Paths (`dmenu`, `include`, and `audit_log`) can start with `~` for the
+home directory, and use environment variables, like `$XDG_DATA_HOME` or
+`${HOME}`; a variable that isn't set is an error.
+
+Menus are as tall as it takes to show all their items; `max_lines = 20`
+(say) keeps them from being any taller, and adding `fixed_lines = true`
+makes every menu exactly that tall, however few items it has. With more
+than one monitor, `monitor = 1` (say) opens menus on the second one.
+
+Setting `pin_binary = true` resolves the `dmenu` binary to an absolute path
+when the configuration is loaded, and refuses to run anything else
+afterward (see `Dmx::pin_binary()`). Similarly, `check_font = true`
+replaces a font that isn't installed with `monospace` (see
+`Font::or_fallback()`).
+
+Programs run from a key binding have nowhere to show their errors;
+`notify_errors = true` shows them as desktop notifications too (with
+`notify-send`).
+
+The menu program's environment can be adjusted, for instance to show
+menus on another display:
+
+```toml
+env_remove = ["WAYLAND_DISPLAY"]
+
+[env]
+DISPLAY = ":1"
+```
+
+To use something other than `dmenu` (or to fall back to something else
+where `dmenu` can't run), list the menu programs to try in `backends`;
+see the `backend` module.
+
+Setting `fuzzy = true` turns on fuzzy matching where the menu program
+supports it (see `Dmx::fuzzy`).
+
+If your `dmenu` isn't UTF-8 clean, `encoding` can be set to `"latin1"`
+(or to `"locale"` to follow the locale's character set). Similarly,
+`stderr` chooses what happens to the menu program's error output
+(`"inherit"`, `"capture"`, `"log"`, or `"discard"`; see `StderrMode`).
+
+For enormous menus, `spool_threshold = 100000` (or whatever) feeds any
+menu with at least that many lines to the menu program from a temporary
+file instead of a pipe (see `Dmx::spool_threshold`).
+
+A `[retry]` table turns on retrying menus that fail transiently; any of
+its keys can be omitted (see `RetryPolicy`):
+
+```toml
+[retry]
+attempts         = 5
+initial_delay_ms = 200
+max_delay_ms     = 3000
+```
+
+Options added by popular `dmenu` patches are set the same way, and only
+passed to `dmenu` when it accepts them:
+
+```toml
+center       = true   # the center patch's -c
+border_width = 2      # the border patch's -bw
+width        = 600    # the xyw patch's -z
+columns      = 8      # the grid patch's -g
+```
+
+Anything else goes in `extra_args`, which is added to the end of
+`dmenu`'s command line as it is:
+
+```toml
+extra_args = ["-x", "100", "-y", "40"]
+```
+
+If your `dmenu` is a fork that has renamed some of its options, a
+`[flag_map]` table renames them back (see `FlagMap`); an empty string
+leaves that option out:
+
+```toml
+[flag_map]
+font      = "--fn"
+select_fg = ""
+```
+
+If your menu program doesn't take `dmenu`'s arguments at all, the
+`command` key replaces the generated command line entirely; see the
+documentation of `Dmx::command` for the placeholders it understands.
+
+```toml
+command = ["mymenu", "-p", "{prompt}", "--lines", "{lines}"]
+```
+
+The `bookmarks` feature adds the `bookmarks` module, for choosing one of
+the user's browser bookmarks (which needs a JSON parser, hence the gate). Similarly, `snippets` adds
+the `snippets` module, a menu of canned text to copy or type, and
+`picker` adds the `picker` module, with a menu that runs in the terminal
+without needing any menu program installed. The `switcher` feature adds
+the `switcher` module, for switching between the open windows, and
+`power` adds the `power` module, a menu for logging out, suspending,
+rebooting, and the like.
+
+The `derive` feature adds `#[derive(DmxItem)]`, which implements `Item`
+for a struct or enum whose fields are marked as its key and description:
+
+```
+# #[cfg(feature = "derive")]
+# {
+use dm_x::DmxItem;
+
+#[derive(DmxItem)]
+struct Host {
+    #[dmx(key)]
+    alias: String,
+    #[dmx(desc)]
+    address: String,
+}
+# }
+```
+
+The `pywal` feature adds `Dmx::from_pywal()`, which takes the menu's
+colors from the palette [pywal](https://github.com/dylanaraps/pywal)
+generated from the wallpaper.
+
+The `strum` feature adds `Dmx::select_variant()`, for choosing one of the
+variants of an enum that derives [`strum`](https://crates.io/crates/strum)'s
+`EnumIter` (and implements `Display`).
+
+The `tracing` feature lets `Dmx` log the menu program's standard error
+with [`tracing`](https://crates.io/crates/tracing) (see `StderrMode::Log`).
+
 */
 
 #![feature(doc_cfg)]
 
-use std::io::{Read, Write};
-use std::path::PathBuf;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, OnceLock};
+
 #[cfg(feature = "config")]
-use std::path::Path;
-use std::process::{Command, Stdio};
+use serde::{Deserialize, Serialize};
 
+mod audit;
+pub mod backend;
+#[cfg(feature = "bookmarks")]
+#[doc(cfg(feature = "bookmarks"))]
+pub mod bookmarks;
+mod breadcrumbs;
+mod builder;
+pub mod clipboard;
+mod color;
 #[cfg(feature = "config")]
 mod config;
+pub mod desktop;
+mod encoding;
+mod environment;
+mod error;
+mod font;
+mod keyed;
+pub mod menu;
+pub mod mime;
+pub mod numbered;
+mod open;
+mod overrides;
+pub mod path_bin;
+#[cfg(feature = "picker")]
+#[doc(cfg(feature = "picker"))]
+pub mod picker;
+#[cfg(feature = "power")]
+#[doc(cfg(feature = "power"))]
+pub mod power;
+mod probe;
+#[cfg(feature = "pywal")]
+mod pywal;
+pub mod rofi_script;
+#[cfg(feature = "snippets")]
+#[doc(cfg(feature = "snippets"))]
+pub mod snippets;
+mod spool;
+pub mod store;
+#[cfg(feature = "switcher")]
+#[doc(cfg(feature = "switcher"))]
+pub mod switcher;
+mod xresources;
+
+use backend::ExitMeaning;
+pub use backend::{Backend, MenuBackend, MenuHints};
+pub use breadcrumbs::Breadcrumbs;
+pub use builder::DmxBuilder;
+pub use color::Color;
+pub use encoding::Encoding;
+pub use error::DmxError;
+pub use font::Font;
+pub use keyed::Keyed;
+pub use menu::{Menu, Selection};
+pub use overrides::BackendOverrides;
+
+#[cfg(feature = "derive")]
+#[doc(cfg(feature = "derive"))]
+pub use dm_x_derive::DmxItem;
 
 const NEWLINE: u8 = b'\n';
 
+static GLOBAL: OnceLock<Dmx> = OnceLock::new();
+
+/*
+Find the executable named `name` in `$PATH`, like the shell would.
+*/
+fn which(name: &Path) -> Option<PathBuf> {
+    which_in(name, &std::env::var_os("PATH")?)
+}
+
+/*
+Find the executable named `name` in the directories listed in `path`.
+*/
+fn which_in(name: &Path, path: &std::ffi::OsStr) -> Option<PathBuf> {
+    std::env::split_paths(path)
+        .map(|dir| dir.join(name))
+        .find(|p| is_executable(p))
+}
+
+/*
+Whether `p` is a file (or a link to one) that someone may execute.
+*/
+fn is_executable(p: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    match p.metadata() {
+        Ok(md) => md.is_file() && md.permissions().mode() & 0o111 != 0,
+        Err(_) => false,
+    }
+}
+
+/*
+Substitute `{placeholder}`s in `template` with the values returned by
+`lookup`. Placeholders for which `lookup` returns `None` are left intact,
+and `{{` and `}}` are unescaped to `{` and `}`.
+*/
+fn expand_template<'a, F>(template: &str, lookup: F) -> String
+where
+    F: Fn(&str) -> Option<&'a str>,
+{
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(n) = rest.find(['{', '}']) {
+        out.push_str(&rest[..n]);
+        rest = &rest[n..];
+        if rest.starts_with("{{") || rest.starts_with("}}") {
+            out.push_str(&rest[..1]);
+            rest = &rest[2..];
+        } else if rest.starts_with('}') {
+            out.push('}');
+            rest = &rest[1..];
+        } else {
+            match rest.find('}') {
+                Some(end) => {
+                    let key = &rest[1..end];
+                    match lookup(key) {
+                        Some(val) => out.push_str(val),
+                        None => out.push_str(&rest[..=end]),
+                    }
+                    rest = &rest[end + 1..];
+                }
+                None => break,
+            }
+        }
+    }
+    out.push_str(rest);
+
+    out
+}
+
 /**
 Implement this trait for any types you want to use as `dmenu` selectors.
 
@@ -153,6 +499,106 @@ pub trait Item {
     to generate each `Item`'s dmenu line.
     */
     fn line(&self, key_len: usize) -> Vec<u8>;
+
+    /**
+    Return this `Item`'s weight. Items are displayed in order of
+    decreasing weight; items with equal weights are displayed most
+    recently chosen first (in a `Menu` given a `Recent`; see
+    `Menu::recent()`), and otherwise in the order in which they were
+    passed to `Dmx::select()`.
+
+    The default implementation returns 0, so unless you override this,
+    items are displayed in the order given. Override it for a few
+    important items to pin them near the top of the list (or give
+    some negative weights to sink them to the bottom).
+    */
+    fn weight(&self) -> i32 {
+        0
+    }
+
+    /**
+    Return a string that identifies this `Item` among the others in its
+    menu, for remembering it between runs (see the `store` module).
+
+    The default implementation uses the (first line of the) text returned
+    by `Item::line()`, which is fine as long as that doesn't change.
+    */
+    fn id(&self) -> String {
+        let line = self.line(0);
+        let line = String::from_utf8_lossy(&line);
+        line.lines().next().unwrap_or("").trim_end().to_owned()
+    }
+
+    /**
+    Return the name of an icon from the icon theme (like `firefox`), or
+    the path to an image file, to show next to this `Item`, for backends
+    that can show icons (currently just rofi). Others ignore it.
+
+    The default implementation returns `None`.
+    */
+    fn icon(&self) -> Option<String> {
+        None
+    }
+
+    /**
+    Return this `Item`'s line (without the trailing newline) in [Pango
+    markup](https://docs.gtk.org/Pango/pango_markup.html), for backends
+    that can display it (currently just rofi). This allows for things
+    like bold keys and dimmed descriptions, which look better than
+    padding in a proportional font:
+
+    ```
+    # use dm_x::{escape_markup, Item};
+    struct App { key: String, desc: String }
+
+    impl Item for App {
+        fn key_len(&self) -> usize { self.key.chars().count() }
+        fn line(&self, key_len: usize) -> Vec<u8> {
+            format!("{:w$}  {}\n", &self.key, &self.desc, w = key_len).into_bytes()
+        }
+        fn markup(&self, _: usize) -> Option<String> {
+            Some(format!(
+                "<b>{}</b>  <span alpha=\"60%\">{}</span>",
+                escape_markup(&self.key),
+                escape_markup(&self.desc)
+            ))
+        }
+    }
+    ```
+
+    Other backends display `Item::line()` as usual. When markup is shown,
+    the lines of items that don't have any are escaped, so they appear
+    as they would otherwise.
+
+    The default implementation returns `None`.
+    */
+    fn markup(&self, key_len: usize) -> Option<String> {
+        let _ = key_len;
+        None
+    }
+}
+
+/**
+Escape `text` so that it appears verbatim in Pango markup (see
+`Item::markup()`).
+
+```
+assert_eq!(dm_x::escape_markup("Tom & <Jerry>"), "Tom &amp; &lt;Jerry&gt;");
+```
+*/
+pub fn escape_markup(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '\'' => out.push_str("&apos;"),
+            '"' => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+    out
 }
 
 /**
@@ -198,7 +644,31 @@ The most basic possible implementation, this just presents a list of
 options verbatim with no "key" business or special formatting or
 any of that jazz.
 */
-impl Item for &str {
+impl Item for str {
+    fn key_len(&self) -> usize {
+        0
+    }
+    fn line(&self, _: usize) -> Vec<u8> {
+        self.as_bytes().to_vec()
+    }
+}
+
+/**
+`String`s are presented verbatim, like `str`s.
+*/
+impl Item for String {
+    fn key_len(&self) -> usize {
+        0
+    }
+    fn line(&self, _: usize) -> Vec<u8> {
+        self.as_bytes().to_vec()
+    }
+}
+
+/**
+So are `Cow<str>`s.
+*/
+impl Item for std::borrow::Cow<'_, str> {
     fn key_len(&self) -> usize {
         0
     }
@@ -207,140 +677,1463 @@ impl Item for &str {
     }
 }
 
+/**
+Paths are presented whole. Any parts that aren't UTF-8 are shown as
+U+FFFD REPLACEMENT CHARACTER, so two paths that differ only in those parts
+look the same.
+*/
+impl Item for Path {
+    fn key_len(&self) -> usize {
+        0
+    }
+    fn line(&self, _: usize) -> Vec<u8> {
+        self.to_string_lossy().into_owned().into_bytes()
+    }
+}
+
+impl Item for PathBuf {
+    fn key_len(&self) -> usize {
+        0
+    }
+    fn line(&self, _: usize) -> Vec<u8> {
+        self.as_path().line(0)
+    }
+}
+
+/**
+`OsStr`s are presented like paths, replacing anything that isn't UTF-8.
+*/
+impl Item for std::ffi::OsStr {
+    fn key_len(&self) -> usize {
+        0
+    }
+    fn line(&self, _: usize) -> Vec<u8> {
+        self.to_string_lossy().into_owned().into_bytes()
+    }
+}
+
+impl Item for std::ffi::OsString {
+    fn key_len(&self) -> usize {
+        0
+    }
+    fn line(&self, _: usize) -> Vec<u8> {
+        self.as_os_str().line(0)
+    }
+}
+
+/**
+A reference to an `Item` is the same `Item`, so that `Dmx::select()` can
+be handed anything that iterates over references to `Item`s (like a slice
+or a `&Vec`).
+*/
+impl<T: Item + ?Sized> Item for &T {
+    fn key_len(&self) -> usize {
+        (**self).key_len()
+    }
+    fn line(&self, key_len: usize) -> Vec<u8> {
+        (**self).line(key_len)
+    }
+    fn weight(&self) -> i32 {
+        (**self).weight()
+    }
+    fn id(&self) -> String {
+        (**self).id()
+    }
+    fn icon(&self) -> Option<String> {
+        (**self).icon()
+    }
+    fn markup(&self, key_len: usize) -> Option<String> {
+        (**self).markup(key_len)
+    }
+}
+
+/**
+Choosing from an iterator of `Item`s in one call, for scripts:
+
+```no_run
+# use dm_x::{Dmx, SelectExt};
+let dmx = Dmx::default();
+let words = "the quick brown fox jumps over the lazy dog".split(' ');
+if let Some(word) = words.filter(|w| w.len() > 3).pick("word:", &dmx).unwrap() {
+    println!("{}", word);
+}
+```
+
+This is implemented for every `Iterator` of `Item`s.
+*/
+pub trait SelectExt: Iterator + Sized
+where
+    Self::Item: Item,
+{
+    /**
+    Launch `dmenu` (as configured by `dmx`) to select one of the items,
+    and return it (or `None` if cancelled).
+    */
+    fn pick<S: AsRef<str>>(self, prompt: S, dmx: &Dmx) -> Result<Option<Self::Item>, DmxError> {
+        dmx.select_owned(prompt, self.collect())
+    }
+}
+
+impl<T> SelectExt for T
+where
+    T: Iterator,
+    T::Item: Item,
+{
+}
+
 /**
 This struct contains all the arguments necessary to pass to `dmenu` on the
 command line.
 */
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "config", derive(Serialize))]
 pub struct Dmx {
     /// Path to the `dmenu` binary. If it's in your system's `$PATH`, the
     /// default value of `"dmenu"` should work fine.`
     pub dmenu: PathBuf,
     /// Font to use, in xls or xfontsel format, depending on what your version
     /// of `dmenu` supports.
-    pub font: String,
+    pub font: Font,
     /// item background color
-    pub normal_bg: String,
+    pub normal_bg: Color,
     /// item foreground color
-    pub normal_fg: String,
+    pub normal_fg: Color,
     /// selected item background color
-    pub select_bg: String,
+    pub select_bg: Color,
     /// selected item foreground color
-    pub select_fg: String,
-}
-
-impl std::default::Default for Dmx {
-    fn default() -> Self {
-        Dmx {
-            dmenu: "dmenu".into(),
-            font: "LiberationMono-12".to_owned(),
-            normal_bg: "#222".to_owned(),
-            normal_fg: "#aaa".to_owned(),
-            select_bg: "#888".to_owned(),
-            select_fg: "#aff".to_owned(),
-        }
-    }
+    pub select_fg: Color,
+    /// If this is set, no menu is taller than this many lines (even if
+    /// `Menu::lines()` asks for more).
+    pub max_lines: Option<usize>,
+    /// If `true` (and `max_lines` is set), every menu is `max_lines` tall,
+    /// rather than sized to fit its items, so that it doesn't change size
+    /// from one menu to the next.
+    pub fixed_lines: bool,
+    /// If `true`, center the menu on the screen (`-c`, from the center
+    /// patch).
+    pub center: bool,
+    /// The width of the border around the menu, in pixels (`-bw`, from the
+    /// border patch).
+    pub border_width: Option<u32>,
+    /// The width of the menu, in pixels (`-z`, from the xyw patch).
+    pub width: Option<u32>,
+    /// Lay the items out in a grid with this many columns (`-g`, from the
+    /// grid patch). The number of lines passed with `-l` is then the
+    /// number of rows needed to show as many items as there would have
+    /// been lines.
+    pub columns: Option<u32>,
+    /// The X window ID of a window to embed the menu in (`-w`), for
+    /// programs that want the menu to appear inside their own window.
+    pub embed_window: Option<u64>,
+    /// The monitor to show the menu on, numbered from 0 (`-m`, or rofi's
+    /// `-monitor`). Backends that can't be given a monitor by number
+    /// show it wherever they usually do.
+    pub monitor: Option<u32>,
+    /// More arguments to add to the end of `dmenu`'s command line, for
+    /// options (like the `-x` and `-y` of the xyw patch) that have no
+    /// field of their own. They're passed as they are, and only to
+    /// `dmenu` (and `dmenu-wl`), not to other backends or a `command`.
+    pub extra_args: Vec<String>,
+    /// A complete replacement for the command line `Dmx` would otherwise
+    /// generate. The first element is the program to run, and the rest are
+    /// its arguments. The following placeholders will be substituted
+    /// wherever they appear:
+    ///
+    ///   * `{prompt}`: the prompt passed to `Dmx::select()`
+    ///   * `{lines}`: the number of lines to display
+    ///   * `{dmenu}`: the `dmenu` field
+    ///   * `{font}`, `{normal_bg}`, `{normal_fg}`, `{select_bg}`,
+    ///     `{select_fg}`: the values of the respective fields
+    ///
+    /// Use `{{` and `}}` for literal braces.
+    ///
+    /// If this is set, `backends` is ignored.
+    pub command: Option<Vec<String>>,
+    /// The menu programs to try, in order; the first that can be started
+    /// will be used. See the `backend` module documentation.
+    ///
+    /// When `absolute_binary` is set, only backends whose programs are
+    /// specified by absolute paths (a pinned `dmenu`, or a `Command`
+    /// template) will be launched.
+    pub backends: Vec<Backend>,
+    /// If `true`, ask the menu program to use fuzzy matching. This only
+    /// works with a `dmenu` that has a fuzzy matching patch (which is
+    /// detected automatically, whether it adds `-F` or `-z`) or a backend
+    /// with fuzzy matching built in;
+    /// otherwise, it's ignored (unless `fuzzy_required` is set).
+    pub fuzzy: bool,
+    /// If `true` (and `fuzzy` is set), a backend that can't do fuzzy
+    /// matching will not be used.
+    pub fuzzy_required: bool,
+    /// The text encoding `dmenu` expects. Item lines will be transcoded
+    /// to this before being written to `dmenu`.
+    pub encoding: Encoding,
+    /// If this is set, `dmenu` will be run with an otherwise empty
+    /// environment, passing through only the variables named here
+    /// (those that are set, anyway). `ENV_ALLOWLIST` is a reasonable
+    /// starting point.
+    ///
+    /// This is for programs that handle secrets and don't want to leak
+    /// them into a subprocess they don't control.
+    pub env_allowlist: Option<Vec<String>>,
+    /// Environment variables to set (or override) for the menu program,
+    /// like `DISPLAY = ":1"` to show menus on another display. These are
+    /// set even if they aren't in `env_allowlist`.
+    pub env: BTreeMap<String, String>,
+    /// Environment variables to remove from the menu program's
+    /// environment, like `WAYLAND_DISPLAY` to make it use XWayland.
+    pub env_remove: Vec<String>,
+    /// If this is set, menus with at least this many lines are written to
+    /// a temporary file (readable only by the current user), which is
+    /// given to the menu program as its standard input, rather than being
+    /// written down a pipe. This is much faster for truly enormous menus.
+    /// The file is removed as soon as the menu is done with.
+    pub spool_threshold: Option<usize>,
+    /// What to do with the menu program's standard error.
+    pub stderr: StderrMode,
+    /// The flags used to pass each of the above settings to `dmenu`.
+    pub flag_map: FlagMap,
+    /// If this is set, showing a menu will be retried when it fails in a
+    /// way that looks transient.
+    pub retry: Option<RetryPolicy>,
+    /// If `true`, refuse to launch a menu program that isn't specified by
+    /// an absolute path, so that a hostile `$PATH` can't substitute its own
+    /// `dmenu`. Such a program isn't run at all, not even to find out which
+    /// options it takes. `Dmx::pin_binary()` will resolve a bare program
+    /// name to an absolute path and set this.
+    pub absolute_binary: bool,
+    /// If this is set, a line describing every menu spawned (and every
+    /// launch reported through `Dmx::audit_launch()`) is appended to this
+    /// file. Each line holds tab-separated fields:
+    ///
+    /// ```text
+    /// <seconds since the epoch>.<millis>  <event>  <result>  <item count>  <argv>
+    /// ```
+    ///
+    /// where `<event>` is `menu` or `launch`, `<result>` is one of
+    /// `selected`, `cancelled`, `error`, or `-` (for launches), and `<argv>`
+    /// is the command line, quoted the way a POSIX shell would need it.
+    /// (A menu whose line can't be written still returns what was chosen;
+    /// with the `tracing` feature, the failure is logged.)
+    pub audit_log: Option<PathBuf>,
+    /// If `true`, errors showing a menu (like the menu program not being
+    /// installed) are also shown as desktop notifications, with
+    /// `notify-send`, for programs run from a key binding, whose standard
+    /// error goes nowhere anyone will see it. (`notify-send` is run with
+    /// `env` set, like the menu program.)
+    pub notify_errors: bool,
+    /// Settings for particular backends, by name (like `"rofi"`), used in
+    /// place of the ones above while that backend is showing a menu.
+    pub backend_overrides: BTreeMap<String, BackendOverrides>,
+    /// If this is set, it's run on the `Command` for every menu program,
+    /// right before it's spawned; see `Dmx::with_command_hook()`. It can't
+    /// be set from a configuration file.
+    #[cfg_attr(feature = "config", serde(skip))]
+    pub command_hook: Option<CommandHook>,
 }
 
-impl Dmx {
-    /*
-    Generate a `Command` to pass to `dmenu`.
-    */
-    fn cmd(&self, prompt: &str, n_items: usize) -> Command {
-        let mut c = Command::new(&self.dmenu);
-        c.args([
-            "-l",
-            &n_items.to_string(),
-            "-p",
-            prompt,
-            "-fn",
-            &self.font,
-            "-nb",
-            &self.normal_bg,
-            "-nf",
-            &self.normal_fg,
-            "-sb",
-            &self.select_bg,
-            "-sf",
-            &self.select_fg,
-        ])
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .stderr(Stdio::inherit());
-
-        c
-    }
+/**
+A function run on the `Command` for a menu program, just before it's
+spawned. Two hooks are equal only if they're the same hook.
+*/
+#[derive(Clone)]
+pub struct CommandHook(Arc<dyn Fn(&mut Command) + Send + Sync>);
 
+impl CommandHook {
     /**
-    Launch `dmenu` to select an `Item`.
-
-    Returns the slice index of the `Item` selected, or `None` if cancelled.
+    Wrap `f` as a hook.
     */
-    pub fn select<S, I>(&self, prompt: S, items: &[I]) -> Result<Option<usize>, String>
+    pub fn new<F>(f: F) -> CommandHook
     where
-        S: AsRef<str>,
-        I: Item,
+        F: Fn(&mut Command) + Send + Sync + 'static,
     {
-        let klen: usize = items.iter().map(|x| x.key_len()).max().unwrap_or(0);
+        CommandHook(Arc::new(f))
+    }
+}
 
-        let output: Vec<Vec<u8>> = items
-            .iter()
-            .map(|x| {
-                let mut v = x.line(klen);
-                if Some(&NEWLINE) == v.last() {
-                    v
-                } else {
-                    v.push(NEWLINE);
-                    v
-                }
-            })
-            .collect();
+impl std::fmt::Debug for CommandHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("CommandHook(..)")
+    }
+}
 
-        let mut child = self
-            .cmd(prompt.as_ref(), output.len())
-            .spawn()
-            .map_err(|e| format!("Unable to launch dmenu: {}", &e))?;
+impl PartialEq for CommandHook {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
 
-        {
-            let mut stdin = child.stdin.take().unwrap();
-            for line in output.iter() {
-                stdin
-                    .write_all(line)
-                    .map_err(|e| format!("Error writing to dmenu subprocess: {}", &e))?;
-            }
-            stdin
-                .flush()
-                .map_err(|e| format!("Error writing to dmenu subprocess: {}", &e))?;
-        }
+impl Eq for CommandHook {}
 
-        let mut stdout = child.stdout.take().unwrap();
-        child
-            .wait()
-            .map_err(|e| format!("dmenu subprocess returned error: {}", &e))?;
-        let mut choice_bytes: Vec<u8> = Vec::new();
-        let _ = stdout
-            .read_to_end(&mut choice_bytes)
-            .map_err(|e| format!("Error reading dmenu output: {}", &e))?;
+/*
+Why an attempt to show a menu failed, and whether it's worth trying again.
+*/
+struct Failure {
+    err: DmxError,
+    transient: bool,
+}
+
+impl<E: Into<DmxError>> From<E> for Failure {
+    fn from(err: E) -> Failure {
+        Failure {
+            err: err.into(),
+            transient: false,
+        }
+    }
+}
+
+/**
+How to retry showing a menu when the attempt fails in a way that looks
+like it might resolve itself shortly, like the menu program being unable
+to connect to the display right after the session is unlocked or resumed
+(or the system being briefly unable to start a new process).
+
+The delay before each retry doubles, up to `max_delay_ms`.
+*/
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "config", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "config", serde(default))]
+pub struct RetryPolicy {
+    /// Maximum number of retries (after the first attempt).
+    pub attempts: u32,
+    /// Delay before the first retry, in milliseconds.
+    pub initial_delay_ms: u64,
+    /// Maximum delay between retries, in milliseconds.
+    pub max_delay_ms: u64,
+}
+
+impl std::default::Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            attempts: 3,
+            initial_delay_ms: 250,
+            max_delay_ms: 2000,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /*
+    How long to wait before retry number `n` (counting from 0).
+    */
+    fn delay(&self, n: u32) -> std::time::Duration {
+        let ms = self
+            .initial_delay_ms
+            .saturating_mul(1u64.checked_shl(n).unwrap_or(u64::MAX))
+            .min(self.max_delay_ms);
+        std::time::Duration::from_millis(ms)
+    }
+}
+
+/**
+The command line flags `Backend::Dmenu` uses to pass each setting, for
+`dmenu` forks that have renamed some of them. The defaults are stock
+`dmenu`'s (like `-fn` for the font); a flag set to the empty string is
+left out entirely.
+
+```
+let mut dmx = dm_x::Dmx::default();
+dmx.flag_map.font = "--fn".to_owned();
+```
+*/
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "config", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "config", serde(default))]
+pub struct FlagMap {
+    /// The number of lines (`-l`).
+    pub lines: String,
+    /// The prompt (`-p`).
+    pub prompt: String,
+    /// `Dmx::font` (`-fn`).
+    pub font: String,
+    /// `Dmx::normal_bg` (`-nb`).
+    pub normal_bg: String,
+    /// `Dmx::normal_fg` (`-nf`).
+    pub normal_fg: String,
+    /// `Dmx::select_bg` (`-sb`).
+    pub select_bg: String,
+    /// `Dmx::select_fg` (`-sf`).
+    pub select_fg: String,
+}
+
+impl std::default::Default for FlagMap {
+    fn default() -> Self {
+        FlagMap {
+            lines: "-l".to_owned(),
+            prompt: "-p".to_owned(),
+            font: "-fn".to_owned(),
+            normal_bg: "-nb".to_owned(),
+            normal_fg: "-nf".to_owned(),
+            select_bg: "-sb".to_owned(),
+            select_fg: "-sf".to_owned(),
+        }
+    }
+}
+
+/**
+What to do with anything the menu program writes to its standard error.
+*/
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "config", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "config", serde(rename_all = "lowercase"))]
+pub enum StderrMode {
+    /// Let it go to this process's standard error (the default).
+    #[default]
+    Inherit,
+    /// Collect it, and if the menu program exits unsuccessfully, return
+    /// it as part of the error.
+    Capture,
+    /// Collect it and log it with
+    /// [`tracing`](https://crates.io/crates/tracing) (if the `tracing`
+    /// feature is enabled; otherwise it is just printed to this process's
+    /// standard error, prefixed with `dm_x:`).
+    Log,
+    /// Throw it away.
+    Discard,
+}
+
+/**
+What became of one of the configuration files `Dmx::automagiconf()`
+looks for, as `Dmx::automagiconf_verbose()` reports it.
+*/
+#[doc(cfg(feature = "config"))]
+#[cfg(feature = "config")]
+#[derive(Debug)]
+pub enum ConfigOutcome {
+    /// There's no file there.
+    Missing,
+    /// The file couldn't be read, or has a mistake in it.
+    Failed(DmxError),
+    /// The file's settings are used.
+    Used,
+}
+
+#[cfg(feature = "config")]
+impl std::fmt::Display for ConfigOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigOutcome::Missing => write!(f, "not found"),
+            ConfigOutcome::Failed(e) => write!(f, "not used: {}", e),
+            ConfigOutcome::Used => write!(f, "used"),
+        }
+    }
+}
+
+/**
+The environment variables a graphical menu needs in order to start up and
+display text sensibly; a reasonable value for `Dmx::env_allowlist`.
+*/
+pub const ENV_ALLOWLIST: &[&str] = &[
+    "DISPLAY",
+    "XAUTHORITY",
+    "WAYLAND_DISPLAY",
+    "XDG_RUNTIME_DIR",
+    "HOME",
+    "PATH",
+    "LANG",
+    "LC_ALL",
+    "LC_CTYPE",
+];
+
+/**
+A configuration file that explains every setting in comments (and sets
+the ones that have defaults to them), as `Dmx::write_default_config()`
+writes it.
+*/
+#[doc(cfg(feature = "config"))]
+#[cfg(feature = "config")]
+pub const DEFAULT_CONFIG: &str = include_str!("default_config.toml");
+
+impl std::default::Default for Dmx {
+    fn default() -> Self {
+        Dmx {
+            dmenu: "dmenu".into(),
+            font: Font::new("LiberationMono-12"),
+            normal_bg: Color::Rgb(0x22, 0x22, 0x22),
+            normal_fg: Color::Rgb(0xaa, 0xaa, 0xaa),
+            select_bg: Color::Rgb(0x88, 0x88, 0x88),
+            select_fg: Color::Rgb(0xaa, 0xff, 0xff),
+            max_lines: None,
+            fixed_lines: false,
+            center: false,
+            border_width: None,
+            width: None,
+            columns: None,
+            embed_window: None,
+            monitor: None,
+            extra_args: Vec::new(),
+            command: None,
+            backends: vec![Backend::Dmenu],
+            fuzzy: false,
+            fuzzy_required: false,
+            encoding: Encoding::Utf8,
+            env_allowlist: None,
+            env: BTreeMap::new(),
+            env_remove: Vec::new(),
+            spool_threshold: None,
+            stderr: StderrMode::Inherit,
+            flag_map: FlagMap::default(),
+            retry: None,
+            absolute_binary: false,
+            audit_log: None,
+            notify_errors: false,
+            backend_overrides: BTreeMap::new(),
+            command_hook: None,
+        }
+    }
+}
+
+impl Dmx {
+    /*
+    Generate a `Command` to run the given backend.
+    */
+    fn cmd(&self, backend: &Backend, prompt: &str, lines: usize) -> Command {
+        let overrides = self.backend_overrides.get(backend.name());
+        let dmx = match overrides {
+            Some(overrides) => Cow::Owned(overrides.apply_to(self)),
+            None => Cow::Borrowed(self),
+        };
+        let lines = match dmx.max_lines {
+            Some(max) if self.fixed_lines => max,
+            Some(max) => lines.min(max),
+            None => lines,
+        };
+        let mut c = backend.command(&dmx, prompt, lines);
+        if let Some(overrides) = overrides {
+            c.args(&overrides.args);
+        }
+        self.restrict_env(&mut c);
+        for var in self.env_remove.iter() {
+            c.env_remove(var);
+        }
+        c.envs(self.env.iter());
+
+        c.stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(match self.stderr {
+                // If retrying, stderr is needed to detect transient failures.
+                // dmenu's is needed to tell a usage message (from options it
+                // doesn't accept) from the user cancelling, since it exits
+                // with 1 either way.
+                _ if self.retry.is_some() => Stdio::piped(),
+                _ if matches!(backend, Backend::Dmenu | Backend::DmenuWl) => Stdio::piped(),
+                StderrMode::Inherit => Stdio::inherit(),
+                StderrMode::Discard => Stdio::null(),
+                StderrMode::Capture | StderrMode::Log => Stdio::piped(),
+            });
+
+        c
+    }
+
+    /*
+    Apply `env_allowlist` (if it's set) to the environment of `c`.
+    */
+    pub(crate) fn restrict_env(&self, c: &mut Command) {
+        self.restrict_env_in(c, |var| std::env::var_os(var))
+    }
+
+    /*
+    `restrict_env()`, with this process's environment given by `var_os`.
+    */
+    pub(crate) fn restrict_env_in<F>(&self, c: &mut Command, var_os: F)
+    where
+        F: Fn(&str) -> Option<std::ffi::OsString>,
+    {
+        if let Some(allowed) = &self.env_allowlist {
+            c.env_clear();
+            for var in allowed.iter() {
+                if let Some(val) = var_os(var) {
+                    c.env(var, val);
+                }
+            }
+        }
+    }
+
+    /*
+    The backends to try, in order.
+    */
+    fn chain(&self) -> Vec<Backend> {
+        match &self.command {
+            Some(argv) if !argv.is_empty() => vec![Backend::Command(argv.clone())],
+            _ => self.backends.clone(),
+        }
+    }
+
+    /**
+    Resolve the `dmenu` binary to an absolute path by searching `$PATH`
+    (if it isn't already absolute), and require the binary to be specified
+    by an absolute path from now on.
+
+    Do this once, early (like right after reading your configuration),
+    so that any later changes to `$PATH` won't affect which binary gets
+    run.
+    */
+    pub fn pin_binary(&mut self) -> Result<(), DmxError> {
+        if !self.dmenu.is_absolute() {
+            self.dmenu =
+                which(&self.dmenu).ok_or_else(|| DmxError::BinaryNotFound(self.dmenu.clone()))?;
+        }
+        self.absolute_binary = true;
+        Ok(())
+    }
+
+    /**
+    Check the settings for mistakes that would keep the menu from
+    working, and return every problem found (so nothing, if they all
+    look usable): a menu program not being installed (or not being
+    executable; each backend's is looked for in the `$PATH` it will be
+    given), a blank font or one fontconfig doesn't have (see
+    `Font::is_installed()`), and a `max_lines` of 0. (Colors are checked
+    as they're parsed; see `Color`.)
+
+    Without this, most of these only show up as `dmenu` refusing to
+    start, so it's worth calling after loading a configuration file.
+
+    ```no_run
+    # use dm_x::Dmx;
+    for problem in Dmx::default().validate() {
+        eprintln!("{}", problem);
+    }
+    ```
+    */
+    pub fn validate(&self) -> Vec<DmxError> {
+        let mut problems: Vec<DmxError> = Vec::new();
+
+        let programs: Vec<(String, PathBuf)> = self
+            .chain()
+            .iter()
+            .filter(|backend| !backend.in_process())
+            .map(|backend| (backend.to_string(), backend.program(self)))
+            .filter(|(_, program)| !program.as_os_str().is_empty())
+            .collect();
+        let path = self.child_var("PATH");
+        for (backend, program) in programs.iter() {
+            if self.absolute_binary && !program.is_absolute() {
+                problems.push(DmxError::NotAbsolute {
+                    backend: backend.clone(),
+                    program: program.clone(),
+                });
+                continue;
+            }
+            // Like `Command`, only look in $PATH for bare names.
+            let found = match program.components().count() {
+                1 if !program.is_absolute() => {
+                    path.as_ref().and_then(|path| which_in(program, path))
+                }
+                _ => Some(program.clone()).filter(|p| p.exists()),
+            };
+            match found {
+                None => problems.push(DmxError::BinaryNotFound(program.clone())),
+                Some(path) if !is_executable(&path) => problems.push(DmxError::NotExecutable(path)),
+                Some(_) => {}
+            }
+        }
+
+        let font = self.font.name();
+        if font.trim().is_empty() || font.contains('\n') {
+            problems.push(DmxError::ConfigParse(format!("Invalid font \"{}\".", font)));
+        } else if self.font.is_installed() == Some(false) {
+            problems.push(DmxError::ConfigParse(format!(
+                "Font \"{}\" isn't installed.",
+                font
+            )));
+        }
+        if self.max_lines == Some(0) {
+            problems.push(DmxError::ConfigParse(
+                "The number of lines must be at least 1.".to_owned(),
+            ));
+        }
+        problems
+    }
+
+    /**
+    Run `hook` on the `Command` for each menu program, right before it's
+    spawned, to add arguments, environment variables, or whatever else
+    this crate doesn't know how to ask for. It replaces any hook already
+    set.
+
+    ```no_run
+    # use dm_x::Dmx;
+    let dmx = Dmx::default().with_command_hook(|cmd| {
+        cmd.arg("-i");
+    });
+    ```
+    */
+    pub fn with_command_hook<F>(mut self, hook: F) -> Dmx
+    where
+        F: Fn(&mut Command) + Send + Sync + 'static,
+    {
+        self.command_hook = Some(CommandHook::new(hook));
+        self
+    }
+
+    /**
+    Return the version of the `dmenu` binary, as it reports it when run
+    with `-v` (like `dmenu-5.2`), or `None` if it doesn't (or isn't run,
+    because `absolute_binary` is set and it isn't an absolute path).
+
+    This is found out once per binary (and kept for the life of the
+    process); it's used to adapt the command line to older versions.
+    */
+    pub fn dmenu_version(&self) -> Option<String> {
+        if !backend::may_probe(self, &self.dmenu) {
+            return None;
+        }
+        probe::version(&self.dmenu)
+    }
+
+    /**
+    Launch `dmenu` to select an `Item`.
+
+    `items` can be anything that iterates over `Item`s (or references to
+    them): a slice, a `Vec`, the values of a map, or an iterator.
+
+    Returns the index of the `Item` selected (its position in `items`),
+    or `None` if cancelled. For more options (like a message shown above
+    the items), build a `Menu` instead.
+    */
+    pub fn select<S, T>(&self, prompt: S, items: T) -> Result<Option<usize>, DmxError>
+    where
+        S: AsRef<str>,
+        T: IntoIterator,
+        T::Item: Item,
+    {
+        let items: Vec<T::Item> = items.into_iter().collect();
+        Menu::new(prompt).items(&items).show(self)
+    }
+
+    /**
+    Launch `dmenu` to select an `Item`, and return the item itself (or
+    `None` if cancelled), rather than its index.
+
+    ```no_run
+    # use dm_x::Dmx;
+    let flavors = &["Vanilla", "Chocolate", "Strawberry"];
+    if let Some(flavor) = Dmx::default().select_value("flavor:", flavors).unwrap() {
+        println!("One scoop of {}, coming up.", flavor);
+    }
+    ```
+    */
+    pub fn select_value<'a, S, I>(
+        &self,
+        prompt: S,
+        items: &'a [I],
+    ) -> Result<Option<&'a I>, DmxError>
+    where
+        S: AsRef<str>,
+        I: Item,
+    {
+        Ok(self.select(prompt, items)?.map(|n| &items[n]))
+    }
+
+    /**
+    Like `Dmx::select_value()`, but returns a clone of the item chosen.
+    */
+    pub fn select_cloned<S, I>(&self, prompt: S, items: &[I]) -> Result<Option<I>, DmxError>
+    where
+        S: AsRef<str>,
+        I: Item + Clone,
+    {
+        Ok(self.select_value(prompt, items)?.cloned())
+    }
+
+    /**
+    Launch `dmenu` to select one of `items`, and return it, moved out of
+    the `Vec` (or `None` if cancelled).
+    */
+    pub fn select_owned<S, I>(&self, prompt: S, mut items: Vec<I>) -> Result<Option<I>, DmxError>
+    where
+        S: AsRef<str>,
+        I: Item,
+    {
+        Ok(self.select(prompt, &items)?.map(|n| items.swap_remove(n)))
+    }
+
+    /**
+    Launch `dmenu` to select one of the variants of the enum `E` (in the
+    order `strum`'s `EnumIter` gives them, shown with their `Display`
+    implementations), and return it (or `None` if cancelled).
+
+    ```no_run
+    # use dm_x::Dmx;
+    #[derive(strum::Display, strum::EnumIter)]
+    enum Governor {
+        Performance,
+        Powersave,
+    }
+
+    let governor = Dmx::default().select_variant::<Governor, _>("governor:").unwrap();
+    ```
+    */
+    #[cfg(feature = "strum")]
+    #[doc(cfg(feature = "strum"))]
+    pub fn select_variant<E, S>(&self, prompt: S) -> Result<Option<E>, DmxError>
+    where
+        E: strum::IntoEnumIterator + std::fmt::Display,
+        S: AsRef<str>,
+    {
+        let mut variants: Vec<E> = E::iter().collect();
+        let names = variants.iter().map(|v| v.to_string());
+        Ok(self.select(prompt, names)?.map(|n| variants.swap_remove(n)))
+    }
+
+    /**
+    Launch `dmenu` to select an `Item`, or to type any answer at all: text
+    that doesn't match one of the items is returned as
+    `Selection::Custom`.
+
+    ```no_run
+    # use dm_x::{Dmx, Selection};
+    let hosts = &["localhost", "example.com"];
+    let host = match Dmx::default().select_or_input("ssh to:", hosts).unwrap() {
+        Selection::Picked(n) => hosts[n].to_owned(),
+        Selection::Custom(host) => host,
+        Selection::Cancelled => return,
+    };
+    ```
+    */
+    pub fn select_or_input<S, T>(&self, prompt: S, items: T) -> Result<Selection, DmxError>
+    where
+        S: AsRef<str>,
+        T: IntoIterator,
+        T::Item: Item,
+    {
+        let items: Vec<T::Item> = items.into_iter().collect();
+        Menu::new(prompt)
+            .items(&items)
+            .allow_custom()
+            .show_selection(self)
+    }
+
+    /**
+    Ask the user to type a line of text, with a menu that has no items.
+    Returns `None` if the menu was cancelled.
+
+    ```no_run
+    # use dm_x::Dmx;
+    if let Some(name) = Dmx::default().input("new branch:").unwrap() {
+        println!("git switch -c {}", name);
+    }
+    ```
+    */
+    pub fn input<S: AsRef<str>>(&self, prompt: S) -> Result<Option<String>, DmxError> {
+        match self.select_or_input(prompt, std::iter::empty::<&str>())? {
+            Selection::Custom(text) => Ok(Some(text)),
+            _ => Ok(None),
+        }
+    }
+
+    /**
+    Ask the user to type a number in `range`, asking again (with a message
+    saying what was wrong) until they do. Returns `None` if the menu was
+    cancelled.
+
+    ```no_run
+    # use dm_x::Dmx;
+    if let Some(n) = Dmx::default().input_number("volume:", 0..=100u32).unwrap() {
+        println!("pactl set-sink-volume @DEFAULT_SINK@ {}%", n);
+    }
+    ```
+    */
+    pub fn input_number<S, T, R>(&self, prompt: S, range: R) -> Result<Option<T>, DmxError>
+    where
+        S: AsRef<str>,
+        T: std::str::FromStr + PartialOrd,
+        R: std::ops::RangeBounds<T>,
+    {
+        let mut message: Option<String> = None;
+        loop {
+            let mut menu: Menu<&str> = Menu::new(prompt.as_ref()).allow_custom();
+            if let Some(message) = &message {
+                menu = menu.message(message);
+            }
+            let text = match menu.show_selection(self)? {
+                Selection::Custom(text) => text,
+                _ => return Ok(None),
+            };
+            match text.trim().parse::<T>() {
+                Ok(n) if range.contains(&n) => return Ok(Some(n)),
+                Ok(_) => message = Some(format!("{} is out of range.", text.trim())),
+                Err(_) => message = Some(format!("\"{}\" isn't a number.", text.trim())),
+            }
+        }
+    }
+
+    /**
+    Like `Dmx::select()`, but tells apart the menu being cancelled and the
+    user entering text that isn't one of the items (which `select()` both
+    return as `None`).
+
+    ```no_run
+    # use dm_x::{Dmx, Selection};
+    let colors = &["red", "green", "blue"];
+    match Dmx::default().selection("color:", colors).unwrap() {
+        Selection::Picked(n) => println!("{}", colors[n]),
+        Selection::Custom(other) => println!("{} isn't on the list.", other),
+        Selection::Cancelled => {}
+    }
+    ```
+    */
+    pub fn selection<S, T>(&self, prompt: S, items: T) -> Result<Selection, DmxError>
+    where
+        S: AsRef<str>,
+        T: IntoIterator,
+        T::Item: Item,
+    {
+        let items: Vec<T::Item> = items.into_iter().collect();
+        Menu::new(prompt).items(&items).show_selection(self)
+    }
+
+    /**
+    Launch `dmenu` to select any number of `Item`s (from anything
+    `Dmx::select()` accepts).
+
+    Returns the indices of the `Item`s selected, in the order they were
+    chosen (which is empty if the menu was cancelled). See
+    `Menu::show_multi()` for how each backend does this.
+    */
+    pub fn multi_select<S, T>(&self, prompt: S, items: T) -> Result<Vec<usize>, DmxError>
+    where
+        S: AsRef<str>,
+        T: IntoIterator,
+        T::Item: Item,
+    {
+        let items: Vec<T::Item> = items.into_iter().collect();
+        Menu::new(prompt).items(&items).show_multi(self)
+    }
+
+    /**
+    Launch `dmenu` to select an `Item`, again and again until the menu is
+    cancelled, and return the indices of the `Item`s chosen, in order. If
+    `remove_chosen` is set, items already chosen are left out of the
+    menu, and it stops when they've all been chosen.
+
+    This works with any backend, unlike `Dmx::multi_select()`, which needs
+    the menu program's help.
+    */
+    pub fn select_loop<S, I>(
+        &self,
+        prompt: S,
+        items: &[I],
+        remove_chosen: bool,
+    ) -> Result<Vec<usize>, DmxError>
+    where
+        S: AsRef<str>,
+        I: Item,
+    {
+        let mut chosen: Vec<usize> = Vec::new();
+        loop {
+            let offered: Vec<usize> = (0..items.len())
+                .filter(|n| !remove_chosen || !chosen.contains(n))
+                .collect();
+            if offered.is_empty() {
+                return Ok(chosen);
+            }
+            match self.select(prompt.as_ref(), offered.iter().map(|&n| &items[n]))? {
+                Some(n) => chosen.push(offered[n]),
+                None => return Ok(chosen),
+            }
+        }
+    }
+
+    /**
+    Launch `dmenu` to select one of `actions` by its `Item`, and call its
+    function, returning what that returns (or `None` if the menu was
+    cancelled).
+
+    ```no_run
+    # use dm_x::Dmx;
+    let dmx = Dmx::default();
+    dmx.dispatch("do:", &[
+        ("hello", &|| println!("Hello!")),
+        ("goodbye", &|| println!("Goodbye!")),
+    ])
+    .unwrap();
+    ```
+    */
+    pub fn dispatch<S, I, R>(
+        &self,
+        prompt: S,
+        actions: &[(I, &dyn Fn() -> R)],
+    ) -> Result<Option<R>, DmxError>
+    where
+        S: AsRef<str>,
+        I: Item,
+    {
+        let n = self.select(prompt, actions.iter().map(|(item, _)| item))?;
+        Ok(n.map(|n| (actions[n].1)()))
+    }
+
+    /**
+    Ask the user to confirm something, with a menu of "no" and "yes" (in
+    that order, so a hasty Enter doesn't confirm it). Returns `true` only
+    if "yes" was chosen.
+    */
+    pub fn confirm<S: AsRef<str>>(&self, prompt: S) -> Result<bool, DmxError> {
+        Ok(self.select(prompt, &["no", "yes"])? == Some(1))
+    }
+
+    /*
+    Return the value the environment variable `var` will have in the
+    menu's environment.
+    */
+    pub(crate) fn child_var(&self, var: &str) -> Option<std::ffi::OsString> {
+        if self.env_remove.iter().any(|v| v == var) {
+            return None;
+        }
+        if let Some(val) = self.env.get(var) {
+            return Some(val.into()).filter(|v: &std::ffi::OsString| !v.is_empty());
+        }
+        if let Some(allowed) = &self.env_allowlist {
+            if !allowed.iter().any(|v| v == var) {
+                return None;
+            }
+        }
+        std::env::var_os(var).filter(|v| !v.is_empty())
+    }
+
+    /*
+    Pass `result` through, showing its error as a notification first if
+    `notify_errors` is set.
+    */
+    fn reported<T>(&self, result: Result<T, DmxError>) -> Result<T, DmxError> {
+        if let (Err(msg), true) = (&result, self.notify_errors) {
+            self.notify_send(&msg.to_string());
+        }
+        result
+    }
+
+    /*
+    Show `msg` in a desktop notification, titled with this program's name,
+    with `env` set for `notify-send` as it is for the menu program. If that
+    doesn't work, there's nowhere left to report it, so it isn't.
+    */
+    fn notify_send(&self, msg: &str) {
+        let program = std::env::args_os()
+            .next()
+            .and_then(|a| {
+                Path::new(&a)
+                    .file_name()
+                    .map(|n| n.to_string_lossy().into_owned())
+            })
+            .unwrap_or_else(|| "dm_x".to_owned());
+        let _ = Command::new("notify-send")
+            .args(["--urgency=critical", "--app-name", &program, &program, msg])
+            .envs(self.env.iter())
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+    }
+
+    /*
+    Everything `choose_hinted()` does, short of reporting errors.
+    */
+    fn show(
+        &self,
+        prompt: &str,
+        lines: usize,
+        input: &[Vec<u8>],
+        hints: &MenuHints,
+    ) -> Result<Option<Vec<u8>>, DmxError> {
+        let output: Vec<Vec<u8>> = input.iter().map(|l| self.encoding.encode(l)).collect();
+        let mut cmd: Option<Command> = None;
+        let spooled = matches!(self.spool_threshold, Some(n) if output.len() >= n);
+        let mut spool: Option<spool::Spool> = None;
+
+        let mut retry = 0;
+        let result = loop {
+            let attempt = self
+                .start(
+                    prompt,
+                    lines,
+                    &output,
+                    hints,
+                    spooled.then_some(&mut spool),
+                    &mut cmd,
+                )
+                .and_then(|(child, backend)| {
+                    self.exchange(child, &backend, prompt, &output, hints)
+                });
+            match (attempt, &self.retry) {
+                (Err(f), Some(policy)) if f.transient && retry < policy.attempts => {
+                    std::thread::sleep(policy.delay(retry));
+                    retry += 1;
+                }
+                (attempt, _) => break attempt.map_err(|f| f.err),
+            }
+        };
+        // Hand back the original (unencoded) line that was chosen, if it
+        // was one of them.
+        let result = result.map(|choice| {
+            choice.and_then(|choice| {
+                per_line(choice, hints.multi, |choice| {
+                    Some(match output.iter().position(|line| *line == choice) {
+                        Some(n) => input[n].clone(),
+                        None => self.encoding.decode(&choice).into_bytes(),
+                    })
+                })
+            })
+        });
+
+        if let Some(path) = &self.audit_log {
+            let category = match &result {
+                Ok(Some(_)) => "selected",
+                Ok(None) => "cancelled",
+                Err(_) => "error",
+            };
+            let argv: Vec<_> = match &cmd {
+                Some(cmd) => std::iter::once(cmd.get_program())
+                    .chain(cmd.get_args())
+                    .map(|a| a.to_string_lossy())
+                    .collect(),
+                None => Vec::new(),
+            };
+            // The menu has been shown either way, so not being able to say
+            // so isn't a reason to throw away what was chosen.
+            if let Err(e) = audit::record(path, "menu", category, Some(output.len()), &argv) {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(target: "dm_x", "Couldn't write to the audit log: {}", e);
+                #[cfg(not(feature = "tracing"))]
+                let _ = e;
+            }
+        }
+
+        result
+    }
+
+    /*
+    Start the first backend in the chain that can be started. The command
+    line of the last backend tried is left in `last_cmd`. If `spool` is
+    given, the menu reads its input (with `hints` added, as it would have
+    been fed) from a file, which is left there. (Otherwise, `output` is
+    only needed for backends that take their items on the command line.)
+    A backend that runs in this process has no `Child`.
+    */
+    fn start(
+        &self,
+        prompt: &str,
+        lines: usize,
+        output: &[Vec<u8>],
+        hints: &MenuHints,
+        mut spool: Option<&mut Option<spool::Spool>>,
+        last_cmd: &mut Option<Command>,
+    ) -> Result<(Option<Child>, Backend), Failure> {
+        let mut errors: Vec<DmxError> = Vec::new();
+        let mut transient = false;
+
+        for backend in self.chain().into_iter() {
+            // Before anything else, so that nothing is run (even to probe
+            // it) by a bare name.
+            if self.absolute_binary && !backend.in_process() {
+                let program = backend.program(self);
+                if !program.is_absolute() {
+                    errors.push(DmxError::NotAbsolute {
+                        backend: backend.to_string(),
+                        program,
+                    });
+                    continue;
+                }
+            }
+            if let Err(e) = backend.check(self) {
+                errors.push(e);
+                continue;
+            }
+            if backend.in_process() {
+                *last_cmd = None;
+                return Ok((None, backend));
+            }
+            let cmd = last_cmd.insert(self.cmd(&backend, prompt, lines));
+            backend.apply_hints(cmd, hints);
+            if backend.items_as_args() {
+                use std::os::unix::ffi::OsStrExt;
+                for (n, line) in backend.annotate(output, hints).iter().enumerate() {
+                    let line = line.strip_suffix(b"\n").unwrap_or(line);
+                    cmd.arg(n.to_string())
+                        .arg(std::ffi::OsStr::from_bytes(line));
+                }
+                cmd.stdin(Stdio::null());
+            } else if let Some(spool) = spool.as_mut() {
+                let spool = spool.insert(spool::Spool::create(&backend.annotate(output, hints))?);
+                cmd.stdin(spool.stdin()?);
+            }
+            if let Some(hook) = &self.command_hook {
+                (hook.0)(cmd);
+            }
+            // Checked last, so a hook can't sneak a bare name past it.
+            if self.absolute_binary && !Path::new(cmd.get_program()).is_absolute() {
+                errors.push(DmxError::NotAbsolute {
+                    backend: backend.to_string(),
+                    program: cmd.get_program().into(),
+                });
+                continue;
+            }
+            match cmd.spawn() {
+                Ok(child) => return Ok((Some(child), backend)),
+                Err(e) => {
+                    use std::io::ErrorKind;
+                    transient |= matches!(
+                        e.kind(),
+                        ErrorKind::WouldBlock | ErrorKind::Interrupted | ErrorKind::OutOfMemory
+                    );
+                    errors.push(DmxError::spawn(cmd.get_program().to_string_lossy(), e));
+                }
+            }
+        }
+
+        let err = match errors.len() {
+            1 => errors.remove(0),
+            _ => DmxError::NoBackend(errors),
+        };
+        Err(Failure { err, transient })
+    }
+
+    /*
+    Feed `output` (with `hints`) to the menu (unless it's reading from a
+    spool file), and return the line chosen (or `None` if the menu was
+    cancelled).
+    */
+    fn exchange(
+        &self,
+        mut child: Option<Child>,
+        backend: &Backend,
+        prompt: &str,
+        output: &[Vec<u8>],
+        hints: &MenuHints,
+    ) -> Result<Option<Vec<u8>>, Failure> {
+        if let Some(child) = child.as_mut() {
+            feed_child(child, &backend.annotate(output, hints))?;
+        }
+        self.outcome(child, backend, prompt, output, hints)
+    }
+
+    /*
+    Wait for the menu (which has been fed `output`, with `hints`) to
+    finish, and return the line chosen. A backend without a `Child` is
+    shown now.
+    */
+    fn outcome(
+        &self,
+        child: Option<Child>,
+        backend: &Backend,
+        prompt: &str,
+        output: &[Vec<u8>],
+        hints: &MenuHints,
+    ) -> Result<Option<Vec<u8>>, Failure> {
+        match child {
+            Some(child) => {
+                let fed = backend.annotate(output, hints);
+                let choice = self.finish(child, backend, hints)?;
+                let choice = choice.map(|c| backend.strip_query(c, hints));
+                Ok(choice.and_then(|c| {
+                    per_line(c, hints.multi, |line| {
+                        backend
+                            .clean_choice(line, &fed)
+                            .map(|line| backend.unannotate(line, output, &fed))
+                    })
+                }))
+            }
+            None => {
+                let n = numbered::ask(
+                    prompt,
+                    hints.message.as_deref(),
+                    output,
+                    std::io::stdin().lock(),
+                    std::io::stderr(),
+                )?;
+                Ok(n.map(|n| output[n].clone()))
+            }
+        }
+    }
+
+    /*
+    Wait for the menu to exit, and return what it wrote to stdout (or
+    `None` if it was cancelled).
+    */
+    fn finish(
+        &self,
+        child: Child,
+        backend: &Backend,
+        hints: &MenuHints,
+    ) -> Result<Option<Vec<u8>>, Failure> {
+        let result = child
+            .wait_with_output()
+            .map_err(|e| DmxError::io("Error reading dmenu output", e))?;
 
-        for (n, line) in output.iter().enumerate() {
-            if *line == choice_bytes {
-                return Ok(Some(n));
+        let stderr = String::from_utf8_lossy(&result.stderr);
+        let stderr = stderr.trim();
+        if !stderr.is_empty() {
+            match self.stderr {
+                #[cfg(feature = "tracing")]
+                StderrMode::Log => {
+                    tracing::warn!(target: "dm_x", status = %result.status, "{}", stderr);
+                }
+                #[cfg(not(feature = "tracing"))]
+                StderrMode::Log => {
+                    eprintln!("dm_x: dmenu subprocess ({}): {}", &result.status, stderr);
+                }
+                // stderr only gets captured in this mode when retrying is
+                // enabled (or for dmenu), so pass it along like it would
+                // have been.
+                StderrMode::Inherit => {
+                    let _ = std::io::stderr().write_all(&result.stderr);
+                }
+                _ => {}
+            }
+        }
+
+        // The menu program can't connect to the display yet (as happens right
+        // after resuming, sometimes), which may resolve itself if we wait.
+        let transient = {
+            let stderr = stderr.to_lowercase();
+            stderr.contains("open display") || stderr.contains("connect to display")
+        };
+        let meaning = match backend.exit_meaning(result.status.code()) {
+            // fzf exits with 1 when nothing matches what was typed, which
+            // is an answer when it's been asked to print that.
+            ExitMeaning::Cancelled
+                if backend.prints_query(hints) && result.status.code() == Some(1) =>
+            {
+                ExitMeaning::Selected
             }
+            meaning => meaning,
+        };
+        let err = match meaning {
+            ExitMeaning::Selected => return Ok(Some(result.stdout)),
+            ExitMeaning::Key(n) => {
+                let n = usize::from(n) - 1;
+                hints.key.set(Some(n).filter(|&n| n < hints.keys.len()));
+                return Ok(Some(result.stdout));
+            }
+            // A user cancelling doesn't cause any complaining on stderr, so
+            // if we've captured some, something else went wrong.
+            ExitMeaning::Cancelled if stderr.is_empty() => return Ok(None),
+            _ if stderr.contains("usage:") => DmxError::MenuRejected {
+                program: backend.to_string(),
+                stderr: stderr.to_owned(),
+            },
+            _ => DmxError::MenuFailed {
+                program: backend.to_string(),
+                status: result.status,
+                stderr: stderr.to_owned(),
+            },
+        };
+        Err(Failure { err, transient })
+    }
+
+    /**
+    Record the launch of a program in the audit log, if `audit_log` is set.
+
+    `Dmx` records what it launches itself (desktop entries, and the
+    programs that open files and URLs), but programs that launch other
+    programs based on the user's selection (like the `launcher` example)
+    should call this right before doing so, so the audit log contains a
+    complete picture.
+    */
+    pub fn audit_launch<S: AsRef<str>>(&self, argv: &[S]) -> Result<(), DmxError> {
+        match &self.audit_log {
+            Some(path) => audit::record(path, "launch", "-", None, argv),
+            None => Ok(()),
         }
+    }
 
-        Ok(None)
+    /*
+    Record the launch of a program that's already been started. Failing to
+    record it is only logged, since it's too late to not launch it.
+    */
+    pub(crate) fn audit_launched<S: AsRef<str>>(&self, argv: &[S]) {
+        if let Err(e) = self.audit_launch(argv) {
+            #[cfg(feature = "tracing")]
+            tracing::warn!(target: "dm_x", "Couldn't write to the audit log: {}", e);
+            #[cfg(not(feature = "tracing"))]
+            let _ = e;
+        }
     }
     
     /**
-    Return a `Dmx` configured by a slice of bytes.
+    Return a `Dmx` configured by a slice of bytes: TOML, or JSON if it
+    starts with `{`. (A file it `include`s is looked for relative to the
+    current directory.)
     */
     #[doc(cfg(feature = "config"))]
     #[cfg(feature = "config")]
-    pub fn from_bytes(bytes: &[u8]) -> Result<Dmx, String> {
-        let cfgf = config::ConfigFile::from(&bytes)?;
-        
+    pub fn from_bytes(bytes: &[u8]) -> Result<Dmx, DmxError> {
+        let cfgf = config::ConfigFile::from(bytes)?;
+        cfgf.check_strict(bytes)?;
         let mut dmx = Dmx::default();
-        if let Some(dmenu_path) = cfgf.dmenu {
-            dmx.dmenu = dmenu_path;
+        let mut deferred = Deferred::default();
+        dmx.layer_config(cfgf, Path::new(""), 0, None, &mut deferred)?;
+        dmx.finish_config(deferred)?;
+        Ok(dmx)
+    }
+
+    /*
+    Change the settings in `cfgf`, on top of those in the file it includes
+    (if it includes one), found relative to `dir`. `depth` is how many
+    files have included this one. If `app` is given, the settings in its
+    `[app.<name>]` section are applied last.
+    */
+    #[cfg(feature = "config")]
+    fn layer_config(
+        &mut self,
+        mut cfgf: config::ConfigFile,
+        dir: &Path,
+        depth: usize,
+        app: Option<&str>,
+        deferred: &mut Deferred,
+    ) -> Result<(), DmxError> {
+        match &cfgf.include {
+            Some(_) if depth >= config::MAX_INCLUDE_DEPTH => {
+                return Err(DmxError::ConfigParse(
+                    "Configuration files include each other too deeply (is there a loop?)."
+                        .to_owned(),
+                ));
+            }
+            Some(base) => {
+                let base = dir.join(config::expand_path(base)?);
+                self.layer_file(&base, depth + 1, app, deferred)?;
+            }
+            None => {}
+        }
+        let section = app.and_then(|app| cfgf.app.as_mut()?.remove(app));
+        self.apply_config(cfgf, deferred)?;
+        if let Some(section) = section {
+            self.apply_config(section, deferred)?;
+        }
+        Ok(())
+    }
+
+    /*
+    Act on the settings in `deferred`, now that every layer of the
+    configuration has been applied.
+    */
+    #[cfg(feature = "config")]
+    fn finish_config(&mut self, deferred: Deferred) -> Result<(), DmxError> {
+        if let Some(true) = deferred.pin_binary {
+            self.pin_binary()?;
+        }
+        if let Some(true) = deferred.check_font {
+            self.font = self.font.clone().or_fallback();
+        }
+        Ok(())
+    }
+
+    /*
+    Change the settings `cfgf` sets (other than `include` and `app`), and
+    note those that have to wait for every layer in `deferred`.
+    */
+    #[cfg(feature = "config")]
+    fn apply_config(
+        &mut self,
+        cfgf: config::ConfigFile,
+        deferred: &mut Deferred,
+    ) -> Result<(), DmxError> {
+        let dmx = self;
+        if let Some(path) = cfgf.dmenu {
+            dmx.dmenu = config::expand_path(&path)?;
+        }
+        for (name, overrides) in cfgf.backend.into_iter().flatten() {
+            dmx.backend_overrides.insert(name, overrides);
         }
         if let Some(font) = cfgf.font {
             dmx.font = font;
@@ -357,23 +2150,150 @@ impl Dmx {
         if let Some(sfg) = cfgf.select_fg {
             dmx.select_fg = sfg;
         }
+        if let Some(max) = cfgf.max_lines {
+            dmx.max_lines = Some(max);
+        }
+        if let Some(fixed) = cfgf.fixed_lines {
+            dmx.fixed_lines = fixed;
+        }
+        if let Some(center) = cfgf.center {
+            dmx.center = center;
+        }
+        if let Some(bw) = cfgf.border_width {
+            dmx.border_width = Some(bw);
+        }
+        if let Some(width) = cfgf.width {
+            dmx.width = Some(width);
+        }
+        if let Some(columns) = cfgf.columns {
+            dmx.columns = Some(columns);
+        }
+        if let Some(window) = cfgf.embed_window {
+            dmx.embed_window = Some(window);
+        }
+        if let Some(monitor) = cfgf.monitor {
+            dmx.monitor = Some(monitor);
+        }
+        if let Some(args) = cfgf.extra_args {
+            dmx.extra_args = args;
+        }
+        if let Some(command) = cfgf.command {
+            if command.is_empty() {
+                return Err(DmxError::ConfigParse(
+                    "Config value \"command\" must not be empty.".to_owned(),
+                ));
+            }
+            dmx.command = Some(command);
+        }
+        if let Some(backends) = cfgf.backends {
+            dmx.backends = backends;
+        }
+        if let Some(fuzzy) = cfgf.fuzzy {
+            dmx.fuzzy = fuzzy;
+        }
+        if let Some(required) = cfgf.fuzzy_required {
+            dmx.fuzzy_required = required;
+        }
+        if let Some(encoding) = cfgf.encoding {
+            dmx.encoding = encoding;
+        }
+        if let Some(allowed) = cfgf.env_allowlist {
+            dmx.env_allowlist = Some(allowed);
+        }
+        if let Some(env) = cfgf.env {
+            dmx.env = env;
+        }
+        if let Some(remove) = cfgf.env_remove {
+            dmx.env_remove = remove;
+        }
+        if let Some(n) = cfgf.spool_threshold {
+            dmx.spool_threshold = Some(n);
+        }
+        if let Some(stderr) = cfgf.stderr {
+            dmx.stderr = stderr;
+        }
+        if let Some(retry) = cfgf.retry {
+            dmx.retry = Some(retry);
+        }
+        if let Some(flag_map) = cfgf.flag_map {
+            dmx.flag_map = flag_map;
+        }
+        if let Some(path) = cfgf.audit_log {
+            dmx.audit_log = Some(config::expand_path(&path)?);
+        }
+        if let Some(notify) = cfgf.notify_errors {
+            dmx.notify_errors = notify;
+        }
+        if cfgf.pin_binary.is_some() {
+            deferred.pin_binary = cfgf.pin_binary;
+        }
+        if cfgf.check_font.is_some() {
+            deferred.check_font = cfgf.check_font;
+        }
         
-        Ok(dmx)
+        Ok(())
     }
     
     /**
-    Return a `Dmx` configured based on a configuration file.
+    Return a `Dmx` configured based on a configuration file, which is
+    read as JSON if its name ends in `.json` (or it starts with `{`), as
+    YAML if it ends in `.yaml` or `.yml` (with the `yaml` feature), in
+    the INI format if it ends in `.ini`, and as TOML otherwise.
     */
     #[doc(cfg(feature = "config"))]
     #[cfg(feature = "config")]
-    pub fn from_file<P>(p: P) -> Result<Dmx, String>
+    pub fn from_file<P>(p: P) -> Result<Dmx, DmxError>
     where
         P: AsRef<Path>,
     {
-        let p = p.as_ref();
+        Dmx::from_file_for(p.as_ref(), None)
+    }
+
+    /*
+    `Dmx::from_file()`, with the settings for `app` (if it's given).
+    */
+    #[cfg(feature = "config")]
+    fn from_file_for(p: &Path, app: Option<&str>) -> Result<Dmx, DmxError> {
+        let mut dmx = Dmx::default();
+        let mut deferred = Deferred::default();
+        dmx.layer_file(p, 0, app, &mut deferred)?;
+        dmx.finish_config(deferred)?;
+        Ok(dmx)
+    }
+
+    /*
+    Change the settings in the file at `p` (which is included by `depth`
+    others), and in its section for `app` (if it's given), as
+    `layer_config()` does.
+    */
+    #[cfg(feature = "config")]
+    fn layer_file(
+        &mut self,
+        p: &Path,
+        depth: usize,
+        app: Option<&str>,
+        deferred: &mut Deferred,
+    ) -> Result<(), DmxError> {
         let bytes = std::fs::read(p)
-            .map_err(|e| format!("Error reading from \"{}\": {}", p.display(), &e))?;
-        Dmx::from_bytes(&bytes)
+            .map_err(|e| DmxError::io(format!("Error reading from \"{}\"", p.display()), e))?;
+        let ext = p
+            .extension()
+            .map(|e| e.to_string_lossy().to_ascii_lowercase());
+        let cfgf = match ext.as_deref() {
+            Some("json") => config::ConfigFile::from_json(&bytes)?,
+            Some("ini") => config::ConfigFile::from_ini(&bytes)?,
+            #[cfg(feature = "yaml")]
+            Some("yaml" | "yml") => config::ConfigFile::from_yaml(&bytes)?,
+            _ => config::ConfigFile::from(&bytes)?,
+        };
+        cfgf.check_strict(&bytes)?;
+        self.layer_config(
+            cfgf,
+            p.parent().unwrap_or(Path::new("")),
+            depth,
+            app,
+            deferred,
+        )
     }
     
     /**
@@ -381,49 +2301,490 @@ impl Dmx {
     */
     #[doc(cfg(feature = "config"))]
     #[cfg(feature = "config")]
-    pub fn from_slice(b: &[u8]) -> Result<Dmx, String> {
+    pub fn from_slice(b: &[u8]) -> Result<Dmx, DmxError> {
         Dmx::from_bytes(b)
     }
     
     /**
     Configure "automagically".
     
-    That is, attempt to configure from (in this order):
+    That is, attempt to configure from the first of these that works:
       * the file specified by the `$DMX_CONFIG` environment variable
       * the file at `$XDG_CONFIG_HOME/dmx.toml`
       * the file at `$HOME/.config/dmx.toml`
       * `Dmx::default()` (this always works)
+
+    on top of `dmx.toml` in each of the directories in `$XDG_CONFIG_DIRS`
+    (by default, just `/etc/xdg`), where system-wide settings go. The user's
+    file only has to change what it wants different from those (and
+    the first directory in `$XDG_CONFIG_DIRS` wins over the rest).
+
+    `Dmx::automagiconf_verbose()` tells which files were used, and what
+    was wrong with the others; to let environment variables override the
+    files, use `Dmx::load()`.
     */
     #[doc(cfg(feature = "config"))]
     #[cfg(feature = "config")]
     pub fn automagiconf() -> Dmx {
-        use std::env::var;
-        
-        if let Ok(path) = var("DMX_CONFIG") {
-            if let Ok(dmx) = Dmx::from_file(path) {
-                return dmx;
+        Dmx::automagiconf_verbose().0
+    }
+
+    /**
+    Configure like `Dmx::automagiconf()`, but also return each file that
+    was looked for, in order, and what became of it, for finding out why
+    a configuration file is being ignored:
+
+    ```no_run
+    # use dm_x::Dmx;
+    let (dmx, tried) = Dmx::automagiconf_verbose();
+    for (path, outcome) in tried.iter() {
+        eprintln!("{}: {}", path.display(), outcome);
+    }
+    ```
+
+    The user's files come first, then the system-wide ones. User files
+    after the one that's used aren't looked for, so they aren't listed. If
+    none is used, the `Dmx` is `Dmx::default()`.
+    */
+    #[doc(cfg(feature = "config"))]
+    #[cfg(feature = "config")]
+    pub fn automagiconf_verbose() -> (Dmx, Vec<(PathBuf, ConfigOutcome)>) {
+        let var_os = |var: &str| std::env::var_os(var);
+        Dmx::first_config(config_files_in(var_os), system_config_files_in(var_os))
+    }
+
+    /*
+    The settings in the first of `files` that can be used, on top of those
+    in each of `system` that can be (the first of them counting most), and
+    what became of each file that was tried, `files` first.
+    */
+    #[cfg(feature = "config")]
+    fn first_config(
+        files: Vec<PathBuf>,
+        system: Vec<PathBuf>,
+    ) -> (Dmx, Vec<(PathBuf, ConfigOutcome)>) {
+        let mut dmx = Dmx::default();
+        let mut deferred = Deferred::default();
+        let mut tried_system = Vec::new();
+        for path in system.into_iter().rev() {
+            let outcome = dmx.try_layer(&path, &mut deferred);
+            tried_system.push((path, outcome));
+        }
+        let mut tried = Vec::new();
+        for path in files {
+            let outcome = dmx.try_layer(&path, &mut deferred);
+            let used = matches!(outcome, ConfigOutcome::Used);
+            tried.push((path, outcome));
+            if used {
+                break;
             }
         }
-        
-        if let Ok(config_path) = var("XDG_CONFIG_HOME") {
-            let mut config_file = PathBuf::from(config_path);
-            config_file.push("dmx.toml");
-            if let Ok(dmx) = Dmx::from_file(&config_file) {
-                return dmx;
+        tried.extend(tried_system.into_iter().rev());
+
+        if let Err(e) = dmx.finish_config(deferred) {
+            // Blame the file that counted most.
+            if let Some(top) = tried
+                .iter_mut()
+                .find(|(_, o)| matches!(o, ConfigOutcome::Used))
+            {
+                top.1 = ConfigOutcome::Failed(e);
             }
+            return (Dmx::default(), tried);
         }
-        
-        if let Ok(home_dir) = var("HOME") {
-            let mut config_file = PathBuf::from(home_dir);
-            config_file.push(".config");
-            config_file.push("dmx.toml");
-            if let Ok(dmx) = Dmx::from_file(&config_file) {
-                return dmx;
+        (dmx, tried)
+    }
+
+    /*
+    Layer the file at `path` over these settings (and `deferred`), unless
+    it's missing or has a mistake in it, in which case nothing changes.
+    */
+    #[cfg(feature = "config")]
+    fn try_layer(&mut self, path: &Path, deferred: &mut Deferred) -> ConfigOutcome {
+        if !path.exists() {
+            return ConfigOutcome::Missing;
+        }
+        let (mut dmx, mut layered) = (self.clone(), deferred.clone());
+        match dmx.layer_file(path, 0, None, &mut layered) {
+            Ok(()) => {
+                (*self, *deferred) = (dmx, layered);
+                ConfigOutcome::Used
             }
+            Err(e) => ConfigOutcome::Failed(e),
         }
-        
-        Dmx::default()
     }
+
+    /**
+    Configure from every source there is, each overriding the settings
+    of the one before:
+      * `Dmx::default()`
+      * with the `config` feature, the system-wide configuration files,
+        and the first of the user's that exists, as `Dmx::automagiconf()`
+        finds them
+      * the environment variables read by `Dmx::from_env()`
+
+    So a script can change just the font, say, by setting `$DMX_FONT`,
+    and keep the rest of the user's configuration. Unlike
+    `Dmx::automagiconf()`, a file or variable with a mistake in it is an
+    error.
+    */
+    pub fn load() -> Result<Dmx, DmxError> {
+        Dmx::load_in(None, |var| std::env::var_os(var))
+    }
+
+    /**
+    Configure like `Dmx::load()`, but with the settings in the
+    configuration file's section for the program `app` applied on top of
+    the rest of the file (its `[app.launcher]` section, for `"launcher"`),
+    so that programs sharing one file can each look their own way.
+
+    ```no_run
+    # use dm_x::Dmx;
+    let dmx = Dmx::for_app("launcher").unwrap_or_default();
+    ```
+
+    Without the `config` feature (or a section for `app`), this is the
+    same as `Dmx::load()`.
+    */
+    pub fn for_app(app: &str) -> Result<Dmx, DmxError> {
+        Dmx::load_in(Some(app), |var| std::env::var_os(var))
+    }
+
+    /*
+    `Dmx::load()`, with the settings for `app` (if it's given), and the
+    environment given by `var_os`.
+    */
+    fn load_in<F>(app: Option<&str>, var_os: F) -> Result<Dmx, DmxError>
+    where
+        F: Fn(&str) -> Option<std::ffi::OsString>,
+    {
+        let mut dmx = Dmx::default();
+        #[cfg(feature = "config")]
+        let mut deferred = Deferred::default();
+        #[cfg(feature = "config")]
+        {
+            let system = system_config_files_in(&var_os);
+            for path in system.iter().rev().filter(|path| path.is_file()) {
+                dmx.layer_file(path, 0, app, &mut deferred)?;
+            }
+            if let Some(path) = config_files_in(&var_os)
+                .into_iter()
+                .find(|path| path.is_file())
+            {
+                dmx.layer_file(&path, 0, app, &mut deferred)?;
+            }
+        }
+        #[cfg(not(feature = "config"))]
+        let _ = app;
+
+        environment::apply(&mut dmx, |var| var_os(var)?.into_string().ok())?;
+        #[cfg(feature = "config")]
+        dmx.finish_config(deferred)?;
+        Ok(dmx)
+    }
+
+    /**
+    Write `DEFAULT_CONFIG`, a configuration file with every setting
+    explained in it, to `dmx.toml` in `$XDG_CONFIG_HOME` (or in
+    `~/.config`), where `Dmx::load()` looks, and return its path. This is
+    what `dmx init-config` does.
+
+    A file that's already there is left alone, and is an error.
+    */
+    #[doc(cfg(feature = "config"))]
+    #[cfg(feature = "config")]
+    pub fn write_default_config() -> Result<PathBuf, DmxError> {
+        let path = new_config_file().ok_or_else(|| DmxError::EnvNotSet {
+            var: "HOME".to_owned(),
+            what: "the configuration directory".to_owned(),
+        })?;
+        Dmx::write_default_config_to(&path)?;
+        Ok(path)
+    }
+
+    /**
+    Like `Dmx::write_default_config()`, but writing to `path` (and
+    creating the directory it's in, if need be).
+    */
+    #[doc(cfg(feature = "config"))]
+    #[cfg(feature = "config")]
+    pub fn write_default_config_to<P: AsRef<Path>>(path: P) -> Result<(), DmxError> {
+        let path = path.as_ref();
+        let error = |e| DmxError::io(format!("Error writing {}", path.display()), e);
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir).map_err(error)?;
+        }
+        std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(path)
+            .and_then(|mut f| f.write_all(DEFAULT_CONFIG.as_bytes()))
+            .map_err(error)
+    }
+}
+
+/*
+Where a new configuration file goes: `dmx.toml` in `$XDG_CONFIG_HOME`,
+or in `~/.config`.
+*/
+#[cfg(feature = "config")]
+fn new_config_file() -> Option<PathBuf> {
+    match std::env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => Some(Path::new(&dir).join("dmx.toml")),
+        None => Some(Path::new(&std::env::var_os("HOME")?).join(".config/dmx.toml")),
+    }
+}
+
+/*
+The settings from configuration files that are acted on only once every
+layer (included files, sections, and environment variables) has been
+applied, so that a later layer can still change what they act on.
+*/
+#[cfg(feature = "config")]
+#[derive(Clone, Default)]
+struct Deferred {
+    pin_binary: Option<bool>,
+    check_font: Option<bool>,
+}
+
+/*
+The user's configuration files to look for, in order: `$DMX_CONFIG`,
+then `dmx.toml` in `$XDG_CONFIG_HOME` and in `~/.config`, with the
+environment given by `var_os`.
+*/
+#[cfg(feature = "config")]
+fn config_files_in<F>(var_os: F) -> Vec<PathBuf>
+where
+    F: Fn(&str) -> Option<std::ffi::OsString>,
+{
+    let mut files: Vec<PathBuf> = Vec::new();
+    if let Some(path) = var_os("DMX_CONFIG") {
+        files.push(path.into());
+    }
+    if let Some(dir) = var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+        files.push(Path::new(&dir).join("dmx.toml"));
+    }
+    if let Some(home) = var_os("HOME") {
+        files.push(Path::new(&home).join(".config/dmx.toml"));
+    }
+    files
+}
+
+/*
+The system-wide configuration files, most important first: `dmx.toml` in
+each of `$XDG_CONFIG_DIRS` (or `/etc/xdg`), with the environment given by
+`var_os`.
+*/
+#[cfg(feature = "config")]
+fn system_config_files_in<F>(var_os: F) -> Vec<PathBuf>
+where
+    F: Fn(&str) -> Option<std::ffi::OsString>,
+{
+    xdg_config_dirs_in(var_os)
+        .into_iter()
+        .map(|dir| dir.join("dmx.toml"))
+        .collect()
+}
+
+/*
+The system-wide configuration directories, most important first:
+`$XDG_CONFIG_DIRS` (or `/etc/xdg`), with the environment given by
+`var_os`.
+*/
+pub(crate) fn xdg_config_dirs_in<F>(var_os: F) -> Vec<PathBuf>
+where
+    F: Fn(&str) -> Option<std::ffi::OsString>,
+{
+    // The base directory spec says to ignore relative paths, and to use
+    // /etc/xdg if the variable isn't set (or is empty).
+    let dirs = var_os("XDG_CONFIG_DIRS")
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| "/etc/xdg".into());
+    std::env::split_paths(&dirs)
+        .filter(|dir| dir.is_absolute())
+        .collect()
+}
+
+/*
+Write `output` to the menu's stdin (if it's still connected), and close it.
+*/
+fn feed_child(child: &mut Child, output: &[Vec<u8>]) -> Result<(), Failure> {
+    if let Some(mut stdin) = child.stdin.take() {
+        for line in output.iter() {
+            stdin
+                .write_all(line)
+                .map_err(|e| DmxError::io("Error writing to dmenu subprocess", e))?;
+        }
+        stdin
+            .flush()
+            .map_err(|e| DmxError::io("Error writing to dmenu subprocess", e))?;
+    }
+    Ok(())
+}
+
+/*
+Apply `f` to each line of what the menu program printed, if the user
+could choose more than one (`multi`), or else to the whole thing, and put
+the results back together. Returns `None` if nothing is left.
+*/
+fn per_line<F>(choice: Vec<u8>, multi: bool, mut f: F) -> Option<Vec<u8>>
+where
+    F: FnMut(Vec<u8>) -> Option<Vec<u8>>,
+{
+    if !multi {
+        return f(choice);
+    }
+    let mut out: Vec<u8> = Vec::new();
+    for line in choice.split_inclusive(|&b| b == NEWLINE) {
+        if let Some(mut line) = f(line.to_vec()) {
+            if Some(&NEWLINE) != line.last() {
+                line.push(NEWLINE);
+            }
+            out.extend(line);
+        }
+    }
+    Some(out).filter(|out| !out.is_empty())
+}
+
+/**
+The running menu program, and the backend it's running for.
+*/
+pub struct DmxSession {
+    prompt: String,
+    lines: usize,
+    started: Option<(Option<Child>, Backend)>,
+    output: Vec<Vec<u8>>,
+}
+
+/**
+`Dmx` runs its menu program through each step of the `MenuBackend`
+protocol, but also overrides `MenuBackend::choose()` to add the things
+configured in the `Dmx` that span all three steps (encoding the lines,
+retrying, spooling input to a file, and audit logging).
+
+The menu program isn't actually started until `MenuBackend::feed()`,
+because some backends take their items on the command line.
+*/
+impl MenuBackend for Dmx {
+    type Session = DmxSession;
+
+    fn spawn(&self, prompt: &str, lines: usize) -> Result<DmxSession, DmxError> {
+        Ok(DmxSession {
+            prompt: prompt.to_owned(),
+            lines,
+            started: None,
+            output: Vec::new(),
+        })
+    }
+
+    fn feed(&self, session: &mut DmxSession, input: &[Vec<u8>]) -> Result<(), DmxError> {
+        session.output = input.iter().map(|l| self.encoding.encode(l)).collect();
+        let started = self
+            .start(
+                &session.prompt,
+                session.lines,
+                &session.output,
+                &MenuHints::default(),
+                None,
+                &mut None,
+            )
+            .map_err(|f| f.err);
+        let (mut child, backend) = self.reported(started)?;
+        if let Some(child) = child.as_mut() {
+            self.reported(feed_child(child, &session.output).map_err(|f| f.err))?;
+        }
+        session.started = Some((child, backend));
+        Ok(())
+    }
+
+    fn read_selection(&self, session: DmxSession) -> Result<Option<Vec<u8>>, DmxError> {
+        let (child, backend) = session.started.ok_or_else(|| {
+            DmxError::InvalidArgument("The menu was never given any items.".to_owned())
+        })?;
+        let choice = self.reported(
+            self.outcome(
+                child,
+                &backend,
+                &session.prompt,
+                &session.output,
+                &MenuHints::default(),
+            )
+            .map_err(|f| f.err),
+        )?;
+        Ok(choice.map(|c| self.encoding.decode(&c).into_bytes()))
+    }
+
+    fn choose(
+        &self,
+        prompt: &str,
+        lines: usize,
+        input: &[Vec<u8>],
+    ) -> Result<Option<Vec<u8>>, DmxError> {
+        self.choose_hinted(prompt, lines, input, &MenuHints::default())
+    }
+
+    fn choose_hinted(
+        &self,
+        prompt: &str,
+        lines: usize,
+        input: &[Vec<u8>],
+        hints: &MenuHints,
+    ) -> Result<Option<Vec<u8>>, DmxError> {
+        self.reported(self.show(prompt, lines, input, hints))
+    }
+}
+
+/**
+Set the global `Dmx` used by the free function `dm_x::select()`.
+
+This can only be done once, and only before the first call to
+`dm_x::global()` (or `dm_x::select()`); it is an error to try to set
+the global `Dmx` after it has already been set.
+
+```
+let dmx = dm_x::Dmx {
+    font: "Terminus-12".into(),
+    ..Default::default()
+};
+dm_x::init(dmx).unwrap();
+
+assert_eq!(dm_x::global().font.name(), "Terminus-12");
+```
+*/
+pub fn init(dmx: Dmx) -> Result<(), DmxError> {
+    GLOBAL.set(dmx).map_err(|_| DmxError::AlreadyInitialized)
+}
+
+/**
+Return a reference to the global `Dmx`.
+
+If `dm_x::init()` hasn't been called, the global `Dmx` is initialized
+with `Dmx::default()` (or with `Dmx::automagiconf()` if the `config`
+feature is enabled).
+*/
+pub fn global() -> &'static Dmx {
+    GLOBAL.get_or_init(|| {
+        #[cfg(not(feature = "config"))]
+        let dmx = Dmx::default();
+        #[cfg(feature = "config")]
+        let dmx = Dmx::automagiconf();
+        dmx
+    })
+}
+
+/**
+Launch `dmenu` to select an `Item`, using the global `Dmx`.
+
+This is for small programs that don't want to pass a `Dmx` around to
+every function that might need to ask the user something. It is
+equivalent to `dm_x::global().select(prompt, items)`.
+*/
+pub fn select<S, T>(prompt: S, items: T) -> Result<Option<usize>, DmxError>
+where
+    S: AsRef<str>,
+    T: IntoIterator,
+    T::Item: Item,
+{
+    global().select(prompt, items)
 }
 
 #[cfg(test)]
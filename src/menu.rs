@@ -0,0 +1,683 @@
+/*!
+A `Menu` bundles everything about a single invocation of `dmenu`: the
+prompt, the items, and any per-call options.
+
+`Dmx` holds the things that describe how `dmenu` _looks_ (and which binary
+to run), and is generally configured once and reused; a `Menu` holds the
+things that describe one particular question being asked of the user.
+
+```
+# use dm_x::{Dmx, Menu};
+let dmx = Dmx::default();
+# // Doctests shouldn't depend on being run in an X session.
+# let display = std::env::var("DISPLAY").unwrap_or(":0".to_owned());
+# let dmx = Dmx { env: [("DISPLAY".to_owned(), display)].into(), ..dmx };
+let flavors = &["Vanilla", "Chocolate", "Strawberry", "Pistachio"];
+
+// Default to the user's usual choice.
+match Menu::new("flavor:").items(flavors).preselect(1).show(&dmx).unwrap() {
+    Some(n) => println!("One scoop of {}, coming up.", flavors[n]),
+    None => println!("No ice cream, then."),
+}
+```
+
+This module also supports hierarchical menus, where choosing some items
+opens a submenu of further items instead of ending the selection; see the
+`Nested` trait and the `navigate()` family of functions.
+*/
+
+use crate::backend::{MenuBackend, MenuHints};
+use crate::store::{Favorites, Recent};
+use crate::{expand_template, Breadcrumbs, Dmx, DmxError, Item, NEWLINE};
+
+/**
+How a single-choice menu ended.
+
+`Menu::show()` (and `Dmx::select()`) return `None` both when the menu was
+cancelled and when the user typed something that isn't one of the items;
+`Menu::show_selection()` (and `Dmx::selection()`) tell them apart. (See
+`Menu::allow_custom()` for the backends that need to be told to accept
+text that isn't an item.)
+*/
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Selection {
+    /// The item at this slice index was chosen.
+    Picked(usize),
+    /// The menu was cancelled (with Escape, usually).
+    Cancelled,
+    /// The user entered this text, which doesn't match any item.
+    Custom(String),
+}
+
+impl Selection {
+    /**
+    The index of the item chosen, if one was.
+    */
+    pub fn picked(&self) -> Option<usize> {
+        match self {
+            Selection::Picked(n) => Some(*n),
+            _ => None,
+        }
+    }
+}
+
+/**
+A single `dmenu` invocation, built up with chained method calls and then
+launched with `Menu::show()`.
+
+`Dmx::select(prompt, items)` is exactly equivalent to
+`Menu::new(prompt).items(items).show(&dmx)`.
+*/
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Menu<'a, I> {
+    pub(crate) prompt: String,
+    pub(crate) items: &'a [I],
+    pub(crate) lines: Option<usize>,
+    pub(crate) preselect: Option<usize>,
+    pub(crate) favorites: Option<&'a Favorites>,
+    pub(crate) recent: Option<(&'a Recent, usize)>,
+    pub(crate) separator: String,
+    pub(crate) message: Option<String>,
+    pub(crate) custom: bool,
+    pub(crate) query: Option<String>,
+    pub(crate) template: bool,
+}
+
+impl<'a, I> Menu<'a, I> {
+    /**
+    Start building a menu with the given prompt and no items.
+    */
+    pub fn new<S: AsRef<str>>(prompt: S) -> Menu<'a, I> {
+        Menu {
+            prompt: prompt.as_ref().to_owned(),
+            items: &[],
+            lines: None,
+            preselect: None,
+            favorites: None,
+            recent: None,
+            separator: "────────".to_owned(),
+            message: None,
+            custom: false,
+            query: None,
+            template: false,
+        }
+    }
+
+    /**
+    Set the items from which the user will choose.
+    */
+    pub fn items(mut self, items: &'a [I]) -> Menu<'a, I> {
+        self.items = items;
+        self
+    }
+
+    /**
+    Set the number of lines `dmenu` will display vertically. If this isn't
+    set, `dmenu` will be tall enough to display every item.
+    */
+    pub fn lines(mut self, n: usize) -> Menu<'a, I> {
+        self.lines = Some(n);
+        self
+    }
+
+    /**
+    Highlight the item at index `n` when the menu opens, so that it is
+    chosen if the user just hits Enter.
+
+    Stock `dmenu` (like most other backends) has no way to highlight an
+    arbitrary line, so this is accomplished by displaying that item first
+    (rofi is told to highlight it, too, with `-selected-row`). The index
+    returned by `Menu::show()` is still the item's index in the original
+    slice.
+    */
+    pub fn preselect(mut self, n: usize) -> Menu<'a, I> {
+        self.preselect = Some(n);
+        self
+    }
+
+    /**
+    Display any items pinned in `favs` at the top of the menu.
+    */
+    pub fn favorites(mut self, favs: &'a Favorites) -> Menu<'a, I> {
+        self.favorites = Some(favs);
+        self
+    }
+
+    /**
+    Display (up to) the `n` items most recently chosen, according to
+    `recent`, in a section of their own above the rest of the items,
+    which are displayed as usual (without those).
+
+    If there's a preselected item, it's displayed at the top of this
+    section (which is still no more than `n` items long).
+    */
+    pub fn recent(mut self, recent: &'a Recent, n: usize) -> Menu<'a, I> {
+        self.recent = Some((recent, n));
+        self
+    }
+
+    /**
+    Set the text of the line that separates sections of the menu (like
+    the one added by `Menu::recent()`). If the user selects this line,
+    it is treated as if they had cancelled.
+    */
+    pub fn separator<S: AsRef<str>>(mut self, separator: S) -> Menu<'a, I> {
+        self.separator = separator.as_ref().to_owned();
+        self
+    }
+
+    /**
+    Display `message` above the items, for context (like "3 devices
+    found") that doesn't belong in the prompt.
+
+    rofi, fzf, zenity, and yad have a place for this; other backends
+    show it as the first line of the menu, and choosing that line counts
+    as cancelling.
+    */
+    pub fn message<S: AsRef<str>>(mut self, message: S) -> Menu<'a, I> {
+        self.message = Some(message.as_ref().to_owned());
+        self
+    }
+
+    /**
+    Let the user answer with text that isn't one of the items, which
+    `Menu::show_selection()` returns as `Selection::Custom`.
+
+    dmenu (and most of its imitators, and rofi) always allow this, but
+    fzf has to be asked. zenity, yad, kdialog, and the numbered list
+    can't do it at all.
+    */
+    pub fn allow_custom(mut self) -> Menu<'a, I> {
+        self.custom = true;
+        self
+    }
+
+    /**
+    Open the menu with `text` already typed, so it starts out filtered
+    (as when one menu's choice narrows down the next).
+
+    rofi, fzf, and wofi can all do this, as can `dmenu` with the
+    initialtext patch (`-it`). Other backends open with nothing typed.
+    */
+    pub fn query<S: AsRef<str>>(mut self, text: S) -> Menu<'a, I> {
+        self.query = Some(text.as_ref().to_owned());
+        self
+    }
+
+    /**
+    Treat the prompt as a template, in which `{count}` is replaced with
+    the number of items (so `"{count} files:"` works for any number of
+    them), and `{{` and `}}` stand for `{` and `}`. A `Breadcrumbs`
+    template can fill in more placeholders first.
+
+    Otherwise (by default), the prompt is shown as is.
+    */
+    pub fn template(mut self, on: bool) -> Menu<'a, I> {
+        self.template = on;
+        self
+    }
+}
+
+impl<'a, I: Item> Menu<'a, I> {
+    /**
+    Launch `dmenu` to select one of this menu's items.
+
+    Returns the slice index of the `Item` selected, or `None` if cancelled.
+    */
+    pub fn show(&self, dmx: &Dmx) -> Result<Option<usize>, DmxError> {
+        self.show_with(dmx)
+    }
+
+    /**
+    Like `Menu::show()`, but using any `MenuBackend` to display the menu.
+    */
+    pub fn show_with<B: MenuBackend>(&self, backend: &B) -> Result<Option<usize>, DmxError> {
+        Ok(self.show_selection_with(backend)?.picked())
+    }
+
+    /**
+    Launch `dmenu` to select one of this menu's items, telling apart a
+    cancelled menu and text that doesn't match any of the items.
+    */
+    pub fn show_selection(&self, dmx: &Dmx) -> Result<Selection, DmxError> {
+        self.show_selection_with(dmx)
+    }
+
+    /**
+    Like `Menu::show_selection()`, but using any `MenuBackend` to display
+    the menu.
+    */
+    pub fn show_selection_with<B: MenuBackend>(&self, backend: &B) -> Result<Selection, DmxError> {
+        Ok(self
+            .chosen(backend, false, &[])?
+            .0
+            .into_iter()
+            .next()
+            .unwrap_or(Selection::Cancelled))
+    }
+
+    /**
+    Launch `dmenu` to select any number of this menu's items.
+
+    Returns the slice indices of the `Item`s selected, in the order they
+    were chosen; this is empty if the menu was cancelled.
+
+    In `dmenu` (and bemenu, and others that copy it), Ctrl-Enter chooses
+    an item and leaves the menu open to choose more. rofi and fzf mark
+    items with Shift-Enter and Tab, respectively, and zenity and yad show
+    checkable rows. Backends that can't do any of this act like
+    `Menu::show()`.
+    */
+    pub fn show_multi(&self, dmx: &Dmx) -> Result<Vec<usize>, DmxError> {
+        self.show_multi_with(dmx)
+    }
+
+    /**
+    Like `Menu::show_multi()`, but using any `MenuBackend` to display the
+    menu.
+    */
+    pub fn show_multi_with<B: MenuBackend>(&self, backend: &B) -> Result<Vec<usize>, DmxError> {
+        Ok(self
+            .chosen(backend, true, &[])?
+            .0
+            .iter()
+            .filter_map(|s| s.picked())
+            .collect())
+    }
+
+    /**
+    Like `Menu::show()`, but with the items pinned in `favs` at the top
+    (as with `Menu::favorites()`), and with `key` (like `"Alt+p"`) pinning
+    the highlighted item, or unpinning it if it's pinned. That saves
+    `favs` and opens the menu again, until something is chosen or the
+    menu is cancelled.
+
+    Only rofi can be given key bindings like this (see `MenuHints::keys`);
+    with any other backend, the items can't be pinned from the menu.
+    */
+    pub fn show_pinning<B: MenuBackend>(
+        &self,
+        backend: &B,
+        favs: &mut Favorites,
+        key: &str,
+    ) -> Result<Option<usize>, DmxError> {
+        let keys = [key.to_owned()];
+        loop {
+            let (chosen, key) = self.with_favorites(favs).chosen(backend, false, &keys)?;
+            match (chosen.into_iter().next().and_then(|s| s.picked()), key) {
+                (Some(n), Some(_)) => {
+                    favs.toggle(&self.items[n]);
+                    favs.save()?;
+                }
+                (n, _) => return Ok(n),
+            }
+        }
+    }
+
+    /*
+    Show the menu with `backend`, and return what was chosen (only one
+    thing, unless `multi` is set; nothing, if the menu was cancelled),
+    along with which of `keys` it was chosen with, if any. An item chosen
+    more than once is only returned the first time.
+    */
+    fn chosen<B: MenuBackend>(
+        &self,
+        backend: &B,
+        multi: bool,
+        keys: &[String],
+    ) -> Result<(Vec<Selection>, Option<usize>), DmxError> {
+        let rows = self.rows();
+        let klen: usize = self.items.iter().map(|x| x.key_len()).max().unwrap_or(0);
+
+        let input: Vec<Vec<u8>> = rows
+            .iter()
+            .map(|&row| {
+                let mut v = match row {
+                    Some(n) => self.items[n].line(klen),
+                    None => self.separator.as_bytes().to_vec(),
+                };
+                if Some(&NEWLINE) != v.last() {
+                    v.push(NEWLINE);
+                }
+                v
+            })
+            .collect();
+
+        let hints = MenuHints {
+            icons: rows
+                .iter()
+                .map(|row| row.and_then(|n| self.items[n].icon()))
+                .collect(),
+            markup: rows
+                .iter()
+                .map(|row| row.and_then(|n| self.items[n].markup(klen)))
+                .collect(),
+            message: self.message.clone(),
+            multi,
+            custom: self.custom,
+            selected: self
+                .preselect
+                .and_then(|n| rows.iter().position(|&row| row == Some(n))),
+            query: self.query.clone(),
+            keys: keys.to_vec(),
+            ..Default::default()
+        };
+
+        let prompt = match self.template {
+            true => {
+                let count = self.items.len().to_string();
+                expand_template(&self.prompt, |key| match key {
+                    "count" => Some(count.as_str()),
+                    _ => None,
+                })
+            }
+            false => self.prompt.clone(),
+        };
+
+        let lines = self.lines.unwrap_or(input.len());
+        let choice = match backend.choose_hinted(&prompt, lines, &input, &hints)? {
+            Some(choice) => choice,
+            None => return Ok((Vec::new(), None)),
+        };
+        let choices: Vec<&[u8]> = if multi {
+            choice.split_inclusive(|&b| b == NEWLINE).collect()
+        } else {
+            vec![&choice]
+        };
+
+        let mut chosen: Vec<Selection> = Vec::new();
+        for choice in choices.into_iter() {
+            let mut choice = choice.to_vec();
+            if Some(&NEWLINE) != choice.last() {
+                choice.push(NEWLINE);
+            }
+            let row = input
+                .iter()
+                .zip(rows.iter())
+                .find(|(line, _)| **line == choice)
+                .map(|(_, row)| *row);
+            let selection = match row {
+                Some(Some(n)) => Selection::Picked(n),
+                // A separator isn't anything.
+                Some(None) => continue,
+                None => {
+                    let text = String::from_utf8_lossy(&choice[..choice.len() - 1]);
+                    Selection::Custom(text.into_owned())
+                }
+            };
+            if !chosen.contains(&selection) {
+                chosen.push(selection);
+            }
+        }
+        Ok((chosen, hints.key.get()))
+    }
+
+    /*
+    This menu, with `favs` as its favorites.
+    */
+    fn with_favorites<'b>(&self, favs: &'b Favorites) -> Menu<'b, I>
+    where
+        'a: 'b,
+    {
+        Menu {
+            prompt: self.prompt.clone(),
+            items: self.items,
+            lines: self.lines,
+            preselect: self.preselect,
+            favorites: Some(favs),
+            recent: self.recent,
+            separator: self.separator.clone(),
+            message: self.message.clone(),
+            custom: self.custom,
+            query: self.query.clone(),
+            template: self.template,
+        }
+    }
+
+    /*
+    The order in which the items should be displayed, as indices into
+    `self.items`: favorites first, then heaviest first, with ties broken
+    by how recently the items were chosen (if there's a `Recent`), and
+    then by the given order; except that a preselected item always comes
+    first.
+    */
+    pub(crate) fn order(&self) -> Vec<usize> {
+        let history = self.recent.map(|(recent, _)| recent.indices(self.items));
+        let history = history.unwrap_or_default();
+        let mut order: Vec<usize> = (0..self.items.len()).collect();
+        order.sort_by_key(|&n| {
+            let item = &self.items[n];
+            let pinned = self.favorites.map(|f| f.is_pinned(item)).unwrap_or(false);
+            let rank = history.iter().position(|&m| m == n).unwrap_or(usize::MAX);
+            (!pinned, std::cmp::Reverse(item.weight()), rank)
+        });
+        if let Some(n) = self.preselect {
+            if let Some(pos) = order.iter().position(|&m| m == n) {
+                order.remove(pos);
+                order.insert(0, n);
+            }
+        }
+        order
+    }
+
+    /*
+    The rows to display, in order: indices into `self.items`, or `None`
+    for section separators. Each item is displayed once, so those in the
+    recent section are left out of the rest.
+    */
+    pub(crate) fn rows(&self) -> Vec<Option<usize>> {
+        let order = self.order();
+        let (mut recent, n) = match self.recent {
+            Some((recent, n)) => (recent.indices(self.items), n),
+            None => (Vec::new(), 0),
+        };
+        if recent.is_empty() || n == 0 {
+            return order.into_iter().map(Some).collect();
+        }
+
+        if let Some(n) = self.preselect.filter(|&n| n < self.items.len()) {
+            recent.retain(|&m| m != n);
+            recent.insert(0, n);
+        }
+        recent.truncate(n);
+        let rest: Vec<usize> = order.into_iter().filter(|m| !recent.contains(m)).collect();
+        let separator = (!rest.is_empty()).then_some(None);
+        recent
+            .into_iter()
+            .map(Some)
+            .chain(separator)
+            .chain(rest.into_iter().map(Some))
+            .collect()
+    }
+}
+
+/**
+Implement this for `Item`s that make up a hierarchical menu, where some
+items are "directories" that contain further items.
+
+See the `launcher` example for a complete implementation.
+*/
+pub trait Nested: Item + Sized {
+    /**
+    Return the items in this item's submenu, or `None` if this item
+    is a "leaf" that can actually be chosen.
+    */
+    fn children(&self) -> Option<&[Self]>;
+
+    /**
+    Return the text to add to the prompt when this item's submenu is
+    displayed (usually its "key").
+    */
+    fn crumb(&self) -> String;
+}
+
+/**
+Present a hierarchical menu, starting with `items`, and return the
+path of indices that leads to the chosen leaf item.
+
+Choosing an item with children opens a submenu of those children, whose
+prompt is the current prompt with the item's `Nested::crumb()` pushed
+onto it. (`prompt` can be a `&str`, in which case crumbs are separated
+with `/`, or a `Breadcrumbs` to control how the prompt is built.)
+Cancelling from a submenu returns to its parent menu, and
+cancelling from the top-level menu returns `None`.
+
+The first index in the returned path is an index into `items`, the next
+is an index into the `children()` of that item, and so on; the last is
+the index of the chosen leaf. Use `resolve_path()` to turn this into
+references to the items themselves.
+*/
+pub fn navigate_path<B, I>(
+    dmx: &Dmx,
+    prompt: B,
+    items: &[I],
+) -> Result<Option<Vec<usize>>, DmxError>
+where
+    B: Into<Breadcrumbs>,
+    I: Nested,
+{
+    let mut crumbs = prompt.into();
+    let mut path: Vec<usize> = Vec::new();
+    if descend(dmx, &mut crumbs, items, &mut path)? {
+        Ok(Some(path))
+    } else {
+        Ok(None)
+    }
+}
+
+/**
+Present a hierarchical menu, as with `navigate_path()`, but return only
+the chosen leaf item.
+*/
+pub fn navigate<'a, B, I>(dmx: &Dmx, prompt: B, items: &'a [I]) -> Result<Option<&'a I>, DmxError>
+where
+    B: Into<Breadcrumbs>,
+    I: Nested,
+{
+    let path = navigate_path(dmx, prompt, items)?;
+    Ok(path.and_then(|p| resolve_path(items, &p).pop()))
+}
+
+/**
+Present a hierarchical menu, as with `navigate_path()`, but starting
+in a submenu.
+
+`keys` is the path to that submenu, as a list of the
+`Nested::crumb()`s of the items along the way; for example, if the
+top-level menu contains a directory with the crumb `ssh`, passing
+`&["ssh"]` will start out displaying the contents of that directory.
+Cancelling from the starting submenu returns `None`, rather than
+displaying its parent menu.
+
+The returned path starts from `items`, just as with `navigate_path()`.
+*/
+pub fn navigate_path_at<B, I, S>(
+    dmx: &Dmx,
+    prompt: B,
+    items: &[I],
+    keys: &[S],
+) -> Result<Option<Vec<usize>>, DmxError>
+where
+    B: Into<Breadcrumbs>,
+    I: Nested,
+    S: AsRef<str>,
+{
+    let mut crumbs = prompt.into();
+    let mut path: Vec<usize> = Vec::new();
+    let mut level = items;
+
+    for key in keys.iter().map(|k| k.as_ref()) {
+        let n = level
+            .iter()
+            .position(|item| item.children().is_some() && item.crumb() == key)
+            .ok_or_else(|| {
+                DmxError::NotFound(format!("a submenu \"{}\" in \"{}\"", key, &crumbs))
+            })?;
+        crumbs.push(key);
+        path.push(n);
+        // `position()` guarantees this has children.
+        level = level[n].children().unwrap_or(&[]);
+    }
+
+    if descend(dmx, &mut crumbs, level, &mut path)? {
+        Ok(Some(path))
+    } else {
+        Ok(None)
+    }
+}
+
+/**
+Present a hierarchical menu starting in a submenu, as with
+`navigate_path_at()`, but return only the chosen leaf item.
+
+This is handy for binding hotkeys to specific parts of a larger menu
+(like "show my SSH connections").
+*/
+pub fn navigate_at<'a, B, I, S>(
+    dmx: &Dmx,
+    prompt: B,
+    items: &'a [I],
+    keys: &[S],
+) -> Result<Option<&'a I>, DmxError>
+where
+    B: Into<Breadcrumbs>,
+    I: Nested,
+    S: AsRef<str>,
+{
+    let path = navigate_path_at(dmx, prompt, items, keys)?;
+    Ok(path.and_then(|p| resolve_path(items, &p).pop()))
+}
+
+/**
+Turn a path of indices (like that returned by `navigate_path()`) into
+references to the items along it, starting with the top-level item.
+
+If the path leads off the tree, the items up to that point are returned.
+*/
+pub fn resolve_path<'a, I: Nested>(items: &'a [I], path: &[usize]) -> Vec<&'a I> {
+    let mut resolved: Vec<&I> = Vec::with_capacity(path.len());
+    let mut level = Some(items);
+    for &n in path.iter() {
+        match level.and_then(|items| items.get(n)) {
+            Some(item) => {
+                resolved.push(item);
+                level = item.children();
+            }
+            None => break,
+        }
+    }
+    resolved
+}
+
+/*
+Present `items` until either a leaf is chosen (in which case its index is
+pushed onto `path` after those of its ancestors, and `true` is returned)
+or the menu is cancelled (in which case `path` is unchanged).
+*/
+fn descend<I: Nested>(
+    dmx: &Dmx,
+    crumbs: &mut Breadcrumbs,
+    items: &[I],
+    path: &mut Vec<usize>,
+) -> Result<bool, DmxError> {
+    loop {
+        let menu = Menu::new(crumbs.to_string()).items(items);
+        let n = match menu.template(crumbs.is_template()).show(dmx)? {
+            Some(n) => n,
+            None => return Ok(false),
+        };
+        path.push(n);
+        match items[n].children() {
+            None => return Ok(true),
+            Some(children) => {
+                crumbs.push(items[n].crumb());
+                let chosen = descend(dmx, crumbs, children, path)?;
+                crumbs.pop();
+                if chosen {
+                    return Ok(true);
+                }
+                path.pop();
+            }
+        }
+    }
+}
@@ -0,0 +1,286 @@
+/*!
+A keyboard-driven "Open with…": choosing which installed application
+to open a kind of file with, and optionally making it the default.
+
+Applications declare the MIME types they can open in their desktop
+entries (see the `desktop` module); the user's choice of default for each
+type lives in `mimeapps.list`, as described by the
+[MIME applications associations](https://specifications.freedesktop.org/mime-apps-spec/latest/)
+spec.
+
+```no_run
+# use dm_x::{mime, Dmx};
+let dmx = Dmx::default();
+if let Some(app) = mime::pick_for_file(&dmx, "notes.md", true).unwrap() {
+    println!("{} is now the default for notes.md", &app.name);
+}
+```
+*/
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::desktop::{self, DesktopEntry};
+use crate::{Dmx, DmxError, Menu};
+
+const DEFAULTS_GROUP: &str = "[Default Applications]";
+
+/**
+Determine the MIME type of the file at `path`, with `xdg-mime` (or
+`file`, if that isn't installed).
+*/
+pub fn mime_type<P: AsRef<Path>>(path: P) -> Result<String, DmxError> {
+    let path = path.as_ref();
+    std::fs::metadata(path)
+        .map_err(|e| DmxError::io(format!("Unable to read \"{}\"", path.display()), e))?;
+
+    let probes: &[&[&str]] = &[
+        &["xdg-mime", "query", "filetype"],
+        &["file", "--brief", "--mime-type"],
+    ];
+    for probe in probes.iter() {
+        let output = Command::new(probe[0])
+            .args(&probe[1..])
+            .arg(path)
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output();
+        match output {
+            Ok(output) if output.status.success() => {
+                let mime = String::from_utf8_lossy(&output.stdout).trim().to_owned();
+                // Some versions of `xdg-mime` tack on a `; charset=...`.
+                let mime = mime.split(';').next().unwrap_or("").trim();
+                if mime.contains('/') {
+                    return Ok(mime.to_owned());
+                }
+            }
+            _ => continue,
+        }
+    }
+
+    Err(DmxError::NotFound(format!(
+        "the MIME type of \"{}\"",
+        path.display()
+    )))
+}
+
+/*
+`$XDG_CONFIG_HOME` (or `$HOME/.config`).
+*/
+fn config_home() -> Result<PathBuf, DmxError> {
+    config_home_in(|var| std::env::var_os(var))
+}
+
+/*
+`config_home()`, with the environment given by `var_os`.
+*/
+fn config_home_in<F>(var_os: F) -> Result<PathBuf, DmxError>
+where
+    F: Fn(&str) -> Option<std::ffi::OsString>,
+{
+    match var_os("XDG_CONFIG_HOME").filter(|v| !v.is_empty()) {
+        Some(dir) => Ok(PathBuf::from(dir)),
+        None => var_os("HOME")
+            .map(|h| PathBuf::from(h).join(".config"))
+            .ok_or_else(|| DmxError::EnvNotSet {
+                var: "HOME".to_owned(),
+                what: "the configuration directory".to_owned(),
+            }),
+    }
+}
+
+/*
+The `mimeapps.list` files to consult, in order of precedence: the user's
+(in `config_home()`), the system-wide ones (in `$XDG_CONFIG_DIRS`), then
+those next to the desktop entries, with the environment given by
+`var_os`.
+*/
+pub(crate) fn mimeapps_files_in<F>(var_os: F) -> Vec<PathBuf>
+where
+    F: Fn(&str) -> Option<std::ffi::OsString>,
+{
+    config_home_in(&var_os)
+        .into_iter()
+        .chain(crate::xdg_config_dirs_in(&var_os))
+        .chain(desktop::application_dirs_in(&var_os))
+        .map(|d| d.join("mimeapps.list"))
+        .collect()
+}
+
+/*
+The desktop file IDs listed as defaults for `mime` in the `mimeapps.list`
+at `path` (if it exists and has any).
+*/
+pub(crate) fn read_defaults(path: &Path, mime: &str) -> Vec<String> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut in_group = false;
+    for line in text.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_group = line == DEFAULTS_GROUP;
+        } else if in_group {
+            if let Some((key, value)) = line.split_once('=') {
+                if key.trim() == mime {
+                    return value
+                        .split(';')
+                        .map(|id| id.trim())
+                        .filter(|id| !id.is_empty())
+                        .map(|id| id.to_owned())
+                        .collect();
+                }
+            }
+        }
+    }
+    Vec::new()
+}
+
+/*
+Set `id` as the default for `mime` in the `mimeapps.list` at `path`,
+leaving the rest of the file as it was.
+*/
+pub(crate) fn write_default(path: &Path, mime: &str, id: &str) -> Result<(), DmxError> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => {
+            return Err(DmxError::io(
+                format!("Error reading \"{}\"", path.display()),
+                e,
+            ))
+        }
+    };
+    let new_line = format!("{}={};", mime, id);
+
+    let mut lines: Vec<String> = text.lines().map(|l| l.to_owned()).collect();
+    let group = lines.iter().position(|l| l.trim() == DEFAULTS_GROUP);
+    match group {
+        Some(start) => {
+            let end = lines[start + 1..]
+                .iter()
+                .position(|l| l.trim_start().starts_with('['))
+                .map(|n| start + 1 + n)
+                .unwrap_or(lines.len());
+            let existing = lines[start + 1..end].iter().position(|l| {
+                l.split_once('=')
+                    .map(|(key, _)| key.trim() == mime)
+                    .unwrap_or(false)
+            });
+            match existing {
+                Some(n) => lines[start + 1 + n] = new_line,
+                None => {
+                    // After the group's last nonblank line.
+                    let mut at = end;
+                    while at > start + 1 && lines[at - 1].trim().is_empty() {
+                        at -= 1;
+                    }
+                    lines.insert(at, new_line);
+                }
+            }
+        }
+        None => {
+            if lines.last().map(|l| !l.trim().is_empty()).unwrap_or(false) {
+                lines.push(String::new());
+            }
+            lines.push(DEFAULTS_GROUP.to_owned());
+            lines.push(new_line);
+        }
+    }
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| DmxError::io(format!("Error creating \"{}\"", dir.display()), e))?;
+    }
+    let mut text = lines.join("\n");
+    text.push('\n');
+    std::fs::write(path, text)
+        .map_err(|e| DmxError::io(format!("Error writing \"{}\"", path.display()), e))
+}
+
+/**
+Return the desktop file ID of the default application for `mime`, if
+one is set (and installed).
+*/
+pub fn default_application(mime: &str) -> Option<String> {
+    let installed: Vec<String> = desktop::entries().into_iter().map(|e| e.id).collect();
+    mimeapps_files_in(|var| std::env::var_os(var))
+        .iter()
+        .flat_map(|f| read_defaults(f, mime))
+        .find(|id| installed.contains(id))
+}
+
+/**
+Make the application with the given desktop file ID the default for
+`mime`, in the user's `mimeapps.list` (in `$XDG_CONFIG_HOME`).
+*/
+pub fn set_default(mime: &str, id: &str) -> Result<(), DmxError> {
+    write_default(&config_home()?.join("mimeapps.list"), mime, id)
+}
+
+/**
+Return the applications among `entries` that can open `mime`, ordered by
+name. (Applications hidden from menus are included, as they're often
+ones that exist only to open files.)
+*/
+pub fn applications_for(entries: &[DesktopEntry], mime: &str) -> Vec<DesktopEntry> {
+    let mut apps: Vec<DesktopEntry> = entries
+        .iter()
+        .filter(|e| e.mime_types.iter().any(|t| t == mime))
+        .cloned()
+        .collect();
+    apps.sort_by_key(|a| a.name.to_lowercase());
+    apps
+}
+
+/**
+Let the user choose, from the installed applications that can open files
+of type `mime`, which one to use (with the current default highlighted).
+If `make_default` is `true`, the chosen application becomes the default
+for `mime`.
+
+Returns `None` if the menu is cancelled. It's an error for no application
+to claim `mime`.
+*/
+pub fn pick_for_type(
+    dmx: &Dmx,
+    mime: &str,
+    make_default: bool,
+) -> Result<Option<DesktopEntry>, DmxError> {
+    let apps = applications_for(&desktop::entries(), mime);
+    if apps.is_empty() {
+        return Err(DmxError::NotFound(format!(
+            "an installed application that can open {}",
+            mime
+        )));
+    }
+
+    let mut menu = Menu::new(format!("{} with:", mime)).items(&apps);
+    if let Some(current) = default_application(mime) {
+        if let Some(n) = apps.iter().position(|a| a.id == current) {
+            menu = menu.preselect(n);
+        }
+    }
+
+    let app = match menu.show(dmx)? {
+        Some(n) => apps[n].clone(),
+        None => return Ok(None),
+    };
+    if make_default {
+        set_default(mime, &app.id)?;
+    }
+    Ok(Some(app))
+}
+
+/**
+Like `pick_for_type()`, for the type of the file at `path`.
+*/
+pub fn pick_for_file<P: AsRef<Path>>(
+    dmx: &Dmx,
+    path: P,
+    make_default: bool,
+) -> Result<Option<DesktopEntry>, DmxError> {
+    pick_for_type(dmx, &mime_type(path)?, make_default)
+}
@@ -0,0 +1,126 @@
+/*!
+A plain numbered list in the terminal, for when there's no menu program
+to be had at all (like on a headless machine, over SSH).
+
+The items are printed to standard error, each preceded by its number,
+and the user types the number of the one they want (or just presses
+Enter to cancel). Nothing needs to be installed, and nothing but a
+terminal on standard input is needed.
+
+It's available to `Dmx` as `Backend::Numbered`, so it can be the last
+resort in the list of backends:
+
+```toml
+backends = ["dmenu", "fzf", "numbered"]
+```
+
+`NumberedList` is the same thing as a `MenuBackend` of its own:
+
+```no_run
+# use dm_x::{numbered::NumberedList, Menu};
+let colors = &["red", "green", "blue"];
+if let Some(n) = Menu::new("color:").items(colors).show_with(&NumberedList).unwrap() {
+    println!("{}", colors[n]);
+}
+```
+*/
+
+use std::io::{BufRead, Write};
+
+use crate::backend::MenuBackend;
+use crate::DmxError;
+
+/**
+A `MenuBackend` that shows a numbered list in the terminal, and reads the
+number chosen from standard input.
+*/
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct NumberedList;
+
+/**
+A menu waiting to be shown by a `NumberedList`.
+*/
+pub struct NumberedSession {
+    prompt: String,
+    items: Vec<Vec<u8>>,
+}
+
+/*
+Write `message` (if there is one) and `items` to `out`, the items
+numbered, then ask for a number on `input` until a valid one (or nothing)
+is given. Returns the index of the item chosen, or `None` if the user
+entered nothing (or `input` ran out).
+*/
+pub(crate) fn ask<R: BufRead, W: Write>(
+    prompt: &str,
+    message: Option<&str>,
+    items: &[Vec<u8>],
+    mut input: R,
+    mut out: W,
+) -> Result<Option<usize>, DmxError> {
+    if items.is_empty() {
+        return Ok(None);
+    }
+    let width = items.len().to_string().len();
+    let write_err = |e: std::io::Error| DmxError::io("Error writing to the terminal", e);
+
+    if let Some(message) = message {
+        writeln!(out, "{}", message).map_err(write_err)?;
+    }
+    for (n, item) in items.iter().enumerate() {
+        write!(out, "{:>width$}) ", n + 1, width = width).map_err(write_err)?;
+        out.write_all(item.strip_suffix(b"\n").unwrap_or(item))
+            .and_then(|_| out.write_all(b"\n"))
+            .map_err(write_err)?;
+    }
+
+    loop {
+        write!(out, "{} ", prompt)
+            .and_then(|_| out.flush())
+            .map_err(write_err)?;
+        let mut answer = String::new();
+        let read = input
+            .read_line(&mut answer)
+            .map_err(|e| DmxError::io("Error reading from the terminal", e))?;
+        let answer = answer.trim();
+        if read == 0 || answer.is_empty() {
+            return Ok(None);
+        }
+        match answer.parse::<usize>() {
+            Ok(n) if (1..=items.len()).contains(&n) => return Ok(Some(n - 1)),
+            _ => writeln!(
+                out,
+                "Enter a number from 1 to {} (or nothing, to cancel).",
+                items.len()
+            )
+            .map_err(write_err)?,
+        }
+    }
+}
+
+impl MenuBackend for NumberedList {
+    type Session = NumberedSession;
+
+    fn spawn(&self, prompt: &str, _lines: usize) -> Result<NumberedSession, DmxError> {
+        Ok(NumberedSession {
+            prompt: prompt.to_owned(),
+            items: Vec::new(),
+        })
+    }
+
+    fn feed(&self, session: &mut NumberedSession, input: &[Vec<u8>]) -> Result<(), DmxError> {
+        session.items.extend_from_slice(input);
+        Ok(())
+    }
+
+    fn read_selection(&self, session: NumberedSession) -> Result<Option<Vec<u8>>, DmxError> {
+        let n = ask(
+            &session.prompt,
+            None,
+            &session.items,
+            std::io::stdin().lock(),
+            std::io::stderr(),
+        )?;
+        Ok(n.map(|n| session.items[n].clone()))
+    }
+}
@@ -0,0 +1,141 @@
+/*!
+Opening whatever the user picked (a file, a directory, a URL) in the
+application the desktop environment prefers for it.
+*/
+
+use std::io::Read;
+use std::os::unix::process::CommandExt;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use crate::{Dmx, DmxError};
+
+/*
+The programs to try, in order, to open something.
+*/
+const OPENERS: &[&[&str]] = &[&["xdg-open"], &["gio", "open"]];
+
+/*
+How long to wait for an opener to report failure. Most openers exit as
+soon as they've started the application, but some (like `xdg-open` when
+it can't identify the desktop environment) run the application in the
+foreground, and there's no telling how long that will take.
+*/
+const GRACE: Duration = Duration::from_millis(750);
+
+impl Dmx {
+    /**
+    Open `target` (a path or a URL, like one chosen from a menu) with the
+    user's preferred application for it, using `xdg-open` (or `gio open`,
+    if that isn't installed).
+
+    The application is started in its own process group, with no standard
+    input or output, so it outlives this program, and isn't disturbed by
+    anything that happens to this program's terminal. Errors reported by
+    the opener (like there being no application for that kind of file)
+    are returned. The launch is recorded in the audit log (see
+    `Dmx::audit_log`).
+    */
+    pub fn open<S: AsRef<str>>(&self, target: S) -> Result<(), DmxError> {
+        self.open_with(OPENERS, target.as_ref())
+    }
+
+    /**
+    Let the user choose one of `targets` (paths or URLs), and open it with
+    `Dmx::open()`. Returns the index of the target chosen (or `None` if
+    the menu was cancelled); any error, from the menu or from opening the
+    target, is returned.
+    */
+    pub fn select_and_open<P, S>(&self, prompt: P, targets: &[S]) -> Result<Option<usize>, DmxError>
+    where
+        P: AsRef<str>,
+        S: AsRef<str>,
+    {
+        self.select_and_open_with(OPENERS, prompt.as_ref(), targets)
+    }
+
+    pub(crate) fn select_and_open_with<S: AsRef<str>>(
+        &self,
+        openers: &[&[&str]],
+        prompt: &str,
+        targets: &[S],
+    ) -> Result<Option<usize>, DmxError> {
+        let items: Vec<&str> = targets.iter().map(|t| t.as_ref()).collect();
+        let n = match self.select(prompt, &items)? {
+            Some(n) => n,
+            None => return Ok(None),
+        };
+        self.open_with(openers, items[n])?;
+        Ok(Some(n))
+    }
+
+    /*
+    Open `target` with the first of `openers` that's installed.
+    */
+    pub(crate) fn open_with(&self, openers: &[&[&str]], target: &str) -> Result<(), DmxError> {
+        // Anything that doesn't look like a URL had better exist.
+        if !target.contains(':') {
+            std::fs::metadata(target)
+                .map_err(|e| DmxError::io(format!("Unable to open \"{}\"", target), e))?;
+        }
+
+        for opener in openers.iter() {
+            let mut argv: Vec<&str> = opener.to_vec();
+            argv.push(target);
+
+            let mut c = Command::new(argv[0]);
+            c.args(&argv[1..])
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::piped())
+                .process_group(0);
+            self.restrict_env(&mut c);
+
+            let mut child = match c.spawn() {
+                Ok(child) => child,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(DmxError::spawn(argv[0], e)),
+            };
+            self.audit_launched(&argv);
+
+            let start = Instant::now();
+            let status = loop {
+                match child.try_wait() {
+                    Ok(Some(status)) => break status,
+                    Ok(None) if start.elapsed() < GRACE => {
+                        std::thread::sleep(Duration::from_millis(10));
+                    }
+                    _ => {
+                        // It's still running (presumably as the application
+                        // itself), so keep its stderr from filling up, and
+                        // reap it whenever it's done.
+                        std::thread::spawn(move || {
+                            if let Some(mut pipe) = child.stderr.take() {
+                                let _ = std::io::copy(&mut pipe, &mut std::io::stderr());
+                            }
+                            child.wait()
+                        });
+                        return Ok(());
+                    }
+                }
+            };
+            if status.success() {
+                return Ok(());
+            }
+
+            let mut stderr = String::new();
+            if let Some(mut pipe) = child.stderr.take() {
+                let _ = pipe.read_to_string(&mut stderr);
+            }
+            return Err(DmxError::CommandFailed {
+                program: argv[0].to_owned(),
+                status,
+                stderr: stderr.trim().to_owned(),
+            });
+        }
+
+        Err(DmxError::NoneInstalled(
+            openers.iter().map(|o| o[0].to_owned()).collect(),
+        ))
+    }
+}
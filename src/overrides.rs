@@ -0,0 +1,78 @@
+/*!
+Settings for one backend in particular, for when the backends in a chain
+can't share them: rofi is themed with named themes, say, where `dmenu`
+only knows colors.
+*/
+
+#[cfg(feature = "config")]
+use serde::{Deserialize, Serialize};
+
+use crate::{Color, Dmx, Font};
+
+/**
+Settings that take the place of a `Dmx`'s own while one backend is
+showing a menu. They're kept in `Dmx::backend_overrides`, under the
+backend's name (`Backend::name()`), and anything left unset is taken from
+the `Dmx` as usual.
+
+```
+# use dm_x::{BackendOverrides, Dmx};
+let mut dmx = Dmx::default();
+dmx.backend_overrides.insert(
+    "rofi".to_owned(),
+    BackendOverrides {
+        theme: Some("gruvbox-dark".to_owned()),
+        ..Default::default()
+    },
+);
+```
+*/
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "config", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "config", serde(default))]
+pub struct BackendOverrides {
+    /// The font, in place of `Dmx::font`.
+    pub font: Option<Font>,
+    /// The background color, in place of `Dmx::normal_bg`.
+    pub normal_bg: Option<Color>,
+    /// The foreground color, in place of `Dmx::normal_fg`.
+    pub normal_fg: Option<Color>,
+    /// The selected item's background color, in place of `Dmx::select_bg`.
+    pub select_bg: Option<Color>,
+    /// The selected item's foreground color, in place of `Dmx::select_fg`.
+    pub select_fg: Option<Color>,
+    /// The most lines a menu shows, in place of `Dmx::max_lines`.
+    pub max_lines: Option<usize>,
+    /// Arguments added to the end of the backend's command line.
+    pub args: Vec<String>,
+    /// For rofi, the name of (or path to) a theme, which is used instead of
+    /// the colors. (Other backends have no themes; with `strict`, a
+    /// configuration file setting this for one is an error.)
+    pub theme: Option<String>,
+}
+
+impl BackendOverrides {
+    /*
+    `dmx`, with these settings in place of its own.
+    */
+    pub(crate) fn apply_to(&self, dmx: &Dmx) -> Dmx {
+        let mut dmx = dmx.clone();
+        if let Some(font) = &self.font {
+            dmx.font = font.clone();
+        }
+        for (color, setting) in [
+            (&mut dmx.normal_bg, &self.normal_bg),
+            (&mut dmx.normal_fg, &self.normal_fg),
+            (&mut dmx.select_bg, &self.select_bg),
+            (&mut dmx.select_fg, &self.select_fg),
+        ] {
+            if let Some(value) = setting {
+                *color = value.clone();
+            }
+        }
+        if self.max_lines.is_some() {
+            dmx.max_lines = self.max_lines;
+        }
+        dmx
+    }
+}
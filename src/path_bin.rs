@@ -0,0 +1,201 @@
+/*!
+The programs in `$PATH`, as `Item`s: what `dmenu_path` lists for
+`dmenu_run`.
+
+Like `dmenu_path`, `executables()` keeps a list of them in
+`$XDG_CACHE_HOME/dm_x/`, along with the modification times of the
+directories in `$PATH`, and only looks through the directories again when
+one of those changes (or `$PATH` does). A program whose permissions have
+changed doesn't change its directory's modification time; call
+`rebuild()` to pick up changes like that.
+
+```no_run
+# use dm_x::{path_bin, Dmx};
+let programs = path_bin::executables();
+if let Some(n) = Dmx::default().select("run:", &programs).unwrap() {
+    println!("{}", programs[n].path.display());
+}
+```
+*/
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use crate::store::{cache_file, dir_stamp, escape_field, read_lines, unescape_field, write_lines};
+use crate::{DmxError, Item};
+
+/*
+The first line of the cache file; a cache file that doesn't start with
+this is from some other version of this module, and is ignored.
+*/
+const CACHE_HEADER: &str = "dm_x path executables 1";
+
+/**
+A program found in `$PATH`.
+*/
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Executable {
+    /// The program's file name, which is what the menu shows.
+    pub name: String,
+    /// Where it was found; the first of the directories in `$PATH` that
+    /// has a program by that name, just as the shell would run.
+    pub path: PathBuf,
+}
+
+impl Item for Executable {
+    fn key_len(&self) -> usize {
+        0
+    }
+
+    fn line(&self, _: usize) -> Vec<u8> {
+        format!("{}\n", &self.name).into_bytes()
+    }
+
+    fn id(&self) -> String {
+        self.name.clone()
+    }
+}
+
+/*
+The executables in `dirs` (in order of precedence), in order by name.
+Names that aren't UTF-8 (or have newlines in them) are skipped, as no
+menu could show them faithfully.
+*/
+pub(crate) fn scan_dirs(dirs: &[PathBuf]) -> Vec<Executable> {
+    let mut found: BTreeMap<String, PathBuf> = BTreeMap::new();
+
+    for dir in dirs.iter() {
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+        for entry in entries.flatten() {
+            let name = match entry.file_name().into_string() {
+                Ok(name) if !name.contains('\n') => name,
+                _ => continue,
+            };
+            if found.contains_key(&name) {
+                continue;
+            }
+            let path = entry.path();
+            if crate::is_executable(&path) {
+                found.insert(name, path);
+            }
+        }
+    }
+
+    found
+        .into_iter()
+        .map(|(name, path)| Executable { name, path })
+        .collect()
+}
+
+/*
+The directories in `$PATH`, in order.
+*/
+fn path_dirs() -> Vec<PathBuf> {
+    match std::env::var_os("PATH") {
+        Some(path) => std::env::split_paths(&path).collect(),
+        None => Vec::new(),
+    }
+}
+
+/*
+Read the list at `cache`, if it's still accurate for `dirs`.
+*/
+fn read_cache(cache: &PathBuf, dirs: &[PathBuf]) -> Option<Vec<Executable>> {
+    let lines = read_lines(cache).ok()?;
+    let mut lines = lines.iter();
+    if lines.next().map(|l| l.as_str()) != Some(CACHE_HEADER) {
+        return None;
+    }
+
+    let mut lines = lines.peekable();
+    for dir in dirs.iter() {
+        if lines.next() != Some(&dir_stamp(dir)) {
+            return None;
+        }
+    }
+    if lines
+        .peek()
+        .map(|l| l.starts_with("dir\t"))
+        .unwrap_or(false)
+    {
+        // A directory that's no longer in `$PATH`.
+        return None;
+    }
+
+    lines
+        .map(|l| {
+            let fields: Vec<String> = l.split('\t').map(unescape_field).collect();
+            match fields.as_slice() {
+                [tag, name, path] if tag == "exe" => Some(Executable {
+                    name: name.clone(),
+                    path: PathBuf::from(path),
+                }),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/*
+Find the executables in `dirs`, from the list at `cache` unless it's
+stale (or `force` is set), in which case they're scanned for and the list
+is rewritten. The executables are returned along with whether that
+worked.
+*/
+pub(crate) fn load_dirs(
+    dirs: &[PathBuf],
+    cache: &PathBuf,
+    force: bool,
+) -> (Vec<Executable>, Result<(), DmxError>) {
+    if !force {
+        if let Some(found) = read_cache(cache, dirs) {
+            return (found, Ok(()));
+        }
+    }
+
+    // Take the stamps first, so that anything that changes during the
+    // scan makes the new list stale.
+    let mut lines = vec![CACHE_HEADER.to_owned()];
+    lines.extend(dirs.iter().map(|d| dir_stamp(d)));
+    let found = scan_dirs(dirs);
+    lines.extend(found.iter().map(|e| {
+        format!(
+            "exe\t{}\t{}",
+            escape_field(&e.name),
+            escape_field(&e.path.to_string_lossy())
+        )
+    }));
+    let written = write_lines(cache, &lines);
+
+    (found, written)
+}
+
+/**
+The executables in the directories in `$PATH`, in order by name, from the
+cached list if it's up to date (and rebuilding it if it isn't). Where two
+directories have programs with the same name, the one in the directory
+that comes first in `$PATH` is the one returned.
+
+Failing to write the list isn't an error; it just means the next call
+will have to scan again. Use `rebuild()` to find out why.
+*/
+pub fn executables() -> Vec<Executable> {
+    let dirs = path_dirs();
+    match cache_file("path-executables") {
+        Ok(cache) => load_dirs(&dirs, &cache, false).0,
+        Err(_) => scan_dirs(&dirs),
+    }
+}
+
+/**
+Look through all the directories in `$PATH` for executables and rewrite
+the cached list, whether it looks stale or not.
+*/
+pub fn rebuild() -> Result<Vec<Executable>, DmxError> {
+    let cache = cache_file("path-executables")?;
+    let (found, written) = load_dirs(&path_dirs(), &cache, true);
+    written.map(|_| found)
+}
@@ -0,0 +1,242 @@
+/*!
+A menu that runs in the terminal, inside this process, so no menu program
+needs to be installed at all.
+
+`TerminalPicker` implements `MenuBackend`, so any `Menu` can be shown with
+it:
+
+```no_run
+# use dm_x::{picker::TerminalPicker, Menu};
+let colors = &["red", "green", "blue"];
+let picker = TerminalPicker::default();
+if let Some(n) = Menu::new("color:").items(colors).show_with(&picker).unwrap() {
+    println!("{}", colors[n]);
+}
+```
+
+The picker draws on the controlling terminal (`/dev/tty`), so standard
+input and output can be redirected. Type to narrow down the items; Up and
+Down (or Ctrl-P and Ctrl-N) move the highlight, Enter chooses, and Escape
+(or Ctrl-C) cancels.
+*/
+
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::style::{Attribute, Print, SetAttribute};
+use crossterm::terminal::{self, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{cursor, queue};
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+
+use crate::backend::MenuBackend;
+use crate::DmxError;
+
+/**
+An in-process, terminal-based `MenuBackend`.
+*/
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TerminalPicker {
+    /// If `true` (the default), typed text matches items fuzzily (and the
+    /// best matches come first); otherwise, items must contain the text
+    /// (ignoring case), and stay in order.
+    pub fuzzy: bool,
+}
+
+impl std::default::Default for TerminalPicker {
+    fn default() -> Self {
+        TerminalPicker { fuzzy: true }
+    }
+}
+
+/**
+A menu waiting to be shown by a `TerminalPicker`.
+*/
+pub struct PickerSession {
+    prompt: String,
+    lines: usize,
+    items: Vec<String>,
+}
+
+/*
+The indices of the `items` that match `query`, in the order they should
+be displayed.
+*/
+pub(crate) fn filter(items: &[String], query: &str, fuzzy: bool) -> Vec<usize> {
+    if query.is_empty() {
+        return (0..items.len()).collect();
+    }
+
+    if fuzzy {
+        let matcher = SkimMatcherV2::default();
+        let mut scored: Vec<(i64, usize)> = items
+            .iter()
+            .enumerate()
+            .filter_map(|(n, item)| matcher.fuzzy_match(item, query).map(|score| (score, n)))
+            .collect();
+        // Stable, so equally good matches stay in order.
+        scored.sort_by_key(|&(score, _)| std::cmp::Reverse(score));
+        scored.into_iter().map(|(_, n)| n).collect()
+    } else {
+        let query = query.to_lowercase();
+        (0..items.len())
+            .filter(|&n| items[n].to_lowercase().contains(&query))
+            .collect()
+    }
+}
+
+/*
+The first of the rows shown, given that it was `top` and `rows` of them
+fit, so that the row at `cursor` is still among them.
+*/
+pub(crate) fn scroll(top: usize, cursor: usize, rows: usize) -> usize {
+    if cursor < top {
+        cursor
+    } else if cursor >= top + rows {
+        cursor + 1 - rows
+    } else {
+        top
+    }
+}
+
+/*
+Puts the terminal in raw mode on an alternate screen for as long as it
+exists, so that however the picker finishes, the terminal is restored.
+*/
+struct Screen {
+    tty: File,
+}
+
+impl Screen {
+    fn new() -> Result<Screen, DmxError> {
+        let mut tty = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open("/dev/tty")
+            .map_err(|e| DmxError::io("Unable to open the terminal", e))?;
+        let screen = Screen {
+            tty: tty
+                .try_clone()
+                .map_err(|e| DmxError::io("Unable to open the terminal", e))?,
+        };
+        terminal::enable_raw_mode()
+            .map_err(|e| DmxError::io("Unable to set up the terminal", e))?;
+        queue!(tty, EnterAlternateScreen, cursor::Hide)
+            .and_then(|_| tty.flush())
+            .map_err(|e| DmxError::io("Unable to set up the terminal", e))?;
+        Ok(screen)
+    }
+
+    /*
+    Draw the prompt and query, then `rows` of `shown` from `top` on, with
+    the row at `cursor` highlighted.
+    */
+    fn draw(
+        &mut self,
+        session: &PickerSession,
+        query: &str,
+        shown: &[usize],
+        (top, rows): (usize, usize),
+        cursor: usize,
+    ) -> std::io::Result<()> {
+        let width = terminal::size().map(|(w, _)| w as usize).unwrap_or(80);
+        let clip = |s: &str| -> String { s.chars().take(width).collect() };
+
+        queue!(
+            self.tty,
+            cursor::MoveTo(0, 0),
+            terminal::Clear(ClearType::All),
+            Print(clip(&format!("{} {}", &session.prompt, query)))
+        )?;
+        for (row, &n) in shown.iter().enumerate().skip(top).take(rows) {
+            queue!(self.tty, cursor::MoveTo(0, (row - top + 1) as u16))?;
+            if row == cursor {
+                queue!(self.tty, SetAttribute(Attribute::Reverse))?;
+            }
+            queue!(
+                self.tty,
+                Print(clip(&session.items[n])),
+                SetAttribute(Attribute::Reset)
+            )?;
+        }
+        self.tty.flush()
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        let _ = queue!(self.tty, cursor::Show, LeaveAlternateScreen);
+        let _ = self.tty.flush();
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+impl MenuBackend for TerminalPicker {
+    type Session = PickerSession;
+
+    fn spawn(&self, prompt: &str, lines: usize) -> Result<PickerSession, DmxError> {
+        Ok(PickerSession {
+            prompt: prompt.to_owned(),
+            lines: lines.max(1),
+            items: Vec::new(),
+        })
+    }
+
+    fn feed(&self, session: &mut PickerSession, input: &[Vec<u8>]) -> Result<(), DmxError> {
+        session.items.extend(
+            input
+                .iter()
+                .map(|l| String::from_utf8_lossy(l).trim_end_matches('\n').to_owned()),
+        );
+        Ok(())
+    }
+
+    fn read_selection(&self, session: PickerSession) -> Result<Option<Vec<u8>>, DmxError> {
+        let mut screen = Screen::new()?;
+        let mut query = String::new();
+        let mut shown = filter(&session.items, &query, self.fuzzy);
+        let (mut top, mut cursor) = (0, 0);
+
+        loop {
+            // As many lines as were asked for, if the terminal (which may
+            // have been resized) has room for them under the prompt.
+            let height = terminal::size().map(|(_, h)| h as usize).unwrap_or(24);
+            let rows = session.lines.min(height.saturating_sub(1)).max(1);
+            top = scroll(top, cursor, rows);
+            screen
+                .draw(&session, &query, &shown, (top, rows), cursor)
+                .map_err(|e| DmxError::io("Error drawing menu", e))?;
+
+            let key = match event::read().map_err(|e| DmxError::io("Error reading terminal", e))? {
+                Event::Key(key) if key.kind != KeyEventKind::Release => key,
+                _ => continue,
+            };
+            let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+            match (key.code, ctrl) {
+                (KeyCode::Esc, _) | (KeyCode::Char('c'), true) => return Ok(None),
+                (KeyCode::Enter, _) => {
+                    return Ok(shown
+                        .get(cursor)
+                        .map(|&n| session.items[n].clone().into_bytes()));
+                }
+                (KeyCode::Up, _) | (KeyCode::Char('p'), true) => {
+                    cursor = cursor.saturating_sub(1);
+                }
+                (KeyCode::Down, _) | (KeyCode::Char('n'), true) => cursor += 1,
+                (KeyCode::Backspace, _) => {
+                    query.pop();
+                    shown = filter(&session.items, &query, self.fuzzy);
+                    cursor = 0;
+                }
+                (KeyCode::Char(c), false) => {
+                    query.push(c);
+                    shown = filter(&session.items, &query, self.fuzzy);
+                    cursor = 0;
+                }
+                _ => {}
+            }
+            cursor = cursor.min(shown.len().saturating_sub(1));
+        }
+    }
+}
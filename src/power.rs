@@ -0,0 +1,167 @@
+/*!
+A power menu: lock the screen, log out, suspend, hibernate, reboot, or
+shut down, with confirmation for anything that can't be undone.
+
+Everything is done through `systemctl` and `loginctl`, so it works on any
+system running systemd (or elogind), with no special privileges.
+
+```no_run
+# use dm_x::{power, Dmx};
+power::menu(&Dmx::default()).unwrap();
+```
+*/
+
+use std::process::{Command, Stdio};
+
+use crate::{Dmx, DmxError, Item};
+
+/**
+Something the power menu can do.
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    /// Lock the screen.
+    Lock,
+    /// End this login session.
+    Logout,
+    /// Suspend to RAM.
+    Suspend,
+    /// Suspend to disk.
+    Hibernate,
+    /// Restart the computer.
+    Reboot,
+    /// Turn the computer off.
+    Shutdown,
+}
+
+impl Action {
+    /**
+    Every action, in the order the menu shows them.
+    */
+    pub const ALL: &'static [Action] = &[
+        Action::Lock,
+        Action::Logout,
+        Action::Suspend,
+        Action::Hibernate,
+        Action::Reboot,
+        Action::Shutdown,
+    ];
+
+    /**
+    What the action is called in the menu.
+    */
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::Lock => "lock",
+            Action::Logout => "logout",
+            Action::Suspend => "suspend",
+            Action::Hibernate => "hibernate",
+            Action::Reboot => "reboot",
+            Action::Shutdown => "shutdown",
+        }
+    }
+
+    /**
+    Whether the action loses whatever's open, and so should be confirmed.
+    */
+    pub fn needs_confirmation(&self) -> bool {
+        matches!(self, Action::Logout | Action::Reboot | Action::Shutdown)
+    }
+
+    /**
+    The command line that carries out the action.
+    */
+    pub fn argv(&self) -> Vec<String> {
+        let argv: &[&str] = match self {
+            Action::Lock => &["loginctl", "lock-session"],
+            Action::Logout => {
+                return match std::env::var("XDG_SESSION_ID") {
+                    Ok(id) if !id.is_empty() => {
+                        vec!["loginctl".into(), "terminate-session".into(), id]
+                    }
+                    _ => vec!["loginctl".into(), "terminate-user".into(), whoami()],
+                };
+            }
+            Action::Suspend => &["systemctl", "suspend"],
+            Action::Hibernate => &["systemctl", "hibernate"],
+            Action::Reboot => &["systemctl", "reboot"],
+            Action::Shutdown => &["systemctl", "poweroff"],
+        };
+        argv.iter().map(|a| a.to_string()).collect()
+    }
+
+    /**
+    Carry out the action (without asking). It's recorded in the audit log
+    (see `Dmx::audit_log`).
+    */
+    pub fn run(&self, dmx: &Dmx) -> Result<(), DmxError> {
+        let argv = self.argv();
+        dmx.audit_launch(&argv)?;
+        let status = Command::new(&argv[0])
+            .args(&argv[1..])
+            .stdin(Stdio::null())
+            .status()
+            .map_err(|e| DmxError::spawn(&argv[0], e))?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(DmxError::CommandFailed {
+                program: argv[0].clone(),
+                status,
+                stderr: String::new(),
+            })
+        }
+    }
+}
+
+impl Item for Action {
+    fn key_len(&self) -> usize {
+        0
+    }
+
+    fn line(&self, _: usize) -> Vec<u8> {
+        format!("{}\n", self.name()).into_bytes()
+    }
+
+    fn id(&self) -> String {
+        self.name().to_owned()
+    }
+}
+
+/*
+The name of the current user, for `loginctl terminate-user`.
+*/
+fn whoami() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("LOGNAME"))
+        .unwrap_or_default()
+}
+
+/**
+Let the user choose an action, confirm it (if it needs confirming; see
+`Action::needs_confirmation()`), and carry it out. Returns the action
+carried out, or `None` if the menu was cancelled or the action wasn't
+confirmed.
+*/
+pub fn menu(dmx: &Dmx) -> Result<Option<Action>, DmxError> {
+    let action = choose(dmx)?;
+    if let Some(action) = action {
+        action.run(dmx)?;
+    }
+    Ok(action)
+}
+
+/*
+The choosing and confirming half of `menu()`: the action the user chose
+and confirmed, which is not carried out.
+*/
+pub(crate) fn choose(dmx: &Dmx) -> Result<Option<Action>, DmxError> {
+    let action = match dmx.select("power:", Action::ALL)? {
+        Some(n) => Action::ALL[n],
+        None => return Ok(None),
+    };
+    if action.needs_confirmation() && !dmx.confirm(format!("{}?", action.name()))? {
+        return Ok(None);
+    }
+    Ok(Some(action))
+}
@@ -0,0 +1,180 @@
+/*!
+Finding out what a `dmenu` binary can do.
+
+There are a lot of patches for `dmenu`, and a lot of forks of it, and
+which command line options they accept varies. Fortunately, when `dmenu`
+doesn't like its arguments, it prints a usage message listing all the
+ones it _does_ accept, like
+
+```text
+usage: dmenu [-bfiv] [-l lines] [-p prompt] [-fn font] [-m monitor]
+             [-nb color] [-nf color] [-sb color] [-sf color] [-w windowid]
+```
+
+so running it with a bogus argument and reading that tells us what we
+need to know. (Forks that ignore bogus arguments usually answer `--help`
+instead.) Results, including failures, are cached for the life of the
+process, and a binary that hasn't answered within `TIMEOUT` (because it
+took the bogus argument and opened a menu, say) is killed.
+
+`Backend::Dmenu` uses this to leave out any options the binary doesn't
+accept, rather than have it refuse to start. Only options beyond the
+ones every `dmenu` takes (like `-c` from the center patch, or a flag
+renamed in `Dmx::flag_map`) need this, so a `Dmx` that doesn't ask for
+any is never probed.
+
+The version `dmenu -v` reports (like `dmenu-5.2`) is used to adapt the
+options that are accepted by every version, but mean different things in
+some, like `-fn`, which took an X core font name before 4.6.
+*/
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/*
+How long a probed binary gets to answer.
+*/
+const TIMEOUT: Duration = Duration::from_secs(1);
+
+/*
+The flags a binary accepts, and which of them are switches (taking no
+argument).
+*/
+type Usage = (HashSet<String>, HashSet<String>);
+
+static CACHE: OnceLock<Mutex<HashMap<PathBuf, Option<Usage>>>> = OnceLock::new();
+static VERSIONS: OnceLock<Mutex<HashMap<PathBuf, Option<String>>>> = OnceLock::new();
+
+/*
+Extract the set of flags (without their leading `-`) from a usage message,
+along with the set of those that are switches.
+*/
+pub(crate) fn parse_usage(usage: &str) -> Usage {
+    let mut flags: HashSet<String> = HashSet::new();
+    let mut switches: HashSet<String> = HashSet::new();
+    let usage = match usage.find("usage:") {
+        Some(n) => &usage[n..],
+        None => return (flags, switches),
+    };
+
+    for group in usage.split('[').skip(1) {
+        let group = group.split(']').next().unwrap_or("");
+        let mut tokens = group.split_whitespace();
+        let flag = match tokens.next().and_then(|t| t.strip_prefix('-')) {
+            Some(flag) => flag,
+            None => continue,
+        };
+        if tokens.next().is_some() {
+            // A flag followed by the name of its argument, like `-l lines`.
+            flags.insert(flag.to_owned());
+        } else {
+            // A bundle of single-letter switches, like `-bfiv`.
+            flags.extend(flag.chars().map(String::from));
+            switches.extend(flag.chars().map(String::from));
+        }
+    }
+
+    (flags, switches)
+}
+
+/**
+Return the set of flags (without their leading `-`) accepted by the
+`dmenu` binary at `path`, or `None` if it can't be run, or doesn't
+produce a usage message when given a bogus argument.
+*/
+pub(crate) fn flags(path: &Path) -> Option<HashSet<String>> {
+    usage(path).map(|(flags, _)| flags)
+}
+
+/*
+Like `flags()`, but only those flags that are switches, taking no
+argument (like `-b`, but not `-l lines`).
+*/
+pub(crate) fn switches(path: &Path) -> Option<HashSet<String>> {
+    usage(path).map(|(_, switches)| switches)
+}
+
+/*
+The flags and switches the binary at `path` accepts, from the cache, or
+from its usage message.
+*/
+fn usage(path: &Path) -> Option<Usage> {
+    let cache = CACHE.get_or_init(|| Mutex::new(HashMap::new()));
+    if let Some(usage) = cache.lock().ok()?.get(path) {
+        return usage.clone();
+    }
+
+    let usage = ["--dm_x-probe", "--help"].iter().find_map(|arg| {
+        let output = run(Command::new(path).arg(arg))?;
+        let mut text = String::from_utf8_lossy(&output.stderr).into_owned();
+        text.push_str(&String::from_utf8_lossy(&output.stdout));
+        Some(parse_usage(&text)).filter(|usage| !usage.0.is_empty())
+    });
+
+    cache.lock().ok()?.insert(path.to_owned(), usage.clone());
+    usage
+}
+
+/**
+Return what the `dmenu` binary at `path` says its version is when run
+with `-v` (like `dmenu-5.2`), or `None` if it says nothing useful.
+*/
+pub(crate) fn version(path: &Path) -> Option<String> {
+    let cache = VERSIONS.get_or_init(|| Mutex::new(HashMap::new()));
+    if let Some(version) = cache.lock().ok()?.get(path) {
+        return version.clone();
+    }
+
+    let version = run(Command::new(path).arg("-v"))
+        .filter(|o| o.status.success())
+        .and_then(|o| {
+            let text = String::from_utf8_lossy(&o.stdout);
+            text.lines().next().map(|l| l.trim().to_owned())
+        })
+        .filter(|v| parse_version(v).is_some());
+
+    cache.lock().ok()?.insert(path.to_owned(), version.clone());
+    version
+}
+
+/*
+Run `command`, with nothing on its standard input, and collect what it
+prints, or kill it and return `None` if it takes longer than `TIMEOUT`.
+(What's being probed for is a line or two, which can't fill a pipe and
+leave the program waiting to write the rest.)
+*/
+fn run(command: &mut Command) -> Option<Output> {
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .ok()?;
+    let deadline = Instant::now() + TIMEOUT;
+    loop {
+        match child.try_wait() {
+            Ok(Some(_)) => return child.wait_with_output().ok(),
+            Ok(None) if Instant::now() < deadline => std::thread::sleep(Duration::from_millis(5)),
+            _ => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return None;
+            }
+        }
+    }
+}
+
+/*
+Split a version string like `dmenu-4.5` (or `dmenu-wl-0.1`) into the name
+of the program and its major and minor version numbers.
+*/
+pub(crate) fn parse_version(version: &str) -> Option<(&str, u32, u32)> {
+    let (name, numbers) = version.rsplit_once('-')?;
+    let mut numbers = numbers.split('.');
+    let major = numbers.next()?.parse().ok()?;
+    let minor = numbers.next().map(|n| n.parse().ok()).unwrap_or(Some(0))?;
+    Some((name, major, minor))
+}
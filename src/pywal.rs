@@ -0,0 +1,79 @@
+/*!
+Taking the menu's colors from [pywal](https://github.com/dylanaraps/pywal),
+so that menus match the rest of a desktop themed after its wallpaper.
+*/
+
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+use crate::{Color, Dmx, DmxError};
+
+/*
+Where pywal writes the colors it generated: `$PYWAL_CACHE_DIR`, or
+`~/.cache/wal`.
+*/
+fn colors_file() -> Option<PathBuf> {
+    let dir = match std::env::var_os("PYWAL_CACHE_DIR").filter(|v| !v.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(std::env::var_os("HOME")?).join(".cache/wal"),
+    };
+    Some(dir.join("colors.json"))
+}
+
+impl Dmx {
+    /**
+    Return a `Dmx` with the default settings, except for the colors,
+    which are taken from the palette pywal last generated (in
+    `~/.cache/wal/colors.json`) the same way pywal's own `dmenu` template
+    does: the background and foreground for normal items, and `color1`
+    behind the selected item.
+
+    ```no_run
+    # use dm_x::Dmx;
+    let dmx = Dmx::from_pywal().unwrap_or_default();
+    ```
+    */
+    #[doc(cfg(feature = "pywal"))]
+    pub fn from_pywal() -> Result<Dmx, DmxError> {
+        let path = colors_file().ok_or_else(|| DmxError::EnvNotSet {
+            var: "HOME".to_owned(),
+            what: "pywal's colors".to_owned(),
+        })?;
+        Dmx::from_pywal_file(path)
+    }
+
+    /**
+    Like `Dmx::from_pywal()`, but reading the palette from the
+    `colors.json` at `path`.
+    */
+    #[doc(cfg(feature = "pywal"))]
+    pub fn from_pywal_file<P: AsRef<Path>>(path: P) -> Result<Dmx, DmxError> {
+        let path = path.as_ref();
+        let json = std::fs::read(path).map_err(|e| {
+            DmxError::io(
+                format!("Error reading pywal colors from {}", path.display()),
+                e,
+            )
+        })?;
+        let palette: Value = serde_json::from_slice(&json)
+            .map_err(|e| DmxError::ConfigParse(format!("Error parsing pywal colors: {}", &e)))?;
+
+        let color = |group: &str, name: &str| -> Result<Color, DmxError> {
+            match palette[group][name].as_str() {
+                Some(text) => text.parse(),
+                None => Err(DmxError::ConfigParse(format!(
+                    "pywal colors are missing {}.{}.",
+                    group, name
+                ))),
+            }
+        };
+        Ok(Dmx {
+            normal_bg: color("special", "background")?,
+            normal_fg: color("special", "foreground")?,
+            select_bg: color("colors", "color1")?,
+            select_fg: color("special", "foreground")?,
+            ..Default::default()
+        })
+    }
+}
@@ -0,0 +1,343 @@
+/*!
+The other side of rofi: helpers for writing a program that runs as a rofi
+script (`rofi -show mymode -modes "mymode:/path/to/program"`).
+
+rofi runs the script once to get its rows, then again each time the user
+does something, with the entry chosen as its argument and what happened in
+the `ROFI_RETV` environment variable. Whatever the script prints then
+replaces the menu (or, if it prints nothing, rofi exits).
+
+```no_run
+# use dm_x::rofi_script::{Call, Event, Reply, Row};
+let colors = &["red", "green", "blue"];
+let call = Call::from_env();
+match call.event {
+    Event::Start => Reply::new().prompt("color").items(colors).print().unwrap(),
+    Event::Selected => {
+        if let Some(n) = call.index() {
+            println!("{}", colors[n]);
+        }
+        // Printing no rows makes rofi exit.
+    }
+    _ => {}
+}
+```
+
+Rows made from `Item`s (with `Reply::items()`) have their index as their
+`info`, so `Call::index()` can tell which one was chosen even if several
+have the same text.
+*/
+
+use std::io::Write;
+
+use crate::{DmxError, Item};
+
+/**
+What the user did to make rofi run the script (from `ROFI_RETV`).
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// rofi has just started, and wants the initial rows.
+    Start,
+    /// An entry was chosen.
+    Selected,
+    /// The user entered text that isn't one of the entries.
+    Custom,
+    /// One of the custom key bindings (`kb-custom-1` through
+    /// `kb-custom-19`, numbered from 1) was pressed on an entry.
+    Key(u8),
+    /// A value of `ROFI_RETV` this module doesn't know about.
+    Other(u32),
+}
+
+impl Event {
+    fn from_retv(retv: Option<&str>) -> Event {
+        match retv.map(|v| v.trim().parse::<u32>()) {
+            None | Some(Ok(0)) => Event::Start,
+            Some(Ok(1)) => Event::Selected,
+            Some(Ok(2)) => Event::Custom,
+            Some(Ok(n @ 10..=28)) => Event::Key((n - 9) as u8),
+            Some(Ok(n)) => Event::Other(n),
+            Some(Err(_)) => Event::Other(u32::MAX),
+        }
+    }
+}
+
+/**
+One run of the script, as rofi called it.
+*/
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Call {
+    /// What happened.
+    pub event: Event,
+    /// The text of the entry chosen (or entered), if there is one.
+    pub entry: Option<String>,
+    /// The `info` of the row chosen (`ROFI_INFO`), if it had one.
+    pub info: Option<String>,
+    /// The `data` the script's last reply set (`ROFI_DATA`), if any.
+    pub data: Option<String>,
+}
+
+impl Call {
+    /**
+    Read the call from this process's arguments and environment.
+    */
+    pub fn from_env() -> Call {
+        let var = |name: &str| std::env::var(name).ok();
+        Call::new(
+            var("ROFI_RETV").as_deref(),
+            std::env::args().nth(1),
+            var("ROFI_INFO"),
+            var("ROFI_DATA"),
+        )
+    }
+
+    pub(crate) fn new(
+        retv: Option<&str>,
+        entry: Option<String>,
+        info: Option<String>,
+        data: Option<String>,
+    ) -> Call {
+        Call {
+            event: Event::from_retv(retv),
+            entry,
+            info,
+            data,
+        }
+    }
+
+    /**
+    The index of the item chosen, for rows made with `Reply::items()`
+    (or any row whose `info` is a number).
+    */
+    pub fn index(&self) -> Option<usize> {
+        self.info.as_deref().and_then(|i| i.parse().ok())
+    }
+}
+
+/*
+Make `s` safe to use as a row's text (which ends at a newline or NUL), or
+as a value (which also ends at a unit separator).
+*/
+fn clean(s: &str, value: bool) -> String {
+    s.chars()
+        .map(|c| match c {
+            '\n' | '\r' => ' ',
+            '\x1f' if value => ' ',
+            c => c,
+        })
+        .filter(|&c| c != '\0')
+        .collect()
+}
+
+/**
+A row of the menu, with any of the options rofi allows on it.
+*/
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Row {
+    text: String,
+    options: Vec<(&'static str, String)>,
+}
+
+impl Row {
+    /**
+    A row reading `text` (which is also what the script gets back if
+    it's chosen).
+    */
+    pub fn new<S: AsRef<str>>(text: S) -> Row {
+        Row {
+            text: clean(text.as_ref(), false),
+            options: Vec::new(),
+        }
+    }
+
+    fn option<S: AsRef<str>>(mut self, name: &'static str, value: S) -> Row {
+        self.options.push((name, clean(value.as_ref(), true)));
+        self
+    }
+
+    /**
+    The name (or path) of an icon to show with the row.
+    */
+    pub fn icon<S: AsRef<str>>(self, icon: S) -> Row {
+        self.option("icon", icon)
+    }
+
+    /**
+    Text to show instead of the row's text.
+    */
+    pub fn display<S: AsRef<str>>(self, display: S) -> Row {
+        self.option("display", display)
+    }
+
+    /**
+    Hidden text the row can also be found by.
+    */
+    pub fn meta<S: AsRef<str>>(self, meta: S) -> Row {
+        self.option("meta", meta)
+    }
+
+    /**
+    Hidden text passed back in `ROFI_INFO` if the row is chosen.
+    */
+    pub fn info<S: AsRef<str>>(self, info: S) -> Row {
+        self.option("info", info)
+    }
+
+    /**
+    Make the row impossible to choose.
+    */
+    pub fn nonselectable(self) -> Row {
+        self.option("nonselectable", "true")
+    }
+
+    /**
+    Keep the row shown whatever's typed.
+    */
+    pub fn permanent(self) -> Row {
+        self.option("permanent", "true")
+    }
+
+    /**
+    Show the row as urgent.
+    */
+    pub fn urgent(self) -> Row {
+        self.option("urgent", "true")
+    }
+
+    /**
+    Show the row as active.
+    */
+    pub fn active(self) -> Row {
+        self.option("active", "true")
+    }
+
+    fn write_to<W: Write>(&self, out: &mut W) -> std::io::Result<()> {
+        out.write_all(self.text.as_bytes())?;
+        for (n, (name, value)) in self.options.iter().enumerate() {
+            out.write_all(if n == 0 { b"\0" } else { b"\x1f" })?;
+            write!(out, "{}\x1f{}", name, value)?;
+        }
+        out.write_all(b"\n")
+    }
+}
+
+/**
+Everything the script prints in answer to a `Call`: options for the menu
+as a whole, then its rows.
+*/
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Reply {
+    options: Vec<(&'static str, String)>,
+    rows: Vec<Row>,
+}
+
+impl Reply {
+    /**
+    An empty reply; printing it makes rofi exit.
+    */
+    pub fn new() -> Reply {
+        Reply::default()
+    }
+
+    fn option<S: AsRef<str>>(mut self, name: &'static str, value: S) -> Reply {
+        self.options.push((name, clean(value.as_ref(), true)));
+        self
+    }
+
+    /**
+    The prompt.
+    */
+    pub fn prompt<S: AsRef<str>>(self, prompt: S) -> Reply {
+        self.option("prompt", prompt)
+    }
+
+    /**
+    A message to show above the rows (in Pango markup).
+    */
+    pub fn message<S: AsRef<str>>(self, message: S) -> Reply {
+        self.option("message", message)
+    }
+
+    /**
+    Interpret the rows' text as Pango markup.
+    */
+    pub fn markup_rows(self) -> Reply {
+        self.option("markup-rows", "true")
+    }
+
+    /**
+    Only allow one of the rows to be chosen (no custom entries).
+    */
+    pub fn no_custom(self) -> Reply {
+        self.option("no-custom", "true")
+    }
+
+    /**
+    Call the script for custom key bindings too (see `Event::Key`).
+    */
+    pub fn use_hot_keys(self) -> Reply {
+        self.option("use-hot-keys", "true")
+    }
+
+    /**
+    Keep the same row selected rather than going back to the first.
+    */
+    pub fn keep_selection(self) -> Reply {
+        self.option("keep-selection", "true")
+    }
+
+    /**
+    Text handed back to the next call, in `Call::data`.
+    */
+    pub fn data<S: AsRef<str>>(self, data: S) -> Reply {
+        self.option("data", data)
+    }
+
+    /**
+    Add a row.
+    */
+    pub fn row(mut self, row: Row) -> Reply {
+        self.rows.push(row);
+        self
+    }
+
+    /**
+    Add a row for each of `items`, with its icon (if it has one), and its
+    index as its `info` (see `Call::index()`).
+    */
+    pub fn items<I: Item>(mut self, items: &[I]) -> Reply {
+        let key_len = items.iter().map(|x| x.key_len()).max().unwrap_or(0);
+        for (n, item) in items.iter().enumerate() {
+            let line = item.line(key_len);
+            let text = String::from_utf8_lossy(line.strip_suffix(b"\n").unwrap_or(&line));
+            let mut row = Row::new(text).info(n.to_string());
+            if let Some(icon) = item.icon() {
+                row = row.icon(icon);
+            }
+            self.rows.push(row);
+        }
+        self
+    }
+
+    /**
+    Write the reply to `out`, the way rofi expects to read it.
+    */
+    pub fn write_to<W: Write>(&self, mut out: W) -> std::io::Result<()> {
+        for (name, value) in self.options.iter() {
+            writeln!(out, "\0{}\x1f{}", name, value)?;
+        }
+        for row in self.rows.iter() {
+            row.write_to(&mut out)?;
+        }
+        out.flush()
+    }
+
+    /**
+    Print the reply to standard output, for rofi to read.
+    */
+    pub fn print(&self) -> Result<(), DmxError> {
+        self.write_to(std::io::stdout().lock())
+            .map_err(|e| DmxError::io("Error writing to rofi", e))
+    }
+}
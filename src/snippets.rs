@@ -0,0 +1,140 @@
+/*!
+A keyboard-driven snippet manager: choose a piece of canned text from a
+menu, and have it put on the clipboard or typed into the focused window.
+
+Snippets are loaded from a file mapping names to text, either TOML
+
+```toml
+sig = "Cheers,\nDan"
+addr = "123 Fake Street"
+```
+
+or JSON (if the file name ends in `.json`):
+
+```json
+{ "sig": "Cheers,\nDan", "addr": "123 Fake Street" }
+```
+
+Copying is done by the `clipboard` module; typing uses `wtype` in a
+Wayland session and `xdotool` under X11. Whichever is needed
+has to be installed.
+
+```no_run
+# use dm_x::{snippets, Dmx};
+let snips = snippets::load("snippets.toml").unwrap();
+snippets::pick(&Dmx::default(), &snips, snippets::Delivery::Type).unwrap();
+```
+*/
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::clipboard::{self, pipe_to};
+use crate::{Dmx, DmxError, Item, Menu};
+
+/*
+The longest preview of a snippet's text shown in the menu, in `char`s.
+*/
+const PREVIEW_LEN: usize = 60;
+
+/**
+A named piece of text.
+*/
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Snippet {
+    /// What the snippet is called in the menu.
+    pub name: String,
+    /// The text to copy or type.
+    pub text: String,
+}
+
+/**
+Snippets display their name, then (lined up like the two-tuple
+implementation) the beginning of their first line of text.
+*/
+impl Item for Snippet {
+    fn key_len(&self) -> usize {
+        self.name.chars().count()
+    }
+
+    fn line(&self, key_len: usize) -> Vec<u8> {
+        let first = self.text.lines().next().unwrap_or("");
+        let mut preview: String = first.chars().take(PREVIEW_LEN).collect();
+        if preview.len() < self.text.trim_end().len() {
+            preview.push('…');
+        }
+        format!("{:kwidth$}  {}\n", &self.name, &preview, kwidth = key_len).into_bytes()
+    }
+
+    fn id(&self) -> String {
+        self.name.clone()
+    }
+}
+
+/**
+What to do with the chosen snippet.
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Delivery {
+    /// Put it on the clipboard.
+    Clipboard,
+    /// Type it into whatever window has the focus.
+    Type,
+}
+
+/**
+Load the snippets in the file at `path` (JSON if its name ends in `.json`,
+TOML otherwise), in order by name.
+*/
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Vec<Snippet>, DmxError> {
+    let path = path.as_ref();
+    let bytes = std::fs::read(path)
+        .map_err(|e| DmxError::io(format!("Error reading \"{}\"", path.display()), e))?;
+
+    let map: BTreeMap<String, String> = if path.extension().map(|x| x == "json").unwrap_or(false) {
+        serde_json::from_slice(&bytes).map_err(|e| {
+            DmxError::ConfigParse(format!("Error parsing \"{}\": {}", path.display(), &e))
+        })?
+    } else {
+        toml::from_slice(&bytes).map_err(|e| {
+            DmxError::ConfigParse(format!("Error parsing \"{}\": {}", path.display(), &e))
+        })?
+    };
+
+    Ok(map
+        .into_iter()
+        .map(|(name, text)| Snippet { name, text })
+        .collect())
+}
+
+/**
+Deliver `text` as directed: put it on the clipboard, or type it into the
+focused window.
+*/
+pub fn deliver(text: &str, how: Delivery) -> Result<(), DmxError> {
+    let wtype: &[&str] = &["wtype", "-"];
+    let xdotool: &[&str] = &["xdotool", "type", "--clearmodifiers", "--file", "-"];
+
+    match how {
+        Delivery::Clipboard => clipboard::copy(text),
+        Delivery::Type if clipboard::wayland() => pipe_to(&[wtype, xdotool], text),
+        Delivery::Type => pipe_to(&[xdotool, wtype], text),
+    }
+}
+
+/**
+Let the user choose one of `snippets`, and deliver its text as directed.
+Returns the chosen snippet (or `None` if the menu was cancelled).
+*/
+pub fn pick<'a>(
+    dmx: &Dmx,
+    snippets: &'a [Snippet],
+    how: Delivery,
+) -> Result<Option<&'a Snippet>, DmxError> {
+    let snippet = match Menu::new("snippet:").items(snippets).show(dmx)? {
+        Some(n) => &snippets[n],
+        None => return Ok(None),
+    };
+    deliver(&snippet.text, how)?;
+    Ok(Some(snippet))
+}
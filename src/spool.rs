@@ -0,0 +1,83 @@
+/*!
+Feeding enormous menus to the menu program from a file instead of a pipe.
+
+With hundreds of thousands of items, writing the whole menu down a pipe
+means a lot of blocking on a full pipe buffer while the menu program
+catches up (and some menu programs don't read any of their input until
+they've finished setting up their window). Writing it to a file first and
+handing that to the menu program as its standard input avoids all that.
+*/
+
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::DmxError;
+
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/*
+A temporary file holding the body of a menu. The file is removed when
+this is dropped, so it gets cleaned up however the menu turns out.
+*/
+pub(crate) struct Spool {
+    path: PathBuf,
+}
+
+impl Spool {
+    /*
+    Write the given lines to a new temporary file, readable and writable
+    only by the current user.
+    */
+    pub(crate) fn create(lines: &[Vec<u8>]) -> Result<Spool, DmxError> {
+        let mut opts = OpenOptions::new();
+        // `create_new` refuses to follow a symlink (or open anything else)
+        // an attacker might have planted at the path.
+        opts.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            opts.mode(0o600);
+        }
+
+        let path = std::env::temp_dir().join(format!(
+            "dmx-spool-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let f = opts
+            .open(&path)
+            .map_err(|e| DmxError::io(format!("Error creating \"{}\"", path.display()), e))?;
+        // From here on, dropping `spool` removes the file.
+        let spool = Spool { path };
+
+        let mut f = std::io::BufWriter::new(f);
+        for line in lines.iter() {
+            f.write_all(line).map_err(|e| {
+                DmxError::io(format!("Error writing \"{}\"", spool.path.display()), e)
+            })?;
+        }
+        f.flush()
+            .map_err(|e| DmxError::io(format!("Error writing \"{}\"", spool.path.display()), e))?;
+
+        Ok(spool)
+    }
+
+    /*
+    Open the file afresh (so each attempt to show the menu reads it from
+    the beginning) for use as a child process's standard input.
+    */
+    pub(crate) fn stdin(&self) -> Result<Stdio, DmxError> {
+        let f = File::open(&self.path)
+            .map_err(|e| DmxError::io(format!("Error opening \"{}\"", self.path.display()), e))?;
+        Ok(Stdio::from(f))
+    }
+}
+
+impl Drop for Spool {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
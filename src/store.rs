@@ -0,0 +1,329 @@
+/*!
+Persistent per-menu state, like which items the user has pinned, or has
+chosen recently.
+
+Each kind of state is stored in its own small text file, named after a
+"menu id" chosen by the program, in `$XDG_DATA_HOME/dm_x/` (or
+`$HOME/.local/share/dm_x/` if `$XDG_DATA_HOME` isn't set). Items are
+identified by their `Item::id()`.
+
+```no_run
+# use dm_x::{Dmx, Menu, store::Favorites};
+let dmx = Dmx::default();
+let tools = &["htop", "nmtui", "pavucontrol", "arandr"];
+let mut favs = Favorites::load("tools").unwrap();
+
+if let Some(n) = Menu::new("tool:").items(tools).favorites(&favs).show(&dmx).unwrap() {
+    // Pin whatever gets chosen, so it's at the top next time.
+    favs.pin(&tools[n]);
+    favs.save().unwrap();
+}
+```
+*/
+
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::{DmxError, Item};
+
+/*
+The `dm_x` subdirectory of the XDG base directory named by the environment
+variable `var`, or of `default` (relative to `$HOME`) if that isn't set.
+*/
+fn xdg_dir(var: &str, default: &[&str]) -> Result<PathBuf, DmxError> {
+    xdg_dir_in(var, default, |var| std::env::var_os(var))
+}
+
+/*
+`xdg_dir()`, with the environment given by `var_os`.
+*/
+fn xdg_dir_in<F>(var: &str, default: &[&str], var_os: F) -> Result<PathBuf, DmxError>
+where
+    F: Fn(&str) -> Option<OsString>,
+{
+    let mut dir = match var_os(var).filter(|v| !v.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => {
+            let mut dir = PathBuf::from(var_os("HOME").ok_or_else(|| DmxError::EnvNotSet {
+                var: "HOME".to_owned(),
+                what: format!("the directory ${} would name", var),
+            })?);
+            dir.extend(default);
+            dir
+        }
+    };
+    dir.push("dm_x");
+    Ok(dir)
+}
+
+/*
+The path to a file of disposable, regenerable data (like an index), in
+`$XDG_CACHE_HOME/dm_x/` (or `$HOME/.cache/dm_x/`).
+*/
+pub(crate) fn cache_file(name: &str) -> Result<PathBuf, DmxError> {
+    let mut path = xdg_dir("XDG_CACHE_HOME", &[".cache"])?;
+    path.push(name);
+    Ok(path)
+}
+
+/*
+The path to the file holding the given kind of state for the given menu.
+*/
+pub(crate) fn state_file<F>(menu_id: &str, kind: &str, var_os: F) -> Result<PathBuf, DmxError>
+where
+    F: Fn(&str) -> Option<OsString>,
+{
+    let valid = !menu_id.is_empty()
+        && !menu_id.starts_with('.')
+        && menu_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "._-".contains(c));
+    if !valid {
+        return Err(DmxError::InvalidArgument(format!(
+            "Invalid menu id \"{}\": use only letters, digits, '.', '_', and '-'.",
+            menu_id
+        )));
+    }
+
+    let mut path = xdg_dir_in("XDG_DATA_HOME", &[".local", "share"], var_os)?;
+    path.push(format!("{}.{}", menu_id, kind));
+    Ok(path)
+}
+
+/*
+Read the nonempty lines of a state file; a file that doesn't exist
+just has no lines.
+*/
+pub(crate) fn read_lines(path: &PathBuf) -> Result<Vec<String>, DmxError> {
+    match std::fs::read_to_string(path) {
+        Ok(text) => Ok(text
+            .lines()
+            .filter(|l| !l.is_empty())
+            .map(|l| l.to_owned())
+            .collect()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(DmxError::io(
+            format!("Error reading \"{}\"", path.display()),
+            e,
+        )),
+    }
+}
+
+/*
+Replace the contents of a state file with the given lines.
+*/
+pub(crate) fn write_lines(path: &PathBuf, lines: &[String]) -> Result<(), DmxError> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| DmxError::io(format!("Error creating \"{}\"", dir.display()), e))?;
+    }
+    let mut text = lines.join("\n");
+    text.push('\n');
+    std::fs::write(path, text)
+        .map_err(|e| DmxError::io(format!("Error writing \"{}\"", path.display()), e))
+}
+
+/*
+Escape tabs, newlines, and backslashes so a value fits in one field of
+one line of a cache file.
+*/
+pub(crate) fn escape_field(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+}
+
+pub(crate) fn unescape_field(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('t') => out.push('\t'),
+                Some('n') => out.push('\n'),
+                Some(c) => out.push(c),
+                None => {}
+            },
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/*
+A line recording the modification time of `dir`, for a cache file to
+compare against later: if the line changes, so has the directory.
+*/
+pub(crate) fn dir_stamp(dir: &Path) -> String {
+    let mtime = std::fs::metadata(dir)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| format!("{}.{:09}", d.as_secs(), d.subsec_nanos()))
+        .unwrap_or_else(|| "-".to_owned());
+    format!("dir\t{}\t{}", escape_field(&dir.to_string_lossy()), &mtime)
+}
+
+/**
+The set of items the user has pinned in a particular menu.
+
+Pass this to `Menu::favorites()` and the pinned items will always be
+displayed at the top of the menu (in the order they'd otherwise appear).
+
+With rofi, `Menu::show_pinning()` lets the user pin and unpin items with
+a key binding, right in the menu. Other backends can't report key
+bindings, so it's up to the program to offer another way (like a second
+menu, or a command line option).
+*/
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Favorites {
+    path: PathBuf,
+    ids: Vec<String>,
+}
+
+impl Favorites {
+    /**
+    Load the favorites for the given menu id, which should be unique to
+    the menu in question (and consist of only letters, digits, `.`, `_`,
+    and `-`). If none have been saved yet, there are none.
+    */
+    pub fn load(menu_id: &str) -> Result<Favorites, DmxError> {
+        Favorites::load_in(menu_id, |var| std::env::var_os(var))
+    }
+
+    /*
+    `Favorites::load()`, with the environment given by `var_os`.
+    */
+    pub(crate) fn load_in<F>(menu_id: &str, var_os: F) -> Result<Favorites, DmxError>
+    where
+        F: Fn(&str) -> Option<OsString>,
+    {
+        let path = state_file(menu_id, "favorites", var_os)?;
+        let ids = read_lines(&path)?;
+        Ok(Favorites { path, ids })
+    }
+
+    /**
+    Write these favorites back to where they were loaded from.
+    */
+    pub fn save(&self) -> Result<(), DmxError> {
+        write_lines(&self.path, &self.ids)
+    }
+
+    /**
+    Return whether the given item is pinned.
+    */
+    pub fn is_pinned<I: Item>(&self, item: &I) -> bool {
+        self.ids.contains(&item.id())
+    }
+
+    /**
+    Pin the given item (if it isn't already).
+    */
+    pub fn pin<I: Item>(&mut self, item: &I) {
+        if !self.is_pinned(item) {
+            self.ids.push(item.id());
+        }
+    }
+
+    /**
+    Unpin the given item (if it's pinned).
+    */
+    pub fn unpin<I: Item>(&mut self, item: &I) {
+        let id = item.id();
+        self.ids.retain(|x| *x != id);
+    }
+
+    /**
+    Pin the given item if it isn't pinned, or unpin it if it is. Returns
+    whether the item is now pinned.
+    */
+    pub fn toggle<I: Item>(&mut self, item: &I) -> bool {
+        if self.is_pinned(item) {
+            self.unpin(item);
+            false
+        } else {
+            self.pin(item);
+            true
+        }
+    }
+}
+
+/**
+The items most recently chosen from a particular menu, most recent first.
+
+Pass this to `Menu::recent()` to display a few of them in their own
+section above the rest of the menu. `Recent` doesn't notice selections
+by itself; `record()` the chosen item and `save()` after each one.
+*/
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Recent {
+    path: PathBuf,
+    ids: Vec<String>,
+    capacity: usize,
+}
+
+impl Recent {
+    /**
+    Load the recent selections for the given menu id (see
+    `Favorites::load()`), remembering at most 20 of them.
+    */
+    pub fn load(menu_id: &str) -> Result<Recent, DmxError> {
+        Recent::load_in(menu_id, |var| std::env::var_os(var))
+    }
+
+    /*
+    `Recent::load()`, with the environment given by `var_os`.
+    */
+    pub(crate) fn load_in<F>(menu_id: &str, var_os: F) -> Result<Recent, DmxError>
+    where
+        F: Fn(&str) -> Option<OsString>,
+    {
+        let path = state_file(menu_id, "recent", var_os)?;
+        let ids = read_lines(&path)?;
+        Ok(Recent {
+            path,
+            ids,
+            capacity: 20,
+        })
+    }
+
+    /**
+    Set the number of selections to remember.
+    */
+    pub fn capacity(mut self, n: usize) -> Recent {
+        self.capacity = n;
+        self.ids.truncate(n);
+        self
+    }
+
+    /**
+    Write these selections back to where they were loaded from.
+    */
+    pub fn save(&self) -> Result<(), DmxError> {
+        write_lines(&self.path, &self.ids)
+    }
+
+    /**
+    Record that the given item was just chosen.
+    */
+    pub fn record<I: Item>(&mut self, item: &I) {
+        let id = item.id();
+        self.ids.retain(|x| *x != id);
+        self.ids.insert(0, id);
+        self.ids.truncate(self.capacity);
+    }
+
+    /*
+    The indices in `items` of the recently chosen items, most recent first.
+    Selections that aren't among `items` are skipped.
+    */
+    pub(crate) fn indices<I: Item>(&self, items: &[I]) -> Vec<usize> {
+        let item_ids: Vec<String> = items.iter().map(|i| i.id()).collect();
+        self.ids
+            .iter()
+            .filter_map(|id| item_ids.iter().position(|x| x == id))
+            .collect()
+    }
+}
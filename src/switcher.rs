@@ -0,0 +1,202 @@
+/*!
+A window switcher: list the open windows, let the user choose one from a
+menu, and focus it.
+
+Under sway, windows are listed with `swaymsg -t get_tree` (and focused with
+`swaymsg`); anywhere else, `wmctrl -l` (and `wmctrl -i -a`) is used, which
+works with most X11 window managers. Whichever is needed has to be
+installed.
+
+```no_run
+# use dm_x::{switcher, Dmx};
+if let Some(w) = switcher::switch(&Dmx::default()).unwrap() {
+    println!("switched to {}", &w.title);
+}
+```
+*/
+
+use std::process::{Command, Stdio};
+
+use serde_json::Value;
+
+use crate::{Dmx, DmxError, Item};
+
+/*
+How a window was found, and so how to focus it.
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Manager {
+    Wmctrl,
+    Sway,
+}
+
+/**
+An open window.
+*/
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Window {
+    /// The window's ID: an X window ID like `0x03a00003` from `wmctrl`,
+    /// or sway's container ID.
+    pub id: String,
+    /// The desktop (or workspace) the window is on, if it's on one.
+    pub desktop: Option<String>,
+    /// The window's title.
+    pub title: String,
+    pub(crate) manager: Manager,
+}
+
+/**
+Windows display their desktop, then (lined up like the two-tuple
+implementation) their title.
+*/
+impl Item for Window {
+    fn key_len(&self) -> usize {
+        self.desktop.as_deref().unwrap_or("").chars().count()
+    }
+
+    fn line(&self, key_len: usize) -> Vec<u8> {
+        format!(
+            "{:kwidth$}  {}\n",
+            self.desktop.as_deref().unwrap_or(""),
+            self.title.replace('\n', " "),
+            kwidth = key_len
+        )
+        .into_bytes()
+    }
+
+    fn id(&self) -> String {
+        self.id.clone()
+    }
+}
+
+/*
+Run `argv`, and return its standard output, or an error if it can't be run
+or fails.
+*/
+fn run(argv: &[&str]) -> Result<String, DmxError> {
+    let output = Command::new(argv[0])
+        .args(&argv[1..])
+        .stdin(Stdio::null())
+        .stderr(Stdio::inherit())
+        .output()
+        .map_err(|e| DmxError::spawn(argv[0], e))?;
+    if !output.status.success() {
+        return Err(DmxError::CommandFailed {
+            program: argv[0].to_owned(),
+            status: output.status,
+            stderr: String::new(),
+        });
+    }
+    String::from_utf8(output.stdout).map_err(|e| DmxError::Utf8 {
+        context: format!("{} wrote something that isn't UTF-8", argv[0]),
+        source: e,
+    })
+}
+
+/*
+Parse the output of `wmctrl -l`: a window ID, a desktop number (-1 for
+windows on every desktop), the client machine, and the title, separated
+by whitespace.
+*/
+pub(crate) fn parse_wmctrl(text: &str) -> Vec<Window> {
+    text.lines()
+        .filter_map(|line| {
+            let mut rest = line;
+            let mut field = || {
+                let trimmed = rest.trim_start();
+                let end = trimmed.find(char::is_whitespace).unwrap_or(trimmed.len());
+                rest = &trimmed[end..];
+                Some(&trimmed[..end]).filter(|f| !f.is_empty())
+            };
+            let id = field()?;
+            let desktop = field()?;
+            let _host = field()?;
+            Some(Window {
+                id: id.to_owned(),
+                desktop: Some(desktop.to_owned()).filter(|d| d != "-1"),
+                title: rest.trim().to_owned(),
+                manager: Manager::Wmctrl,
+            })
+        })
+        .collect()
+}
+
+/*
+Collect the windows in sway's layout tree (as printed by
+`swaymsg -t get_tree`), noting which workspace each is on.
+*/
+pub(crate) fn parse_sway(tree: &Value) -> Vec<Window> {
+    fn walk(node: &Value, workspace: Option<&str>, windows: &mut Vec<Window>) {
+        let workspace = match node["type"].as_str() {
+            Some("workspace") => node["name"].as_str().map(|n| match n {
+                "__i3_scratch" => "scratchpad",
+                n => n,
+            }),
+            _ => workspace,
+        };
+        // Only windows have a process behind them.
+        if node.get("pid").is_some() {
+            if let Some(id) = node["id"].as_u64() {
+                windows.push(Window {
+                    id: id.to_string(),
+                    desktop: workspace.map(|w| w.to_owned()),
+                    title: node["name"].as_str().unwrap_or("").to_owned(),
+                    manager: Manager::Sway,
+                });
+            }
+        }
+        for key in ["nodes", "floating_nodes"] {
+            if let Some(children) = node[key].as_array() {
+                for child in children.iter() {
+                    walk(child, workspace, windows);
+                }
+            }
+        }
+    }
+
+    let mut windows = Vec::new();
+    walk(tree, None, &mut windows);
+    windows
+}
+
+/**
+List the open windows.
+*/
+pub fn list() -> Result<Vec<Window>, DmxError> {
+    if std::env::var_os("SWAYSOCK")
+        .filter(|v| !v.is_empty())
+        .is_some()
+    {
+        let json = run(&["swaymsg", "-t", "get_tree", "-r"])?;
+        let tree: Value = serde_json::from_str(&json).map_err(|e| {
+            DmxError::ConfigParse(format!("Error parsing sway's window tree: {}", &e))
+        })?;
+        Ok(parse_sway(&tree))
+    } else {
+        Ok(parse_wmctrl(&run(&["wmctrl", "-l"])?))
+    }
+}
+
+/**
+Focus `window` (switching to its desktop, if need be).
+*/
+pub fn focus(window: &Window) -> Result<(), DmxError> {
+    match window.manager {
+        Manager::Wmctrl => run(&["wmctrl", "-i", "-a", &window.id]),
+        Manager::Sway => run(&["swaymsg", &format!("[con_id={}] focus", &window.id)]),
+    }
+    .map(|_| ())
+}
+
+/**
+Let the user choose one of the open windows, and focus it. Returns the
+window chosen (or `None` if the menu was cancelled).
+*/
+pub fn switch(dmx: &Dmx) -> Result<Option<Window>, DmxError> {
+    let window = match dmx.select_owned("window:", list()?)? {
+        Some(window) => window,
+        None => return Ok(None),
+    };
+    focus(&window)?;
+    Ok(Some(window))
+}
@@ -16,9 +16,19 @@ const STR_CHOICES: &[&str] = &[
     "Aries is SO MISERABLE (she's not joking...)",
 ];
 
+/*
+`dmx`, with a `$DISPLAY` for its menus (the session's, or else `:0`), so
+that tests which show menus don't depend on being run in one.
+*/
+fn with_display(mut dmx: Dmx) -> Dmx {
+    let display = std::env::var("DISPLAY").unwrap_or_else(|_| ":0".to_owned());
+    dmx.env.entry("DISPLAY".to_owned()).or_insert(display);
+    dmx
+}
+
 #[test]
 fn builtins() {
-    let cfg = Dmx::default();
+    let cfg = with_display(Dmx::default());
     let r = cfg.select("tuples", TUPLE_CHOICES).unwrap();
     println!("(tuple) Selected: {:?}", &r);
 
@@ -42,7 +52,7 @@ fn test_readme() {
         "None of the Above",
     ];
     
-    let dmx = Dmx::default();
+    let dmx = with_display(Dmx::default());
     
     match dmx.select("Pick One:", choices).unwrap() {
         None => {
@@ -62,7 +72,7 @@ fn test_readme() {
 #[cfg(feature = "config")]
 #[test]
 fn test_config_file() {
-    let dmx = Dmx::from_file("test/dmx_conf.toml").unwrap();
+    let dmx = with_display(Dmx::from_file("test/dmx_conf.toml").unwrap());
     match dmx.select(">", TUPLE_CHOICES).unwrap() {
         None => {
             println!("You chose [ dramatic pause ] NOTHING!!11");
@@ -93,7 +103,7 @@ fn readme_config() {
         ("dog", "Just a Regular Border Collie")
     ];
     
-    let dmx = Dmx::from_file("test/dmx_conf.toml").unwrap();
+    let dmx = with_display(Dmx::from_file("test/dmx_conf.toml").unwrap());
     
     match dmx.select("->", CHOICES).unwrap() {
         None => {
@@ -103,4 +113,2777 @@ fn readme_config() {
             println!("You will be accompanied by {}", CHOICES[n].1);
         }
     }
-}
\ No newline at end of file
+}
+#[test]
+fn menu_preselect_order() {
+    let menu = Menu::new("order").items(STR_CHOICES).preselect(2);
+    assert_eq!(menu.order(), vec![2, 0, 1, 3]);
+
+    let menu = Menu::new("order").items(STR_CHOICES).preselect(17);
+    assert_eq!(menu.order(), vec![0, 1, 2, 3]);
+
+    // rofi is told to highlight it, too.
+    let hints = MenuHints {
+        selected: Some(2),
+        ..Default::default()
+    };
+    let mut cmd = Dmx::default().cmd(&Backend::Rofi, "order", 4);
+    Backend::Rofi.apply_hints(&mut cmd, &hints);
+    let args: Vec<_> = cmd.get_args().collect();
+    assert!(args.windows(2).any(|w| w == ["-selected-row", "2"]));
+
+    // The index is the item's own, and a spooled menu is in the same order.
+    let dmx = Dmx {
+        command: Some(vec!["sed".to_owned(), "-n".to_owned(), "1p".to_owned()]),
+        ..Default::default()
+    };
+    let menu = Menu::new("order").items(STR_CHOICES).preselect(2);
+    assert_eq!(menu.show(&dmx).unwrap(), Some(2));
+    let dmx = Dmx {
+        spool_threshold: Some(1),
+        ..dmx
+    };
+    assert_eq!(menu.show(&dmx).unwrap(), Some(2));
+}
+
+#[test]
+fn dmx_traits() {
+    let dmx = Dmx::default();
+    let mut other = dmx.clone();
+    assert_eq!(dmx, other);
+
+    other.font = "Terminus-12".into();
+    assert_ne!(dmx, other);
+    assert!(format!("{:?}", &other).contains("Terminus-12"));
+}
+
+#[test]
+fn command_template() {
+    let dmx = Dmx {
+        command: Some(vec![
+            "mymenu".to_owned(),
+            "-p".to_owned(),
+            "{prompt}".to_owned(),
+            "--lines={lines}".to_owned(),
+            "{{literal}} {unknown}".to_owned(),
+        ]),
+        ..Default::default()
+    };
+    let cmd = dmx.cmd(&dmx.chain()[0], "pick {font}:", 7);
+    assert_eq!(cmd.get_program(), "mymenu");
+    let args: Vec<_> = cmd.get_args().collect();
+    assert_eq!(args, ["-p", "pick {font}:", "--lines=7", "{literal} {unknown}"]);
+}
+
+#[test]
+fn latin1_encoding() {
+    let line = "Café ☕\n".as_bytes();
+    let encoded = Encoding::Latin1.encode(line);
+    assert_eq!(encoded, b"Caf\xe9 ?\n");
+    assert_eq!(Encoding::Latin1.decode(&encoded), "Café ?\n");
+    assert_eq!(Encoding::Utf8.encode(line), line);
+}
+
+#[test]
+fn env_allowlist() {
+    let dmx = Dmx {
+        env_allowlist: Some(vec!["DMX_TEST_PUBLIC".to_owned()]),
+        ..Default::default()
+    };
+    let mut cmd = Command::new("env");
+    dmx.restrict_env_in(&mut cmd, |var| match var {
+        "DMX_TEST_SECRET" => Some("hunter2".into()),
+        "DMX_TEST_PUBLIC" => Some("hello".into()),
+        _ => None,
+    });
+    let envs: Vec<_> = cmd.get_envs().collect();
+    assert!(envs.contains(&("DMX_TEST_PUBLIC".as_ref(), Some("hello".as_ref()))));
+    assert!(!envs.iter().any(|(k, _)| *k == "DMX_TEST_SECRET"));
+}
+
+#[test]
+fn pinned_binary() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mut dmx = Dmx {
+        dmenu: "sh".into(),
+        ..Default::default()
+    };
+    dmx.pin_binary().unwrap();
+    assert!(dmx.dmenu.is_absolute());
+    assert!(dmx.absolute_binary);
+
+    dmx.dmenu = "dmenu".into();
+    assert!(dmx.select("nope", STR_CHOICES).is_err());
+
+    // A relative path is refused before it's run for anything, even the
+    // probes (which options like `center` and `fuzzy` would need).
+    let marker = std::env::temp_dir().join(format!("dmx-hostile-{}", std::process::id()));
+    let hostile = format!("target/dmx-hostile-{}", std::process::id());
+    std::fs::write(&hostile, format!("#!/bin/sh\ntouch '{}'\n", marker.display())).unwrap();
+    std::fs::set_permissions(&hostile, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let dmx = Dmx {
+        dmenu: hostile.clone().into(),
+        absolute_binary: true,
+        center: true,
+        fuzzy: true,
+        env: [("DISPLAY".to_owned(), ":0".to_owned())].into(),
+        ..Default::default()
+    };
+    match dmx.select("nope", STR_CHOICES) {
+        Err(DmxError::NotAbsolute { program, .. }) => assert_eq!(program, Path::new(&hostile)),
+        other => panic!("{:?}", other),
+    }
+    assert!(dmx.validate().iter().any(|e| matches!(e, DmxError::NotAbsolute { .. })));
+    assert_eq!(dmx.dmenu_version(), None);
+    assert!(!marker.exists());
+    std::fs::remove_file(&hostile).unwrap();
+}
+
+#[test]
+fn audit_log() {
+    let path = std::env::temp_dir().join(format!("dmx-audit-{}.log", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let dmx = Dmx {
+        command: Some(vec!["sed".to_owned(), "-n".to_owned(), "2p".to_owned()]),
+        audit_log: Some(path.clone()),
+        ..Default::default()
+    };
+
+    assert_eq!(dmx.select("audit", STR_CHOICES).unwrap(), Some(1));
+    dmx.audit_launch(&["x-terminal-emulator", "-e", "it's"]).unwrap();
+
+    let log = std::fs::read_to_string(&path).unwrap();
+    let lines: Vec<Vec<&str>> = log.lines().map(|l| l.split('\t').collect()).collect();
+    assert_eq!(&lines[0][1..], ["menu", "selected", "4", "sed -n 2p"]);
+    assert_eq!(&lines[1][1..], ["launch", "-", "-", r"x-terminal-emulator -e 'it'\''s'"]);
+    std::fs::remove_file(&path).unwrap();
+
+    // A log that can't be written doesn't lose the selection.
+    let dmx = Dmx {
+        audit_log: Some(std::env::temp_dir()),
+        ..dmx
+    };
+    assert_eq!(dmx.select("audit", STR_CHOICES).unwrap(), Some(1));
+    // Nor does it make an application that was launched look like it
+    // wasn't.
+    let entry = desktop::DesktopEntry {
+        id: "true.desktop".to_owned(),
+        path: "true.desktop".into(),
+        name: "True".to_owned(),
+        generic_name: None,
+        exec: "true".to_owned(),
+        icon: None,
+        mime_types: Vec::new(),
+        no_display: false,
+    };
+    entry.launch(&dmx).unwrap();
+}
+
+#[test]
+fn no_display() {
+    let dmx = Dmx {
+        env_allowlist: Some(vec!["PATH".to_owned()]),
+        ..Default::default()
+    };
+    let err = dmx.select("display", STR_CHOICES).unwrap_err();
+    assert!(matches!(err, DmxError::NoDisplay { .. }), "{:?}", err);
+    assert!(err.to_string().contains("No X display available"));
+
+    let dmx = Dmx {
+        env_allowlist: Some(vec!["PATH".to_owned()]),
+        env: [("WAYLAND_DISPLAY".to_owned(), "wayland-1".to_owned())].into(),
+        ..Default::default()
+    };
+    let err = dmx.select("display", STR_CHOICES).unwrap_err();
+    assert!(matches!(err, DmxError::NoDisplay { .. }), "{:?}", err);
+    assert!(err.to_string().contains("This is a Wayland session"));
+}
+
+#[test]
+fn backend_chain() {
+    let dmx = Dmx {
+        backends: vec![
+            Backend::Command(vec!["/nonexistent/menu".to_owned()]),
+            Backend::Command(vec!["sed".to_owned(), "-n".to_owned(), "3p".to_owned()]),
+        ],
+        ..Default::default()
+    };
+    assert_eq!(dmx.select("chain", STR_CHOICES).unwrap(), Some(2));
+
+    let dmx = Dmx {
+        backends: vec![Backend::Command(vec!["/nonexistent/menu".to_owned()])],
+        ..Default::default()
+    };
+    assert!(dmx.select("chain", STR_CHOICES).is_err());
+}
+
+#[cfg(feature = "config")]
+#[test]
+fn backends_config() {
+    let dmx = Dmx::from_bytes(br#"backends = ["fuzzel", "dmenu", ["mymenu", "{prompt}"]]"#).unwrap();
+    assert_eq!(
+        dmx.backends,
+        vec![
+            Backend::Fuzzel,
+            Backend::Dmenu,
+            Backend::Command(vec!["mymenu".to_owned(), "{prompt}".to_owned()]),
+        ]
+    );
+    assert!(Dmx::from_bytes(br#"backends = ["notepad"]"#).is_err());
+}
+
+#[test]
+fn stderr_capture() {
+    let dmx = Dmx {
+        command: Some(vec![
+            "sh".to_owned(),
+            "-c".to_owned(),
+            "cat >/dev/null; echo 'bad font' >&2; exit 1".to_owned(),
+        ]),
+        stderr: StderrMode::Capture,
+        ..Default::default()
+    };
+    let err = dmx.select("stderr", STR_CHOICES).unwrap_err();
+    assert!(err.to_string().ends_with("bad font"));
+
+    let dmx = Dmx {
+        stderr: StderrMode::Discard,
+        ..dmx
+    };
+    assert_eq!(dmx.select("stderr", STR_CHOICES).unwrap(), None);
+}
+
+#[test]
+fn exit_statuses() {
+    let exiting = |status: i32| Dmx {
+        command: Some(vec![
+            "sh".to_owned(),
+            "-c".to_owned(),
+            format!("cat >/dev/null; exit {}", status),
+        ]),
+        ..Default::default()
+    };
+    assert_eq!(exiting(1).select("exit", STR_CHOICES).unwrap(), None);
+    assert!(exiting(2).select("exit", STR_CHOICES).is_err());
+}
+
+#[test]
+fn retry_transient() {
+    let marker = std::env::temp_dir().join(format!("dmx-retry-{}", std::process::id()));
+    let _ = std::fs::remove_file(&marker);
+    // Fails, complaining about the display, the first time only.
+    let script = format!(
+        "if [ -e {0} ]; then sed -n 1p; else touch {0}; cat >/dev/null; \
+        echo 'cannot open display' >&2; exit 1; fi",
+        marker.display()
+    );
+    let dmx = Dmx {
+        command: Some(vec!["sh".to_owned(), "-c".to_owned(), script]),
+        stderr: StderrMode::Discard,
+        retry: Some(RetryPolicy {
+            initial_delay_ms: 1,
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    assert_eq!(dmx.select("retry", STR_CHOICES).unwrap(), Some(0));
+    std::fs::remove_file(&marker).unwrap();
+
+    let policy = RetryPolicy::default();
+    assert_eq!(policy.delay(0).as_millis(), 250);
+    assert_eq!(policy.delay(2).as_millis(), 1000);
+    assert_eq!(policy.delay(40).as_millis(), 2000);
+}
+
+struct Node(&'static str, Vec<Node>);
+
+impl Item for Node {
+    fn key_len(&self) -> usize {
+        0
+    }
+    fn line(&self, _: usize) -> Vec<u8> {
+        self.0.as_bytes().to_vec()
+    }
+}
+
+impl menu::Nested for Node {
+    fn children(&self) -> Option<&[Node]> {
+        if self.1.is_empty() {
+            None
+        } else {
+            Some(&self.1)
+        }
+    }
+    fn crumb(&self) -> String {
+        self.0.to_owned()
+    }
+}
+
+#[test]
+fn nested_path() {
+    let tree = vec![
+        Node("ssh", vec![Node("work", vec![]), Node("home", vec![])]),
+        Node("term", vec![]),
+    ];
+    // Always chooses the first item.
+    let dmx = Dmx {
+        command: Some(vec!["sed".to_owned(), "-n".to_owned(), "1p".to_owned()]),
+        ..Default::default()
+    };
+    let path = menu::navigate_path(&dmx, "", &tree).unwrap().unwrap();
+    assert_eq!(path, vec![0, 0]);
+    let names: Vec<_> = menu::resolve_path(&tree, &path).iter().map(|n| n.0).collect();
+    assert_eq!(names, ["ssh", "work"]);
+    assert_eq!(menu::navigate(&dmx, "", &tree).unwrap().unwrap().0, "work");
+}
+
+#[test]
+fn nested_at() {
+    let tree = vec![
+        Node("term", vec![]),
+        Node("ssh", vec![Node("work", vec![]), Node("home", vec![])]),
+    ];
+    // Always chooses the second item.
+    let dmx = Dmx {
+        command: Some(vec!["sed".to_owned(), "-n".to_owned(), "2p".to_owned()]),
+        ..Default::default()
+    };
+    let path = menu::navigate_path_at(&dmx, "", &tree, &["ssh"]).unwrap();
+    assert_eq!(path, Some(vec![1, 1]));
+    assert_eq!(menu::navigate_at(&dmx, "", &tree, &["ssh"]).unwrap().unwrap().0, "home");
+    assert!(menu::navigate_at(&dmx, "", &tree, &["term"]).is_err());
+}
+
+struct Weighted(&'static str, i32);
+
+impl Item for Weighted {
+    fn key_len(&self) -> usize {
+        0
+    }
+    fn line(&self, _: usize) -> Vec<u8> {
+        self.0.as_bytes().to_vec()
+    }
+    fn weight(&self) -> i32 {
+        self.1
+    }
+}
+
+#[test]
+fn weight_order() {
+    let items = &[
+        Weighted("a", 0),
+        Weighted("b", 5),
+        Weighted("c", -1),
+        Weighted("d", 5),
+        Weighted("e", 0),
+    ];
+    let menu = Menu::new("weights").items(items);
+    assert_eq!(menu.order(), vec![1, 3, 0, 4, 2]);
+    let menu = menu.preselect(4);
+    assert_eq!(menu.order(), vec![4, 1, 3, 0, 2]);
+
+    // History breaks ties between items of the same weight (but doesn't
+    // outweigh anything).
+    let mut recent = store::Recent::load_in("test-weights", scratch_env).unwrap();
+    recent.record(&items[2]);
+    recent.record(&items[0]);
+    recent.record(&items[4]);
+    recent.record(&items[3]);
+    let menu = Menu::new("weights").items(items).recent(&recent, 0);
+    assert_eq!(menu.order(), vec![3, 1, 4, 0, 2]);
+}
+
+/*
+An environment for the `store` module with $XDG_DATA_HOME in a scratch
+directory (and nothing else). Every test gets the same directory, so
+tests must use distinct menu ids.
+*/
+fn scratch_env(var: &str) -> Option<std::ffi::OsString> {
+    match var {
+        "XDG_DATA_HOME" => {
+            Some(std::env::temp_dir().join(format!("dmx-data-{}", std::process::id())).into())
+        }
+        _ => None,
+    }
+}
+
+/*
+The directory where the `store` module keeps its files in `scratch_env()`.
+*/
+fn scratch_data_dir() -> std::path::PathBuf {
+    PathBuf::from(scratch_env("XDG_DATA_HOME").unwrap()).join("dm_x")
+}
+
+#[test]
+fn favorites() {
+    let dir = scratch_data_dir();
+
+    let mut favs = store::Favorites::load_in("test-favs", scratch_env).unwrap();
+    favs.pin(&STR_CHOICES[2]);
+    assert!(favs.toggle(&STR_CHOICES[3]));
+    assert!(!favs.toggle(&STR_CHOICES[3]));
+    favs.save().unwrap();
+
+    let favs = store::Favorites::load_in("test-favs", scratch_env).unwrap();
+    assert!(favs.is_pinned(&STR_CHOICES[2]));
+    let menu = Menu::new("favs").items(STR_CHOICES).favorites(&favs);
+    assert_eq!(menu.order(), vec![2, 0, 1, 3]);
+
+    assert!(store::Favorites::load_in("../escape", scratch_env).is_err());
+    std::fs::remove_file(dir.join("test-favs.favorites")).unwrap();
+}
+
+#[test]
+fn pin_key() {
+    use std::cell::Cell;
+
+    // Pins the last line with the first key, then chooses the first line.
+    struct Pinner(Cell<usize>);
+    impl MenuBackend for Pinner {
+        type Session = ();
+        fn spawn(&self, _: &str, _: usize) -> Result<(), DmxError> {
+            Ok(())
+        }
+        fn feed(&self, _: &mut (), _: &[Vec<u8>]) -> Result<(), DmxError> {
+            Ok(())
+        }
+        fn read_selection(&self, _: ()) -> Result<Option<Vec<u8>>, DmxError> {
+            Ok(None)
+        }
+        fn choose_hinted(
+            &self,
+            _: &str,
+            _: usize,
+            input: &[Vec<u8>],
+            hints: &MenuHints,
+        ) -> Result<Option<Vec<u8>>, DmxError> {
+            assert_eq!(hints.keys, ["Alt+p"]);
+            self.0.set(self.0.get() + 1);
+            if self.0.get() == 1 {
+                hints.key.set(Some(0));
+                return Ok(input.last().cloned());
+            }
+            Ok(input.first().cloned())
+        }
+    }
+
+    let dir = scratch_data_dir();
+    let mut favs = store::Favorites::load_in("test-pin-key", scratch_env).unwrap();
+    let pinner = Pinner(Cell::new(0));
+    let menu = Menu::new("pin").items(STR_CHOICES);
+    assert_eq!(menu.show_pinning(&pinner, &mut favs, "Alt+p").unwrap(), Some(3));
+    assert_eq!(pinner.0.get(), 2);
+    assert!(store::Favorites::load_in("test-pin-key", scratch_env).unwrap().is_pinned(&STR_CHOICES[3]));
+
+    let hints = MenuHints {
+        keys: vec!["Alt+p".to_owned()],
+        ..Default::default()
+    };
+    let mut cmd = Dmx::default().cmd(&Backend::Rofi, "pin", 4);
+    Backend::Rofi.apply_hints(&mut cmd, &hints);
+    let args: Vec<_> = cmd.get_args().collect();
+    assert!(args.windows(2).any(|w| w == ["-kb-custom-1", "Alt+p"]));
+    std::fs::remove_file(dir.join("test-pin-key.favorites")).unwrap();
+}
+
+#[test]
+fn recent_section() {
+    let dir = scratch_data_dir();
+
+    let mut recent = store::Recent::load_in("test-recent", scratch_env).unwrap().capacity(2);
+    recent.record(&STR_CHOICES[1]);
+    recent.record(&STR_CHOICES[3]);
+    recent.record(&STR_CHOICES[2]);
+    recent.save().unwrap();
+
+    let recent = store::Recent::load_in("test-recent", scratch_env).unwrap();
+    let menu = Menu::new("recent").items(STR_CHOICES).recent(&recent, 5);
+    assert_eq!(menu.rows(), vec![Some(2), Some(3), None, Some(0), Some(1)]);
+    let menu = menu.preselect(0);
+    assert_eq!(menu.rows(), vec![Some(0), Some(2), Some(3), None, Some(1)]);
+
+    // The preselected item doesn't make the section any longer (and what
+    // it pushes out of it still comes before what wasn't chosen lately).
+    let menu = Menu::new("recent").items(STR_CHOICES).recent(&recent, 2).preselect(1);
+    assert_eq!(menu.rows(), vec![Some(1), Some(2), None, Some(3), Some(0)]);
+    let menu = Menu::new("recent").items(&STR_CHOICES[2..]).recent(&recent, 2);
+    assert_eq!(menu.rows(), vec![Some(0), Some(1)]);
+
+    std::fs::remove_file(dir.join("test-recent.recent")).unwrap();
+}
+
+/*
+Write a shell script that prints the given usage message (like a `dmenu`
+that didn't like its arguments), and return its path.
+*/
+fn fake_dmenu(name: &str, usage: &str) -> std::path::PathBuf {
+    use std::os::unix::fs::PermissionsExt;
+
+    let path = std::env::temp_dir().join(format!("dmx-{}-{}", name, std::process::id()));
+    let script = format!("#!/bin/sh\necho '{}' >&2\nexit 1\n", usage);
+    std::fs::write(&path, script).unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    path
+}
+
+#[test]
+fn probe_usage() {
+    let (flags, switches) = probe::parse_usage(
+        "usage: dmenu [-bfFiv] [-l lines] [-p prompt] [-fn font] [-m monitor]\n\
+         [-nb color] [-nf color] [-sb color] [-sf color] [-w windowid]",
+    );
+    for flag in ["b", "F", "v", "l", "fn", "sf", "w"] {
+        assert!(flags.contains(flag), "missing {}", flag);
+    }
+    assert!(!flags.contains("bfFiv"));
+    assert!(switches.contains("F") && !switches.contains("l"));
+}
+
+#[test]
+fn fuzzy_flag() {
+    // `check()` wants a display to show menus on.
+    let patched = Dmx {
+        dmenu: fake_dmenu("fuzzy", "usage: dmenu [-bfFiv] [-l lines]"),
+        fuzzy: true,
+        env: [("DISPLAY".to_owned(), ":0".to_owned())].into(),
+        ..Default::default()
+    };
+    let cmd = patched.cmd(&Backend::Dmenu, "fuzzy", 3);
+    assert!(cmd.get_args().any(|a| a == "-F"));
+
+    let stock = Dmx {
+        dmenu: fake_dmenu("stock", "usage: dmenu [-bfiv] [-l lines]"),
+        ..patched.clone()
+    };
+    let cmd = stock.cmd(&Backend::Dmenu, "fuzzy", 3);
+    assert!(!cmd.get_args().any(|a| a == "-F"));
+    assert!(Backend::Dmenu.check(&stock).is_ok());
+
+    let strict = Dmx {
+        fuzzy_required: true,
+        ..stock.clone()
+    };
+    assert!(Backend::Dmenu.check(&strict).is_err());
+
+    let z = Dmx {
+        dmenu: fake_dmenu("fuzzy-z", "usage: dmenu [-bfivz] [-l lines]"),
+        ..patched.clone()
+    };
+    assert!(z.cmd(&Backend::Dmenu, "fuzzy", 3).get_args().any(|a| a == "-z"));
+    // Here, `-z` sets the width.
+    let width = Dmx {
+        dmenu: fake_dmenu("width-z", "usage: dmenu [-bfiv] [-l lines] [-z width]"),
+        ..patched.clone()
+    };
+    assert!(!width.cmd(&Backend::Dmenu, "fuzzy", 3).get_args().any(|a| a == "-z"));
+
+    for dmx in [&patched, &stock, &z, &width] {
+        std::fs::remove_file(&dmx.dmenu).unwrap();
+    }
+}
+
+/*
+The names of any spool files this process has left lying around.
+*/
+fn leftover_spools() -> Vec<std::ffi::OsString> {
+    let prefix = format!("dmx-spool-{}-", std::process::id());
+    std::fs::read_dir(std::env::temp_dir())
+        .unwrap()
+        .filter_map(|e| e.ok())
+        .map(|e| e.file_name())
+        .filter(|n| n.to_string_lossy().starts_with(&prefix))
+        .collect()
+}
+
+#[test]
+fn spooled_input() {
+    let dmx = Dmx {
+        command: Some(vec!["sed".to_owned(), "-n".to_owned(), "3p".to_owned()]),
+        spool_threshold: Some(3),
+        ..Default::default()
+    };
+    assert_eq!(dmx.select("spool", STR_CHOICES).unwrap(), Some(2));
+    assert!(leftover_spools().is_empty());
+
+    // The file should be gone even if the menu can't be run.
+    let broken = Dmx {
+        command: Some(vec!["/nonexistent/dmx-menu".to_owned()]),
+        ..dmx
+    };
+    assert!(broken.select("spool", STR_CHOICES).is_err());
+    assert!(leftover_spools().is_empty());
+}
+
+#[test]
+fn desktop_index() {
+    let root = std::env::temp_dir().join(format!("dmx-desktop-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    let user = root.join("user");
+    let system = root.join("system");
+    std::fs::create_dir_all(user.join("sub")).unwrap();
+    std::fs::create_dir_all(&system).unwrap();
+    let write = |path: std::path::PathBuf, name: &str, extra: &str| {
+        let text = format!(
+            "[Desktop Entry]\nType=Application\nName={}\nExec=run-{}\n{}",
+            name, name, extra
+        );
+        std::fs::write(path, text).unwrap();
+    };
+    write(
+        system.join("files.desktop"),
+        "Files",
+        "GenericName=File Manager\nIcon=system-file-manager\n",
+    );
+    write(system.join("editor.desktop"), "Editor", "MimeType=text/plain;\n");
+    write(user.join("editor.desktop"), "My Editor", "");
+    write(user.join("sub").join("gone.desktop"), "Gone", "Hidden=true\n");
+
+    let dirs = vec![user.clone(), system.clone()];
+    let cache = root.join("cache");
+    let (entries, written) = desktop::load_dirs(&dirs, "C", &cache, false);
+    written.unwrap();
+    let mut names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+    names.sort();
+    assert_eq!(names, vec!["Files", "My Editor"]);
+    let files = entries.iter().find(|e| e.name == "Files").unwrap();
+    assert_eq!(files.icon(), Some("system-file-manager".to_owned()));
+
+    // Changes to an entry aren't noticed until its directory changes...
+    write(system.join("files.desktop"), "Dateien", "");
+    let (cached, _) = desktop::load_dirs(&dirs, "C", &cache, false);
+    assert_eq!(cached, entries);
+    // ...or the index is rebuilt by force.
+    let (rebuilt, _) = desktop::load_dirs(&dirs, "C", &cache, true);
+    assert!(rebuilt.iter().any(|e| e.name == "Dateien"));
+
+    std::thread::sleep(std::time::Duration::from_millis(20));
+    write(user.join("sub").join("new.desktop"), "New", "");
+    let (entries, _) = desktop::load_dirs(&dirs, "C", &cache, false);
+    let new = entries.iter().find(|e| e.name == "New").unwrap();
+    assert_eq!(new.id, "sub-new.desktop");
+
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn desktop_locale() {
+    let dir = std::env::temp_dir().join(format!("dmx-desktop-l10n-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        dir.join("files.desktop"),
+        "[Desktop Entry]\n\
+         Type=Application\n\
+         Name[de]=Dateien\n\
+         Name=Files\n\
+         Name[de_AT]=Dateien (AT)\n\
+         GenericName=File Manager\n\
+         GenericName[de]=Dateiverwaltung\n\
+         Exec=nautilus\n",
+    )
+    .unwrap();
+    let dirs = vec![dir.clone()];
+
+    let name_in = |locale: &str| {
+        let e = desktop::scan_dirs(&dirs, locale).remove(0);
+        (e.name, e.generic_name.unwrap())
+    };
+    assert_eq!(name_in("C"), ("Files".to_owned(), "File Manager".to_owned()));
+    assert_eq!(name_in("fr_FR.UTF-8"), ("Files".to_owned(), "File Manager".to_owned()));
+    assert_eq!(name_in("de_DE.UTF-8"), ("Dateien".to_owned(), "Dateiverwaltung".to_owned()));
+    assert_eq!(name_in("de_AT.UTF-8@euro").0, "Dateien (AT)");
+
+    // The index is only good for the locale it was built in.
+    let cache = dir.join("cache");
+    let (en, _) = desktop::load_dirs(&dirs, "en_US.UTF-8", &cache, false);
+    let (de, _) = desktop::load_dirs(&dirs, "de_DE.UTF-8", &cache, false);
+    assert_eq!(en[0].name, "Files");
+    assert_eq!(de[0].name, "Dateien");
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn open_target() {
+    let dmx = Dmx::default();
+    let missing: &[&str] = &["/nonexistent/dmx-opener"];
+    let fails: &[&str] = &["sh", "-c", "echo 'no handler' >&2; exit 3", "opener"];
+    let works: &[&str] = &["true"];
+
+    assert!(dmx.open_with(&[works], "/nonexistent/file").is_err());
+    dmx.open_with(&[missing, works], "https://example.com").unwrap();
+    let e = dmx.open_with(&[fails, works], "mailto:x@example.com").unwrap_err();
+    assert!(e.to_string().contains("no handler"), "{}", e);
+    assert!(dmx.open_with(&[missing], "https://example.com").is_err());
+    // A target that was opened was opened, even if it can't be audited.
+    let unaudited = Dmx {
+        audit_log: Some(std::env::temp_dir()),
+        ..Default::default()
+    };
+    unaudited.open_with(&[works], "https://example.com").unwrap();
+
+    let dmx = Dmx {
+        command: Some(vec!["sed".to_owned(), "-n".to_owned(), "2p".to_owned()]),
+        ..Default::default()
+    };
+    let targets = ["/nonexistent/file", "https://example.com"];
+    assert_eq!(dmx.select_and_open_with(&[works], "open:", &targets).unwrap(), Some(1));
+    assert!(dmx.select_and_open_with(&[fails], "open:", &targets).is_err());
+}
+
+#[test]
+fn mimeapps_defaults() {
+    let dir = std::env::temp_dir().join(format!("dmx-mime-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let list = dir.join("mimeapps.list");
+
+    mime::write_default(&list, "text/plain", "gedit.desktop").unwrap();
+    assert_eq!(mime::read_defaults(&list, "text/plain"), vec!["gedit.desktop"]);
+
+    std::fs::write(
+        &list,
+        "[Added Associations]\ntext/plain=vim.desktop;\n\n\
+         [Default Applications]\ntext/plain=gedit.desktop;\nimage/png=eog.desktop;\n\n\
+         [Removed Associations]\n",
+    )
+    .unwrap();
+    mime::write_default(&list, "text/plain", "mousepad.desktop").unwrap();
+    mime::write_default(&list, "text/html", "firefox.desktop").unwrap();
+    assert_eq!(mime::read_defaults(&list, "text/plain"), vec!["mousepad.desktop"]);
+    assert_eq!(mime::read_defaults(&list, "text/html"), vec!["firefox.desktop"]);
+    assert_eq!(mime::read_defaults(&list, "image/png"), vec!["eog.desktop"]);
+    let text = std::fs::read_to_string(&list).unwrap();
+    assert!(text.contains("[Added Associations]\ntext/plain=vim.desktop;"));
+    assert!(text.contains("text/html=firefox.desktop;\n\n[Removed Associations]"));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn mimeapps_files_include_system_config_dirs() {
+    let env = |dirs: Option<&'static str>| {
+        move |var: &str| match var {
+            "HOME" => Some("/home/u".into()),
+            "XDG_CONFIG_DIRS" => dirs.map(Into::into),
+            "XDG_DATA_DIRS" => Some("/usr/share".into()),
+            _ => None,
+        }
+    };
+    let files: Vec<PathBuf> = [
+        "/home/u/.config",
+        "/etc/xdg/xfce",
+        "/etc/xdg",
+        "/home/u/.local/share/applications",
+        "/usr/share/applications",
+    ]
+    .iter()
+    .map(|d| Path::new(d).join("mimeapps.list"))
+    .collect();
+    assert_eq!(mime::mimeapps_files_in(env(Some("/etc/xdg/xfce:relative:/etc/xdg"))), files);
+
+    let default = mime::mimeapps_files_in(env(None));
+    assert_eq!(default[1], Path::new("/etc/xdg/mimeapps.list"));
+    assert_eq!(default.len(), 4);
+}
+
+#[cfg(feature = "bookmarks")]
+#[test]
+fn browser_bookmarks() {
+    let dir = std::env::temp_dir().join(format!("dmx-bookmarks-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let json = dir.join("Bookmarks");
+    std::fs::write(
+        &json,
+        r#"{"roots": {"bookmark_bar": {"type": "folder", "name": "Bookmarks bar", "children": [
+            {"type": "url", "name": "Rust", "url": "https://www.rust-lang.org/"},
+            {"type": "folder", "name": "News", "children": [
+                {"type": "url", "name": "LWN", "url": "https://lwn.net/"}
+            ]}
+        ]}}}"#,
+    )
+    .unwrap();
+    let marks = bookmarks::chromium(&json).unwrap();
+    assert_eq!(marks.len(), 2);
+    assert_eq!(marks[1].folders, vec!["Bookmarks bar", "News"]);
+    assert_eq!(
+        String::from_utf8(marks[1].line(24)).unwrap(),
+        "Bookmarks bar/News/LWN    https://lwn.net/\n"
+    );
+
+    let rows = "1\x1f0\x1f2\x1f\x1f\x1e\
+                2\x1f1\x1f2\x1ftoolbar\x1f\x1e\
+                3\x1f2\x1f1\x1fLWN\x1fhttps://lwn.net/\x1e\
+                4\x1f2\x1f1\x1fRecent\x1fplace:sort=12\x1e";
+    let marks = bookmarks::parse_firefox(rows);
+    assert_eq!(marks.len(), 1);
+    assert_eq!(marks[0].folders, vec!["toolbar"]);
+    assert_eq!(marks[0].url, "https://lwn.net/");
+
+    // The copy of `places.sqlite` goes in a private directory, never one
+    // that's already there.
+    use std::os::unix::fs::PermissionsExt;
+    let first = bookmarks::private_dir().unwrap();
+    let name = first.file_name().unwrap().to_string_lossy().into_owned();
+    let (prefix, n) = name.rsplit_once('-').unwrap();
+    let planted = first.with_file_name(format!("{}-{}", prefix, n.parse::<usize>().unwrap() + 1));
+    std::fs::create_dir(&planted).unwrap();
+    let second = bookmarks::private_dir().unwrap();
+    assert!(second != first && second != planted);
+    let mode = std::fs::metadata(&second).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o700);
+    for dir in [first, planted, second] {
+        std::fs::remove_dir(dir).unwrap();
+    }
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(feature = "snippets")]
+#[test]
+fn snippet_files() {
+    let dir = std::env::temp_dir().join(format!("dmx-snippets-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let toml = dir.join("snippets.toml");
+    std::fs::write(&toml, "sig = \"Cheers,\\nDan\"\naddr = \"123 Fake Street\"\n").unwrap();
+    let json = dir.join("snippets.json");
+    std::fs::write(&json, r#"{"sig": "Cheers,\nDan", "addr": "123 Fake Street"}"#).unwrap();
+
+    let snips = snippets::load(&toml).unwrap();
+    assert_eq!(snips, snippets::load(&json).unwrap());
+    assert_eq!(snips[0].name, "addr");
+    assert_eq!(String::from_utf8(snips[1].line(4)).unwrap(), "sig   Cheers,…\n");
+
+    let out = dir.join("typed");
+    let script = format!("cat > {}", out.display());
+    let missing: &[&str] = &["/nonexistent/dmx-wtype"];
+    let fake: &[&str] = &["sh", "-c", &script];
+    clipboard::pipe_to(&[missing, fake], &snips[1].text).unwrap();
+    assert_eq!(std::fs::read_to_string(&out).unwrap(), "Cheers,\nDan");
+    assert!(clipboard::pipe_to(&[missing], "x").is_err());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+/*
+A picker that always chooses the line containing the given text.
+*/
+struct Chooser(&'static str);
+
+impl MenuBackend for Chooser {
+    type Session = Vec<Vec<u8>>;
+
+    fn spawn(&self, _: &str, _: usize) -> Result<Vec<Vec<u8>>, DmxError> {
+        Ok(Vec::new())
+    }
+
+    fn feed(&self, session: &mut Vec<Vec<u8>>, input: &[Vec<u8>]) -> Result<(), DmxError> {
+        session.extend_from_slice(input);
+        Ok(())
+    }
+
+    fn read_selection(&self, session: Vec<Vec<u8>>) -> Result<Option<Vec<u8>>, DmxError> {
+        Ok(session
+            .into_iter()
+            .find(|l| String::from_utf8_lossy(l).contains(self.0))
+            .map(|mut l| {
+                l.pop();
+                l
+            }))
+    }
+}
+
+#[test]
+fn custom_backend() {
+    let menu = Menu::new("custom").items(TUPLE_CHOICES).preselect(3);
+    assert_eq!(menu.show_with(&Chooser("Milkshake")).unwrap(), Some(2));
+    assert_eq!(menu.show_with(&Chooser("Goblins")).unwrap(), Some(3));
+    assert_eq!(menu.show_with(&Chooser("Unicorns")).unwrap(), None);
+}
+
+#[test]
+fn dmx_backend_steps() {
+    let dmx = Dmx {
+        command: Some(vec!["sed".to_owned(), "-n".to_owned(), "2p".to_owned()]),
+        ..Default::default()
+    };
+    let mut session = MenuBackend::spawn(&dmx, "steps", 3).unwrap();
+    dmx.feed(&mut session, &[b"one\n".to_vec(), b"two\n".to_vec()])
+        .unwrap();
+    assert_eq!(dmx.read_selection(session).unwrap(), Some(b"two\n".to_vec()));
+}
+
+#[test]
+fn rofi_args() {
+    let dmx = Dmx {
+        fuzzy: true,
+        ..Default::default()
+    };
+    let args = |dmx: &Dmx| -> Vec<String> {
+        dmx.cmd(&Backend::Rofi, "run:", 5)
+            .get_args()
+            .map(|a| a.to_string_lossy().into_owned())
+            .collect()
+    };
+
+    let rofi = args(&dmx);
+    assert_eq!(&rofi[..5], &["-dmenu", "-p", "run:", "-l", "5"]);
+    assert_eq!(&rofi[5..7], &["-font", "LiberationMono 12"]);
+    assert!(rofi[8].contains("element selected.normal { background-color: #888888; text-color: #aaffff; }"));
+    assert_eq!(&rofi[9..], &["-matching", "fuzzy"]);
+
+    let pango = Dmx {
+        font: "Terminus:size=9:antialias=false".into(),
+        ..Default::default()
+    };
+    assert_eq!(args(&pango)[6], "Terminus 9");
+    let pango = Dmx {
+        font: "DejaVu Sans Mono 10".into(),
+        ..Default::default()
+    };
+    assert_eq!(args(&pango)[6], "DejaVu Sans Mono 10");
+    assert_eq!("rofi".parse::<Backend>().unwrap(), Backend::Rofi);
+
+    // Custom key bindings choose something, too.
+    use backend::ExitMeaning;
+    assert_eq!(Backend::Rofi.exit_meaning(Some(1)), ExitMeaning::Cancelled);
+    assert_eq!(Backend::Rofi.exit_meaning(Some(10)), ExitMeaning::Key(1));
+    assert_eq!(Backend::Rofi.exit_meaning(Some(28)), ExitMeaning::Key(19));
+    assert_eq!(Backend::Rofi.exit_meaning(Some(29)), ExitMeaning::Failed);
+    assert_eq!(Backend::Dmenu.exit_meaning(Some(10)), ExitMeaning::Failed);
+}
+
+#[test]
+fn wofi_args() {
+    let dmx = Dmx {
+        fuzzy: true,
+        ..Default::default()
+    };
+    let cmd = dmx.cmd(&Backend::Wofi, "run:", 5);
+    assert_eq!(cmd.get_program(), "wofi");
+    let args: Vec<_> = cmd.get_args().collect();
+    assert_eq!(
+        args,
+        ["--dmenu", "--prompt", "run:", "--lines", "5", "--matching", "fuzzy"]
+    );
+    assert_eq!("wofi".parse::<Backend>().unwrap(), Backend::Wofi);
+}
+
+#[test]
+fn dmenu_wl() {
+    let dir = std::env::temp_dir().join(format!("dmx-wl-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("dmenu-wl");
+    let script = fake_dmenu("wl-usage", "usage: dmenu [-biv] [-l lines] [-p prompt] [-fn font]");
+    std::fs::rename(script, &path).unwrap();
+    let dmx = Dmx {
+        dmenu: path.clone(),
+        ..Default::default()
+    };
+
+    let cmd = dmx.cmd(&Backend::Dmenu, "run:", 5);
+    assert_eq!(cmd.get_program(), path.as_os_str());
+    let args: Vec<_> = cmd.get_args().collect();
+    assert_eq!(args, ["-l", "5", "-p", "run:", "-fn", "LiberationMono 12"]);
+    assert_eq!("dmenu-wl".parse::<Backend>().unwrap(), Backend::DmenuWl);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn fzf_args() {
+    let dmx = Dmx::default();
+    let cmd = dmx.cmd(&Backend::Fzf, "run:", 5);
+    let args: Vec<_> = cmd.get_args().collect();
+    assert_eq!(
+        args,
+        ["--prompt", "run: ", "--layout", "reverse", "--height", "7", "--exact"]
+    );
+    assert_eq!(Backend::Fzf.exit_meaning(Some(130)), backend::ExitMeaning::Cancelled);
+    assert_eq!("fzf".parse::<Backend>().unwrap(), Backend::Fzf);
+}
+
+#[cfg(feature = "picker")]
+#[test]
+fn picker_filter() {
+    let items: Vec<String> = STR_CHOICES.iter().map(|s| s.to_string()).collect();
+    assert_eq!(picker::filter(&items, "", false), vec![0, 1, 2, 3]);
+    assert_eq!(picker::filter(&items, "DOWN", false), vec![1, 2]);
+    assert_eq!(picker::filter(&items, "frgdwn", false), Vec::<usize>::new());
+    assert_eq!(picker::filter(&items, "frgdwn", true), vec![1]);
+
+    // Five rows fit: moving past them scrolls, and moving back up above
+    // the top scrolls back.
+    assert_eq!(picker::scroll(0, 4, 5), 0);
+    assert_eq!(picker::scroll(0, 7, 5), 3);
+    assert_eq!(picker::scroll(3, 2, 5), 2);
+    // The terminal got shorter.
+    assert_eq!(picker::scroll(0, 4, 2), 3);
+}
+
+#[test]
+fn list_dialogs() {
+    let dmx = Dmx::default();
+    let cmd = dmx.cmd(&Backend::Zenity, "run:", 5);
+    assert_eq!(cmd.get_program(), "zenity");
+    assert!(cmd.get_args().any(|a| a == "--column=run:"));
+    assert_eq!(Backend::Zenity.exit_meaning(Some(5)), backend::ExitMeaning::Cancelled);
+
+    assert_eq!(Backend::Yad.clean_choice(b"toads|\n".to_vec(), &[]), Some(b"toads".to_vec()));
+    assert_eq!(Backend::Yad.exit_meaning(Some(252)), backend::ExitMeaning::Cancelled);
+    assert_eq!("yad".parse::<Backend>().unwrap(), Backend::Yad);
+}
+
+#[test]
+fn kdialog_items() {
+    let dmx = Dmx::default();
+    let cmd = dmx.cmd(&Backend::Kdialog, "pick:", 4);
+    assert_eq!(cmd.get_program(), "kdialog");
+    assert!(cmd.get_args().any(|a| a == "--menu"));
+    assert!(Backend::Kdialog.items_as_args());
+    assert_eq!("kdialog".parse::<Backend>().unwrap(), Backend::Kdialog);
+
+    // kdialog prints the tag (index) of the chosen item.
+    let out = vec![b"zero\n".to_vec(), b"one\n".to_vec()];
+    assert_eq!(Backend::Kdialog.clean_choice(b"1\n".to_vec(), &out), Some(b"one\n".to_vec()));
+    assert_eq!(Backend::Kdialog.clean_choice(b"7\n".to_vec(), &out), None);
+}
+
+#[test]
+fn choose_args() {
+    let dmx = Dmx {
+        font: "Menlo:size=13".into(),
+        normal_bg: Color::Rgb(0x10, 0x10, 0x10),
+        ..Default::default()
+    };
+    let cmd = dmx.cmd(&Backend::Choose, "go:", 6);
+    assert_eq!(cmd.get_program(), "choose");
+    let args: Vec<_> = cmd.get_args().collect();
+    assert!(args.windows(2).any(|w| w[0] == "-f" && w[1] == "Menlo"));
+    assert!(args.windows(2).any(|w| w[0] == "-s" && w[1] == "13"));
+    assert!(args.windows(2).any(|w| w[0] == "-b" && w[1] == "101010"));
+    assert!(args.windows(2).any(|w| w[0] == "-c" && w[1] == "888888"));
+    assert_eq!(Backend::Choose.check(&dmx).is_ok(), cfg!(target_os = "macos"));
+}
+
+#[test]
+fn numbered_list() {
+    use crate::numbered;
+
+    let items = vec![b"red\n".to_vec(), b"green\n".to_vec(), b"blue\n".to_vec()];
+    let mut out: Vec<u8> = Vec::new();
+    let n = numbered::ask("color:", None, &items, &b"7\nblue\n2\n"[..], &mut out).unwrap();
+    assert_eq!(n, Some(1));
+    let out = String::from_utf8(out).unwrap();
+    assert!(out.starts_with("1) red\n2) green\n3) blue\ncolor: "));
+    assert_eq!(out.matches("Enter a number from 1 to 3").count(), 2);
+
+    assert_eq!(numbered::ask("color:", None, &items, &b"\n"[..], Vec::new()).unwrap(), None);
+    assert_eq!(numbered::ask("color:", None, &items, &b""[..], Vec::new()).unwrap(), None);
+
+    let mut out: Vec<u8> = Vec::new();
+    let n = numbered::ask("color:", Some("Paint the shed"), &items, &b"3\n"[..], &mut out);
+    assert_eq!(n.unwrap(), Some(2));
+    assert!(out.starts_with(b"Paint the shed\n1) red\n"));
+    assert_eq!("numbered".parse::<Backend>().unwrap(), Backend::Numbered);
+}
+
+#[test]
+fn item_icons() {
+    use crate::backend::MenuHints;
+    use std::cell::RefCell;
+
+    struct Iconic(&'static str, Option<&'static str>);
+    impl Item for Iconic {
+        fn key_len(&self) -> usize {
+            0
+        }
+        fn line(&self, _: usize) -> Vec<u8> {
+            self.0.as_bytes().to_vec()
+        }
+        fn icon(&self) -> Option<String> {
+            self.1.map(|i| i.to_owned())
+        }
+    }
+
+    // Picks the last line, and remembers the hints it was given.
+    struct Spy(RefCell<MenuHints>);
+    impl MenuBackend for Spy {
+        type Session = Vec<Vec<u8>>;
+        fn spawn(&self, _: &str, _: usize) -> Result<Self::Session, DmxError> {
+            Ok(Vec::new())
+        }
+        fn feed(&self, session: &mut Self::Session, input: &[Vec<u8>]) -> Result<(), DmxError> {
+            session.extend_from_slice(input);
+            Ok(())
+        }
+        fn read_selection(&self, session: Self::Session) -> Result<Option<Vec<u8>>, DmxError> {
+            Ok(session.last().cloned())
+        }
+        fn choose_hinted(
+            &self,
+            prompt: &str,
+            lines: usize,
+            input: &[Vec<u8>],
+            hints: &MenuHints,
+        ) -> Result<Option<Vec<u8>>, DmxError> {
+            *self.0.borrow_mut() = hints.clone();
+            self.choose(prompt, lines, input)
+        }
+    }
+
+    let items = &[Iconic("vim", None), Iconic("firefox", Some("firefox"))];
+    let spy = Spy(RefCell::new(MenuHints::default()));
+    assert_eq!(Menu::new("run:").items(items).show_with(&spy).unwrap(), Some(1));
+    let hints = spy.0.into_inner();
+    assert_eq!(hints.icons, vec![None, Some("firefox".to_owned())]);
+
+    let output = vec![b"vim\n".to_vec(), b"firefox\n".to_vec()];
+    assert_eq!(
+        Backend::Rofi.annotate(&output, &hints).to_vec(),
+        vec![b"vim\n".to_vec(), b"firefox\0icon\x1ffirefox\n".to_vec()]
+    );
+    assert_eq!(Backend::Dmenu.annotate(&output, &hints).to_vec(), output);
+    let mut cmd = Dmx::default().cmd(&Backend::Rofi, "run:", 2);
+    Backend::Rofi.apply_hints(&mut cmd, &hints);
+    assert!(cmd.get_args().any(|a| a == "-show-icons"));
+}
+
+#[test]
+fn markup_rows() {
+    use crate::backend::MenuHints;
+
+    assert_eq!(escape_markup("<b>R&D</b>"), "&lt;b&gt;R&amp;D&lt;/b&gt;");
+
+    let output = vec![b"ff  Firefox\n".to_vec(), b"R&D\n".to_vec()];
+    let hints = MenuHints {
+        markup: vec![Some("<b>ff</b>  Firefox".to_owned()), None],
+        ..Default::default()
+    };
+    let fed = Backend::Rofi.annotate(&output, &hints).to_vec();
+    assert_eq!(fed, vec![b"<b>ff</b>  Firefox\n".to_vec(), b"R&amp;D\n".to_vec()]);
+    assert_eq!(Backend::Fuzzel.annotate(&output, &hints).to_vec(), output);
+
+    // rofi prints the markup of the row chosen.
+    let chosen = Backend::Rofi.unannotate(b"<b>ff</b>  Firefox\n".to_vec(), &output, &fed);
+    assert_eq!(chosen, output[0]);
+    let mut cmd = Dmx::default().cmd(&Backend::Rofi, "run:", 2);
+    Backend::Rofi.apply_hints(&mut cmd, &hints);
+    assert!(cmd.get_args().any(|a| a == "-markup-rows"));
+
+    // A spooled menu is escaped too. (This rofi writes down what it was
+    // fed, and chooses the first line.)
+    use std::os::unix::fs::PermissionsExt;
+    let dir = std::env::temp_dir().join(format!("dmx-markup-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let fed = dir.join("fed");
+    let script = format!("#!/bin/sh\ncat > '{0}'\nhead -n 1 '{0}'\n", fed.display());
+    std::fs::write(dir.join("rofi"), script).unwrap();
+    std::fs::set_permissions(dir.join("rofi"), std::fs::Permissions::from_mode(0o755)).unwrap();
+    let dmx = Dmx {
+        backends: vec![Backend::Rofi],
+        spool_threshold: Some(1),
+        env: [
+            ("PATH".to_owned(), format!("{}:/usr/bin:/bin", dir.display())),
+            ("DISPLAY".to_owned(), ":0".to_owned()),
+        ]
+        .into(),
+        ..Default::default()
+    };
+    let chosen = dmx.choose_hinted("run:", 2, &output, &hints).unwrap();
+    assert_eq!(chosen, Some(output[0].clone()));
+    assert_eq!(std::fs::read(&fed).unwrap(), b"<b>ff</b>  Firefox\nR&amp;D\n");
+    assert!(leftover_spools().is_empty());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn message_line() {
+    use crate::backend::MenuHints;
+
+    let hints = MenuHints {
+        message: Some("2 devices found".to_owned()),
+        ..Default::default()
+    };
+    let output = vec![b"sda\n".to_vec(), b"sdb\n".to_vec()];
+
+    let mut cmd = Dmx::default().cmd(&Backend::Rofi, "mount:", 2);
+    Backend::Rofi.apply_hints(&mut cmd, &hints);
+    assert!(cmd.get_args().any(|a| a == "-mesg"));
+    assert_eq!(Backend::Rofi.annotate(&output, &hints).to_vec(), output);
+
+    let fed = Backend::Dmenu.annotate(&output, &hints).to_vec();
+    assert_eq!(fed[0], b"2 devices found\n");
+    assert_eq!(Backend::Dmenu.unannotate(b"sdb".to_vec(), &output, &fed), output[1]);
+
+    // With a plain dmenu, the message is the first line, and choosing it
+    // is the same as cancelling.
+    let dmx = Dmx {
+        command: Some(vec!["sed".to_owned(), "-n".to_owned(), "1p".to_owned()]),
+        ..Default::default()
+    };
+    let menu = Menu::new("mount:").items(STR_CHOICES).message("2 devices found");
+    assert_eq!(menu.show(&dmx).unwrap(), None);
+    let menu = Menu::new("mount:").items(STR_CHOICES).message("hi");
+    let dmx = Dmx {
+        command: Some(vec!["sed".to_owned(), "-n".to_owned(), "3p".to_owned()]),
+        ..Default::default()
+    };
+    assert_eq!(menu.show(&dmx).unwrap(), Some(1));
+}
+
+#[test]
+fn multi_select() {
+    // Like choosing the second and fourth lines (and the second again)
+    // with Ctrl-Enter in dmenu.
+    let dmx = Dmx {
+        command: Some(vec!["sed".to_owned(), "-n".to_owned(), "2p;4p;2p".to_owned()]),
+        ..Default::default()
+    };
+    assert_eq!(dmx.multi_select("which:", STR_CHOICES).unwrap(), vec![1, 3]);
+    assert_eq!(dmx.select("which:", STR_CHOICES).unwrap(), None);
+
+    let cancel = Dmx {
+        command: Some(vec![
+            "sh".to_owned(),
+            "-c".to_owned(),
+            "cat >/dev/null; exit 1".to_owned(),
+        ]),
+        ..Default::default()
+    };
+    assert!(cancel.multi_select("which:", STR_CHOICES).unwrap().is_empty());
+
+    let hints = MenuHints {
+        multi: true,
+        ..Default::default()
+    };
+    let mut cmd = Dmx::default().cmd(&Backend::Rofi, "which:", 4);
+    Backend::Rofi.apply_hints(&mut cmd, &hints);
+    assert!(cmd.get_args().any(|a| a == "-multi-select"));
+}
+
+#[test]
+fn select_loop() {
+    // Chooses the second line, then the second of what's left, and so on,
+    // and cancels when there's no second line.
+    let dmx = Dmx {
+        command: Some(vec![
+            "sh".to_owned(),
+            "-c".to_owned(),
+            "sed -n 2p | grep . || exit 1".to_owned(),
+        ]),
+        ..Default::default()
+    };
+    assert_eq!(dmx.select_loop("which:", STR_CHOICES, true).unwrap(), vec![1, 2, 3]);
+
+    let dir = std::env::temp_dir().join(format!("dmx-loop-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let dmx = Dmx {
+        command: Some(vec![
+            "sh".to_owned(),
+            "-c".to_owned(),
+            format!(
+                "cd '{}' && cat >lines && n=$(cat count 2>/dev/null || echo 0) && \
+                 echo $((n + 1)) >count && [ $n -lt 2 ] && sed -n 2p lines || exit 1",
+                dir.display()
+            ),
+        ]),
+        ..Default::default()
+    };
+    assert_eq!(dmx.select_loop("which:", STR_CHOICES, false).unwrap(), vec![1, 1]);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn std_items() {
+    use std::borrow::Cow;
+    use std::ffi::OsString;
+
+    assert_eq!("frog".to_owned().line(0), b"frog");
+    assert_eq!(Cow::Borrowed("frog").line(0), b"frog");
+    assert_eq!(Path::new("/tmp/frog").line(0), b"/tmp/frog");
+    assert_eq!(PathBuf::from("/tmp/frog").id(), "/tmp/frog");
+    assert_eq!(OsString::from("frog").line(0), b"frog");
+
+    use std::os::unix::ffi::OsStrExt;
+    let odd = std::ffi::OsStr::from_bytes(b"fr\xffg");
+    assert_eq!(odd.line(0), "fr\u{fffd}g".as_bytes());
+
+    let dmx = Dmx {
+        command: Some(vec!["sed".to_owned(), "-n".to_owned(), "2p".to_owned()]),
+        ..Default::default()
+    };
+    let paths = vec![PathBuf::from("/bin"), PathBuf::from("/usr/bin")];
+    assert_eq!(dmx.select_owned("dir:", paths).unwrap(), Some(PathBuf::from("/usr/bin")));
+}
+
+#[cfg(feature = "strum")]
+#[test]
+fn select_variant() {
+    #[derive(Debug, PartialEq, strum::Display, strum::EnumIter)]
+    enum Governor {
+        Performance,
+        #[strum(to_string = "power save")]
+        Powersave,
+        Schedutil,
+    }
+
+    let dmx = Dmx {
+        command: Some(vec!["grep".to_owned(), "^power".to_owned()]),
+        ..Default::default()
+    };
+    assert_eq!(dmx.select_variant("governor:").unwrap(), Some(Governor::Powersave));
+}
+
+#[test]
+fn keyed() {
+    let sizes = vec![
+        Keyed::new("s", "Small", 8),
+        Keyed::new("xl", "Extra Large", 20),
+    ];
+    assert_eq!(sizes[0].line(2), b"s   Small\n");
+    assert_eq!(sizes[1].key_len(), 2);
+
+    let dmx = Dmx {
+        command: Some(vec!["sed".to_owned(), "-n".to_owned(), "2p".to_owned()]),
+        ..Default::default()
+    };
+    assert_eq!(dmx.select_keyed("size:", sizes).unwrap(), Some(20));
+}
+
+#[test]
+fn select_ext() {
+    let dmx = Dmx {
+        command: Some(vec!["sed".to_owned(), "-n".to_owned(), "2p".to_owned()]),
+        ..Default::default()
+    };
+    let picked = STR_CHOICES
+        .iter()
+        .copied()
+        .filter(|t| t.contains("Down"))
+        .pick("which:", &dmx)
+        .unwrap();
+    assert_eq!(picked, Some("Down on the Upside"));
+}
+
+#[test]
+fn dispatch() {
+    let dmx = Dmx {
+        command: Some(vec!["sed".to_owned(), "-n".to_owned(), "2p".to_owned()]),
+        ..Default::default()
+    };
+    let called = std::cell::Cell::new(0);
+    let got = dmx
+        .dispatch(
+            "do:",
+            &[
+                ("one", &|| called.set(1)),
+                ("two", &|| called.set(2)),
+                ("three", &|| called.set(3)),
+            ],
+        )
+        .unwrap();
+    assert_eq!(got, Some(()));
+    assert_eq!(called.get(), 2);
+
+    let got = dmx
+        .dispatch("do:", &[("one", &|| "first"), ("two", &|| "second")])
+        .unwrap();
+    assert_eq!(got, Some("second"));
+}
+
+#[test]
+fn selection() {
+    let dmx = Dmx {
+        command: Some(vec!["sed".to_owned(), "-n".to_owned(), "2p".to_owned()]),
+        ..Default::default()
+    };
+    assert_eq!(dmx.selection("which:", STR_CHOICES).unwrap(), Selection::Picked(1));
+
+    let typed = Dmx {
+        command: Some(vec![
+            "sh".to_owned(),
+            "-c".to_owned(),
+            "cat >/dev/null; echo mauve".to_owned(),
+        ]),
+        ..Default::default()
+    };
+    assert_eq!(
+        typed.selection("which:", STR_CHOICES).unwrap(),
+        Selection::Custom("mauve".to_owned())
+    );
+    assert_eq!(typed.select("which:", STR_CHOICES).unwrap(), None);
+
+    let cancel = Dmx {
+        command: Some(vec![
+            "sh".to_owned(),
+            "-c".to_owned(),
+            "cat >/dev/null; exit 1".to_owned(),
+        ]),
+        ..Default::default()
+    };
+    assert_eq!(cancel.selection("which:", STR_CHOICES).unwrap(), Selection::Cancelled);
+}
+
+#[test]
+fn select_or_input() {
+    let typed = Dmx {
+        command: Some(vec![
+            "sh".to_owned(),
+            "-c".to_owned(),
+            "cat >/dev/null; echo example.org".to_owned(),
+        ]),
+        ..Default::default()
+    };
+    assert_eq!(
+        typed.select_or_input("host:", ["localhost", "example.com"]).unwrap(),
+        Selection::Custom("example.org".to_owned())
+    );
+
+    assert_eq!(typed.input("host:").unwrap().as_deref(), Some("example.org"));
+    let cancel = Dmx {
+        command: Some(vec![
+            "sh".to_owned(),
+            "-c".to_owned(),
+            "cat >/dev/null; exit 1".to_owned(),
+        ]),
+        ..Default::default()
+    };
+    assert_eq!(cancel.input("host:").unwrap(), None);
+
+    // fzf only prints what was typed when asked to, before the line
+    // chosen (if there is one).
+    let hints = MenuHints {
+        custom: true,
+        ..Default::default()
+    };
+    let mut cmd = Dmx::default().cmd(&Backend::Fzf, "host:", 2);
+    Backend::Fzf.apply_hints(&mut cmd, &hints);
+    assert!(cmd.get_args().any(|a| a == "--print-query"));
+    assert_eq!(
+        Backend::Fzf.strip_query(b"loc\nlocalhost\n".to_vec(), &hints),
+        b"localhost\n"
+    );
+    assert_eq!(
+        Backend::Fzf.strip_query(b"example.org\n".to_vec(), &hints),
+        b"example.org\n"
+    );
+    assert_eq!(
+        Backend::Fzf.strip_query(b"loc\nlocalhost\n".to_vec(), &MenuHints::default()),
+        b"loc\nlocalhost\n"
+    );
+}
+
+#[test]
+fn input_number() {
+    // Answers "frog", then 500, then 42, noting the message it was shown
+    // (as the first line) each time.
+    let dir = std::env::temp_dir().join(format!("dmx-number-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let script = "n=$(cat count 2>/dev/null || echo 0); echo $((n + 1)) >count; \
+                  head -n 1 >>shown; \
+                  case $n in 0) echo frog ;; 1) echo 500 ;; *) echo 42 ;; esac";
+    let dmx = Dmx {
+        command: Some(vec![
+            "sh".to_owned(),
+            "-c".to_owned(),
+            format!("cd '{}' && {{ {} }}", dir.display(), script),
+        ]),
+        ..Default::default()
+    };
+    assert_eq!(dmx.input_number("volume:", 0..=100u32).unwrap(), Some(42));
+    assert_eq!(std::fs::read_to_string(dir.join("count")).unwrap(), "3\n");
+    assert_eq!(
+        std::fs::read_to_string(dir.join("shown")).unwrap(),
+        "\"frog\" isn't a number.\n500 is out of range.\n"
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn select_value() {
+    let dmx = Dmx {
+        command: Some(vec!["sed".to_owned(), "-n".to_owned(), "3p".to_owned()]),
+        ..Default::default()
+    };
+    assert_eq!(dmx.select_value("which:", STR_CHOICES).unwrap(), Some(&STR_CHOICES[2]));
+    let owned = vec![("a".to_owned(), "Apple".to_owned()), ("b".to_owned(), "Banana".to_owned())];
+    assert_eq!(dmx.select_cloned("which:", &owned).unwrap(), None);
+    let dmx = Dmx {
+        command: Some(vec!["sed".to_owned(), "-n".to_owned(), "2p".to_owned()]),
+        ..Default::default()
+    };
+    assert_eq!(dmx.select_cloned("which:", &owned).unwrap(), Some(owned[1].clone()));
+    assert_eq!(
+        dmx.select_owned("which:", owned).unwrap(),
+        Some(("b".to_owned(), "Banana".to_owned()))
+    );
+}
+
+#[test]
+fn select_iterators() {
+    let dmx = Dmx {
+        command: Some(vec!["sed".to_owned(), "-n".to_owned(), "2p".to_owned()]),
+        ..Default::default()
+    };
+    // The second of the titles with "Down" in them.
+    let down = STR_CHOICES.iter().filter(|t| t.contains("Down"));
+    let n = dmx.select("which:", down.clone()).unwrap().unwrap();
+    assert_eq!(down.clone().nth(n), Some(&STR_CHOICES[2]));
+
+    let mut map = std::collections::BTreeMap::new();
+    map.insert(2, "two");
+    map.insert(1, "one");
+    assert_eq!(dmx.select("which:", map.values()).unwrap(), Some(1));
+    assert_eq!(dmx.select("which:", vec!["one", "two"]).unwrap(), Some(1));
+}
+
+#[test]
+fn probed_options() {
+    use std::os::unix::fs::PermissionsExt;
+
+    // A dmenu that has no idea about colors, which is found out because
+    // its prompt flag isn't the usual one.
+    let mut dmx = Dmx {
+        dmenu: fake_dmenu("mono", "usage: dmenu [-bv] [-l lines] [-P prompt] [-fn font]"),
+        ..Default::default()
+    };
+    dmx.flag_map.prompt = "-P".to_owned();
+    let args = |dmx: &Dmx| -> Vec<String> {
+        dmx.cmd(&Backend::Dmenu, "go:", 3)
+            .get_args()
+            .map(|a| a.to_string_lossy().into_owned())
+            .collect()
+    };
+    assert_eq!(args(&dmx), ["-l", "3", "-P", "go:", "-fn", "LiberationMono-12"]);
+    std::fs::remove_file(&dmx.dmenu).unwrap();
+
+    // With nothing but the usual options, dmenu isn't asked for its usage
+    // (though it's asked for its version), and a dmenu that won't answer
+    // in time is given up on.
+    let dir = std::env::temp_dir().join(format!("dmx-unprobed-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("dmenu");
+    let script = format!("#!/bin/sh\necho \"$1\" >>{}/args\nsleep 5\n", dir.display());
+    std::fs::write(&path, script).unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let mut dmx = Dmx {
+        dmenu: path,
+        ..Default::default()
+    };
+    assert_eq!(args(&dmx).len(), 14);
+    let started = std::time::Instant::now();
+    dmx.center = true;
+    assert!(args(&dmx).contains(&"-c".to_owned()));
+    assert!(args(&dmx).contains(&"-c".to_owned()));
+    assert!(started.elapsed() < std::time::Duration::from_secs(4));
+    let probes = std::fs::read_to_string(dir.join("args")).unwrap();
+    assert_eq!(probes.lines().collect::<Vec<_>>(), ["-v", "--dm_x-probe", "--help"]);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn dmenu_versions() {
+    use std::os::unix::fs::PermissionsExt;
+
+    assert_eq!(probe::parse_version("dmenu-5.2"), Some(("dmenu", 5, 2)));
+    assert_eq!(probe::parse_version("dmenu-wl-0.1"), Some(("dmenu-wl", 0, 1)));
+    assert_eq!(probe::parse_version("dmenu"), None);
+
+    let path = std::env::temp_dir().join(format!("dmx-old-{}", std::process::id()));
+    let script = "#!/bin/sh\n\
+                  [ \"$1\" = -v ] && { echo dmenu-4.5; exit 0; }\n\
+                  cat >/dev/null\n\
+                  echo 'usage: dmenu [-bfiv] [-l lines] [-fn font] [-x]' >&2\n\
+                  exit 1\n";
+    std::fs::write(&path, script).unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let dmx = Dmx {
+        dmenu: path.clone(),
+        font: "Terminus-9".into(),
+        env: [("DISPLAY".to_owned(), ":0".to_owned())].into(),
+        ..Default::default()
+    };
+    assert_eq!(dmx.dmenu_version().as_deref(), Some("dmenu-4.5"));
+    let cmd = dmx.cmd(&Backend::Dmenu, "old:", 2);
+    assert!(cmd.get_args().any(|a| a == "-*-terminus-*-r-*-*-*-90-*-*-*-*-*-*"));
+
+    // Given anything but `-v`, this prints its usage and exits with 1,
+    // which mustn't be mistaken for cancelling.
+    let err = dmx.select("old:", STR_CHOICES).unwrap_err();
+    assert!(matches!(err, DmxError::MenuRejected { .. }), "{}", err);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn flag_map() {
+    let mut dmx = Dmx {
+        dmenu: fake_dmenu(
+            "flag-map",
+            "usage: dmenu [-bfiv] [-l lines] [-p prompt] [--fn font] [-nb color] [-nf color] [-sb color]",
+        ),
+        ..Default::default()
+    };
+    dmx.flag_map.font = "--fn".to_owned();
+    dmx.flag_map.select_fg = String::new();
+    let args: Vec<String> = dmx
+        .cmd(&Backend::Dmenu, "go:", 3)
+        .get_args()
+        .map(|a| a.to_string_lossy().into_owned())
+        .collect();
+    assert!(args.windows(2).any(|w| w[0] == "--fn" && w[1] == "LiberationMono-12"));
+    assert!(!args.iter().any(|a| a == "-fn" || a == "-sf"));
+    std::fs::remove_file(&dmx.dmenu).unwrap();
+
+    #[cfg(feature = "config")]
+    {
+        let dmx = Dmx::from_bytes(b"[flag_map]\nprompt = \"--prompt\"\n").unwrap();
+        assert_eq!(dmx.flag_map.prompt, "--prompt");
+        assert_eq!(dmx.flag_map.lines, "-l");
+    }
+}
+
+#[test]
+fn patch_options() {
+    let mut dmx = Dmx {
+        dmenu: fake_dmenu("center", "usage: dmenu [-bcfiv] [-l lines] [-bw width]"),
+        center: true,
+        border_width: Some(2),
+        width: Some(600),
+        ..Default::default()
+    };
+    let args = |dmx: &Dmx| -> Vec<String> {
+        dmx.cmd(&Backend::Dmenu, "go:", 3)
+            .get_args()
+            .map(|a| a.to_string_lossy().into_owned())
+            .collect()
+    };
+    let with = args(&dmx);
+    assert!(with.iter().any(|a| a == "-c"));
+    assert!(with.windows(2).any(|w| w[0] == "-bw" && w[1] == "2"));
+    // No xyw patch here.
+    assert!(!with.iter().any(|a| a == "-z"));
+
+    std::fs::remove_file(&dmx.dmenu).unwrap();
+    dmx.dmenu = fake_dmenu("plain", "usage: dmenu [-bfiv] [-l lines]");
+    dmx.center = false;
+    let without = args(&dmx);
+    assert!(!without.iter().any(|a| a == "-c" || a == "-bw"));
+    std::fs::remove_file(&dmx.dmenu).unwrap();
+}
+
+#[test]
+fn grid_columns() {
+    let dmx = Dmx {
+        dmenu: fake_dmenu("grid", "usage: dmenu [-bfiv] [-l lines] [-g columns]"),
+        columns: Some(4),
+        ..Default::default()
+    };
+    let args: Vec<String> = dmx
+        .cmd(&Backend::Dmenu, "emoji:", 10)
+        .get_args()
+        .map(|a| a.to_string_lossy().into_owned())
+        .collect();
+    // Ten items in four columns take three rows.
+    assert!(args.windows(2).any(|w| w[0] == "-l" && w[1] == "3"));
+    assert!(args.windows(2).any(|w| w[0] == "-g" && w[1] == "4"));
+    std::fs::remove_file(&dmx.dmenu).unwrap();
+}
+
+#[test]
+fn line_count() {
+    let lines = |dmx: &Dmx, n: usize| -> String {
+        let cmd = dmx.cmd(&Backend::Rofi, "go:", n);
+        let args: Vec<_> = cmd.get_args().collect();
+        let at = args.iter().position(|&a| a == "-l").unwrap();
+        args[at + 1].to_string_lossy().into_owned()
+    };
+    let mut dmx = Dmx::default();
+    assert_eq!(lines(&dmx, 3), "3");
+    dmx.max_lines = Some(10);
+    assert_eq!(lines(&dmx, 3), "3");
+    assert_eq!(lines(&dmx, 30), "10");
+    dmx.fixed_lines = true;
+    assert_eq!(lines(&dmx, 3), "10");
+
+    #[cfg(feature = "config")]
+    {
+        let dmx = Dmx::from_bytes(b"max_lines = 12\nfixed_lines = true\n").unwrap();
+        assert_eq!((dmx.max_lines, dmx.fixed_lines), (Some(12), true));
+    }
+}
+
+#[test]
+fn initial_query() {
+    let hints = MenuHints {
+        query: Some("fro".to_owned()),
+        ..Default::default()
+    };
+    let args = |dmx: &Dmx, backend: Backend| -> Vec<String> {
+        let mut cmd = dmx.cmd(&backend, "animal:", 3);
+        backend.apply_hints(&mut cmd, &hints);
+        cmd.get_args()
+            .map(|a| a.to_string_lossy().into_owned())
+            .collect()
+    };
+    let mut dmx = Dmx::default();
+    assert!(args(&dmx, Backend::Rofi).windows(2).any(|w| w == ["-filter", "fro"]));
+    assert!(args(&dmx, Backend::Fzf).windows(2).any(|w| w == ["--query", "fro"]));
+
+    dmx.dmenu = fake_dmenu("initialtext", "usage: dmenu [-bfiv] [-l lines] [-it text]");
+    assert!(args(&dmx, Backend::Dmenu).windows(2).any(|w| w == ["-it", "fro"]));
+    std::fs::remove_file(&dmx.dmenu).unwrap();
+    // Stock dmenu would refuse to start with -it.
+    dmx.dmenu = fake_dmenu("no-initialtext", "usage: dmenu [-bfiv] [-l lines]");
+    assert!(!args(&dmx, Backend::Dmenu).iter().any(|a| a == "-it"));
+    std::fs::remove_file(&dmx.dmenu).unwrap();
+}
+
+#[test]
+fn embed_window() {
+    let dmx = Dmx {
+        dmenu: fake_dmenu("embed", "usage: dmenu [-bfiv] [-l lines] [-w windowid]"),
+        embed_window: Some(0x1a00003),
+        ..Default::default()
+    };
+    let args: Vec<String> = dmx
+        .cmd(&Backend::Dmenu, "kiosk:", 5)
+        .get_args()
+        .map(|a| a.to_string_lossy().into_owned())
+        .collect();
+    assert!(args.windows(2).any(|w| w[0] == "-w" && w[1] == "27262979"));
+    std::fs::remove_file(&dmx.dmenu).unwrap();
+}
+
+#[test]
+fn validate() {
+    let mut dmx = Dmx {
+        dmenu: fake_dmenu("validate", "usage: dmenu [-bfiv]"),
+        font: "monospace-12".into(),
+        ..Default::default()
+    };
+    assert!(dmx.validate().is_empty());
+    std::fs::remove_file(&dmx.dmenu).unwrap();
+
+    dmx.dmenu = "/nonexistent/dmenu".into();
+    dmx.font = " ".into();
+    let problems = dmx.validate();
+    assert_eq!(problems.len(), 2);
+    assert!(matches!(&problems[0], DmxError::BinaryNotFound(p) if p == &dmx.dmenu));
+    assert_eq!(problems[1].to_string(), "Invalid font \" \".");
+
+    dmx.dmenu = Path::new(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml");
+    assert!(matches!(&dmx.validate()[0], DmxError::NotExecutable(p) if p == &dmx.dmenu));
+    dmx.backends = vec![Backend::Numbered];
+    assert_eq!(dmx.validate().len(), 1);
+
+    // The other backends' programs are looked for in the menu's $PATH.
+    dmx.font = "monospace-12".into();
+    dmx.backends = vec![Backend::Rofi, Backend::Numbered, Backend::Fzf];
+    dmx.env = [("PATH".to_owned(), "/nonexistent".to_owned())].into();
+    let problems = dmx.validate();
+    assert_eq!(problems.len(), 2);
+    assert!(matches!(&problems[0], DmxError::BinaryNotFound(p) if p == Path::new("rofi")));
+    assert!(matches!(&problems[1], DmxError::BinaryNotFound(p) if p == Path::new("fzf")));
+    dmx.env = [("PATH".to_owned(), "/bin:/usr/bin".to_owned())].into();
+    dmx.backends = vec![Backend::Command(vec!["sh".to_owned()])];
+    assert!(dmx.validate().is_empty());
+}
+
+#[cfg(feature = "pywal")]
+#[test]
+fn pywal_colors() {
+    let path = std::env::temp_dir().join(format!("dmx-wal-{}.json", std::process::id()));
+    std::fs::write(
+        &path,
+        r##"{
+            "wallpaper": "/home/user/wall.jpg",
+            "alpha": "100",
+            "special": {"background": "#0f1011", "foreground": "#c3c4c5", "cursor": "#c3c4c5"},
+            "colors": {"color0": "#0f1011", "color1": "#5a6b7c", "color2": "#808080"}
+        }"##,
+    )
+    .unwrap();
+    let dmx = Dmx::from_pywal_file(&path).unwrap();
+    assert_eq!(dmx.normal_bg, Color::Rgb(0x0f, 0x10, 0x11));
+    assert_eq!(dmx.select_bg, Color::Rgb(0x5a, 0x6b, 0x7c));
+    assert_eq!(dmx.select_fg, dmx.normal_fg);
+
+    std::fs::write(&path, r##"{"special": {"background": "#000"}}"##).unwrap();
+    let err = Dmx::from_pywal_file(&path).unwrap_err();
+    assert_eq!(err.to_string(), "pywal colors are missing special.foreground.");
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn env_settings() {
+    use std::collections::HashMap;
+
+    let vars: HashMap<&str, &str> = [
+        ("DMX_FONT", "Terminus-9"),
+        ("DMX_SB", "#268bd2"),
+        ("DMX_NF", ""),
+        ("DMX_LINES", "15"),
+        ("DMX_FUZZY", "yes"),
+        ("DMX_BACKENDS", "fuzzel, dmenu"),
+    ]
+    .into_iter()
+    .collect();
+    let mut dmx = Dmx::default();
+    environment::apply(&mut dmx, |v| vars.get(v).map(|s| s.to_string())).unwrap();
+    assert_eq!(dmx.font.name(), "Terminus-9");
+    assert_eq!(dmx.select_bg, Color::Rgb(0x26, 0x8b, 0xd2));
+    assert_eq!(dmx.normal_fg, Dmx::default().normal_fg);
+    assert_eq!(dmx.max_lines, Some(15));
+    assert!(dmx.fuzzy);
+    assert_eq!(dmx.backends, vec![Backend::Fuzzel, Backend::Dmenu]);
+
+    for (var, value) in [("DMX_LINES", "lots"), ("DMX_FUZZY", "maybe"), ("DMX_NB", "#1234")] {
+        let err = environment::apply(&mut dmx, |v| (v == var).then(|| value.to_owned()));
+        assert_eq!(err.unwrap_err().to_string(), format!("Invalid ${} \"{}\".", var, value));
+    }
+}
+
+#[cfg(feature = "config")]
+#[test]
+fn json_config() {
+    let json = br##"{"font": "Terminus-12", "select_bg": "#005577", "backends": ["rofi", ["mymenu", "-p", "{prompt}"]]}"##;
+    let dmx = Dmx::from_bytes(json).unwrap();
+    assert_eq!(dmx.font.name(), "Terminus-12");
+    assert_eq!(dmx.select_bg, Color::Rgb(0x00, 0x55, 0x77));
+    assert_eq!(dmx.backends[1], Backend::Command(vec!["mymenu".into(), "-p".into(), "{prompt}".into()]));
+
+    let path = std::env::temp_dir().join(format!("dmx-conf-{}.json", std::process::id()));
+    std::fs::write(&path, "").unwrap();
+    // Empty TOML is fine, but empty JSON isn't.
+    assert!(Dmx::from_bytes(b"").is_ok());
+    assert!(Dmx::from_file(&path).is_err());
+    std::fs::write(&path, r#"{"max_lines": 4}"#).unwrap();
+    assert_eq!(Dmx::from_file(&path).unwrap().max_lines, Some(4));
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[cfg(feature = "yaml")]
+#[test]
+fn yaml_config() {
+    let path = std::env::temp_dir().join(format!("dmx-conf-{}.yml", std::process::id()));
+    std::fs::write(
+        &path,
+        "font: Terminus-12\nselect_bg: '#005577'\nbackends:\n  - rofi\n  - [mymenu, -p, '{prompt}']\n",
+    )
+    .unwrap();
+    let dmx = Dmx::from_file(&path).unwrap();
+    assert_eq!(dmx.font.name(), "Terminus-12");
+    assert_eq!(dmx.select_bg, Color::Rgb(0x00, 0x55, 0x77));
+    assert_eq!(dmx.backends[0], Backend::Rofi);
+
+    std::fs::write(&path, "\n").unwrap();
+    assert_eq!(Dmx::from_file(&path).unwrap(), Dmx::default());
+    std::fs::write(&path, "max_lines: lots\n").unwrap();
+    assert!(Dmx::from_file(&path).is_err());
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[cfg(feature = "config")]
+#[test]
+fn ini_config() {
+    let path = std::env::temp_dir().join(format!("dmx-conf-{}.ini", std::process::id()));
+    std::fs::write(
+        &path,
+        "; minimal\n\
+         font = Terminus-12\n\
+         normal_bg = #88cccc\n\
+         max_lines = 8\n\
+         center = true\n\
+         backends = rofi, dmenu\n\
+         extra_args = -x, \"100\", \"-fn\", \"Terminus,Noto Sans-12\"\n\
+         \n\
+         [env]\n\
+         DISPLAY = :1\n\
+         LINES = 40\n\
+         [retry]\n\
+         attempts = 3\n",
+    )
+    .unwrap();
+    let dmx = Dmx::from_file(&path).unwrap();
+    assert_eq!(dmx.font.name(), "Terminus-12");
+    assert_eq!(dmx.normal_bg, Color::Rgb(0x88, 0xcc, 0xcc));
+    assert_eq!((dmx.max_lines, dmx.center), (Some(8), true));
+    assert_eq!(dmx.backends, vec![Backend::Rofi, Backend::Dmenu]);
+    assert_eq!(dmx.extra_args, ["-x", "100", "-fn", "Terminus,Noto Sans-12"]);
+    assert_eq!(dmx.env["LINES"], "40");
+    assert_eq!(dmx.retry.unwrap().attempts, 3);
+
+    std::fs::write(&path, "font Terminus\n").unwrap();
+    assert!(Dmx::from_file(&path).unwrap_err().to_string().contains("line 1"));
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[cfg(feature = "config")]
+#[test]
+fn config_include() {
+    let dir = std::env::temp_dir().join(format!("dmx-include-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("themes")).unwrap();
+    std::fs::write(
+        dir.join("themes/dark.json"),
+        r##"{"font": "Terminus-12", "normal_bg": "#101010", "max_lines": 10}"##,
+    )
+    .unwrap();
+    std::fs::write(dir.join("host.toml"), "include = \"themes/dark.json\"\nfont = \"Terminus-16\"\n").unwrap();
+    let dmx = Dmx::from_file(dir.join("host.toml")).unwrap();
+    assert_eq!(dmx.font.name(), "Terminus-16");
+    assert_eq!(dmx.normal_bg, Color::Rgb(0x10, 0x10, 0x10));
+    assert_eq!(dmx.max_lines, Some(10));
+
+    std::fs::write(dir.join("loop.toml"), "include = \"loop.toml\"\n").unwrap();
+    let err = Dmx::from_file(dir.join("loop.toml")).unwrap_err();
+    assert!(err.to_string().contains("too deeply"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn backend_overrides() {
+    let mut dmx = Dmx {
+        max_lines: Some(10),
+        ..Default::default()
+    };
+    dmx.backend_overrides.insert(
+        "rofi".to_owned(),
+        BackendOverrides {
+            font: Some("Terminus-9".into()),
+            max_lines: Some(3),
+            args: vec!["-no-custom".to_owned()],
+            theme: Some("gruvbox-dark".to_owned()),
+            ..Default::default()
+        },
+    );
+    let args = |backend: &Backend| -> Vec<String> {
+        dmx.cmd(backend, "run:", 5)
+            .get_args()
+            .map(|a| a.to_string_lossy().into_owned())
+            .collect()
+    };
+    let rofi = args(&Backend::Rofi);
+    assert_eq!(&rofi[3..7], &["-l", "3", "-font", "Terminus 9"]);
+    assert_eq!(&rofi[7..], &["-theme", "gruvbox-dark", "-no-custom"]);
+    let bemenu = args(&Backend::Bemenu);
+    assert_eq!(&bemenu[2..6], &["-l", "5", "--fn", "LiberationMono-12"]);
+}
+
+#[cfg(feature = "config")]
+#[test]
+fn backend_sections() {
+    let dmx = Dmx::from_bytes(
+        b"[backend.rofi]\ntheme = \"arc\"\n\n[backend.dmenu]\nnormal_bg = \"#000\"\n",
+    )
+    .unwrap();
+    assert_eq!(dmx.backend_overrides["rofi"].theme.as_deref(), Some("arc"));
+    assert_eq!(dmx.backend_overrides["dmenu"].normal_bg, Some(Color::Rgb(0, 0, 0)));
+    assert!(Dmx::from_bytes(b"dmenu = \"/opt/dmenu\"\n").unwrap().backend_overrides.is_empty());
+    assert!(Dmx::from_bytes(b"[backend.rofi]\nmax_lines = \"many\"\n").is_err());
+    // Other tables aren't backends.
+    assert!(Dmx::from_bytes(b"[rofi]\ntheme = \"arc\"\n").unwrap().backend_overrides.is_empty());
+
+    let path = std::env::temp_dir().join(format!("dmx-backends-{}.ini", std::process::id()));
+    std::fs::write(&path, "[backend.bemenu]\nargs = --ignorecase, --wrap\n").unwrap();
+    let dmx = Dmx::from_file(&path).unwrap();
+    assert_eq!(dmx.backend_overrides["bemenu"].args, ["--ignorecase", "--wrap"]);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[cfg(feature = "config")]
+#[test]
+fn path_expansion() {
+    let lookup = |var: &str| match var {
+        "HOME" => Some("/home/me".to_owned()),
+        "XDG_DATA_HOME" => Some("/data".to_owned()),
+        _ => None,
+    };
+    let expand = |path: &str| config::expand_path_with(Path::new(path), lookup);
+    assert_eq!(expand("~/bin/dmenu-patched").unwrap(), Path::new("/home/me/bin/dmenu-patched"));
+    assert_eq!(expand("$XDG_DATA_HOME/dmx.log").unwrap(), Path::new("/data/dmx.log"));
+    assert_eq!(expand("${HOME}.d/$").unwrap(), Path::new("/home/me.d/$"));
+    assert_eq!(expand("/opt/~me/dmenu").unwrap(), Path::new("/opt/~me/dmenu"));
+    assert!(expand("$NOPE/dmenu").unwrap_err().to_string().contains("$NOPE is not set"));
+    assert!(expand("${HOME/dmenu").is_err());
+
+    let dmx = Dmx::from_bytes(b"dmenu = \"${HOME}/bin/dmenu\"\n").unwrap();
+    assert!(!dmx.dmenu.starts_with("$"));
+}
+
+#[cfg(feature = "config")]
+#[test]
+fn strict_config() {
+    let text = "\
+font = \"Terminus-12\"
+normal_gb = \"#000\"
+backends = [\"rofi\", [\"mymenu\", \"{prompt}\"]]
+
+[backend.rof]
+theme = \"arc\"
+
+[backend.rofi]
+them = \"arc\"
+
+[backend.mymenu]
+args = [\"-x\"]
+
+[backend.dmenu]
+theme = \"arc\"
+
+[app.emoji]
+fnot = \"Noto Color Emoji-16\"
+
+[retry]
+attemps = 3
+";
+    assert!(Dmx::from_bytes(text.as_bytes()).is_ok());
+    let err = Dmx::from_bytes(format!("strict = true\n{}", text).as_bytes()).unwrap_err();
+    let lines: Vec<String> = err.to_string().lines().map(str::to_owned).collect();
+    assert!(lines[0].ends_with("Unknown key `normal_gb` at line 3, column 1; did you mean `normal_bg`?"));
+    assert_eq!(
+        lines[1],
+        "Unknown key `backend.rofi.them` at line 10, column 1; did you mean `theme`?"
+    );
+    assert_eq!(lines[2], "Unknown key `app.emoji.fnot` at line 19, column 1; did you mean `font`?");
+    assert_eq!(
+        lines[3],
+        "Unknown key `retry.attemps` at line 22, column 1; did you mean `attempts`?"
+    );
+    assert_eq!(lines[4], "Unknown key `backend.rof` at line 6, column 10; did you mean `rofi`?");
+    assert_eq!(
+        lines[5],
+        "`backend.dmenu.theme` at line 16, column 1 only means anything for rofi."
+    );
+    assert_eq!(lines.len(), 6);
+
+    let err = Dmx::from_bytes(b"{\"strict\": true,\n  \"zzz\": 1}").unwrap_err();
+    assert!(err.to_string().ends_with("Unknown key `zzz` at line 2, column 4."));
+}
+
+#[cfg(feature = "config")]
+#[test]
+fn default_config() {
+    assert_eq!(Dmx::from_bytes(DEFAULT_CONFIG.as_bytes()).unwrap(), Dmx::default());
+
+    // Every commented-out setting should work when it's uncommented.
+    let uncommented: Vec<&str> = DEFAULT_CONFIG
+        .lines()
+        .map(|line| match line.strip_prefix('#') {
+            Some(setting) if !setting.starts_with([' ', '#']) && !setting.is_empty() => setting,
+            _ => line,
+        })
+        .collect();
+    let dmx = Dmx::from_bytes(uncommented.join("\n").as_bytes()).unwrap();
+    assert_eq!(dmx.max_lines, Some(20));
+    assert_eq!(dmx.backend_overrides["rofi"].theme.as_deref(), Some("gruvbox-dark"));
+
+    let path = std::env::temp_dir()
+        .join(format!("dmx-init-{}", std::process::id()))
+        .join("dmx.toml");
+    Dmx::write_default_config_to(&path).unwrap();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), DEFAULT_CONFIG);
+    assert!(Dmx::write_default_config_to(&path).is_err());
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[cfg(feature = "config")]
+#[test]
+fn config_diagnostics() {
+    let dir = std::env::temp_dir().join(format!("dmx-tried-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("broken.toml"), "font = \n").unwrap();
+    std::fs::write(dir.join("good.toml"), "font = \"Terminus-12\"\n").unwrap();
+    let files = ["missing.toml", "broken.toml", "good.toml", "unused.toml"].map(|f| dir.join(f));
+
+    let (dmx, tried) = Dmx::first_config(files.to_vec(), Vec::new());
+    assert_eq!(dmx.font.name(), "Terminus-12");
+    let outcomes: Vec<String> = tried.iter().map(|(_, outcome)| outcome.to_string()).collect();
+    assert_eq!(tried.len(), 3);
+    assert_eq!(tried[1].0, files[1]);
+    assert_eq!(outcomes[0], "not found");
+    assert!(outcomes[1].starts_with("not used: "));
+    assert_eq!(outcomes[2], "used");
+
+    let (dmx, tried) = Dmx::first_config(files[..2].to_vec(), Vec::new());
+    assert_eq!(dmx, Dmx::default());
+    assert!(matches!(tried[1].1, ConfigOutcome::Failed(DmxError::ConfigParse(_))));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(feature = "config")]
+#[test]
+fn config_dirs() {
+    let files = |vars: &[(&str, &str)]| -> Vec<PathBuf> {
+        let var_os = |var: &str| {
+            vars.iter()
+                .find(|(name, _)| *name == var)
+                .map(|(_, value)| value.into())
+        };
+        let mut files = config_files_in(var_os);
+        files.extend(system_config_files_in(var_os));
+        files
+    };
+    assert_eq!(
+        files(&[("HOME", "/home/me")]),
+        [PathBuf::from("/home/me/.config/dmx.toml"), PathBuf::from("/etc/xdg/dmx.toml")]
+    );
+    assert_eq!(
+        files(&[("XDG_CONFIG_HOME", "/cfg"), ("XDG_CONFIG_DIRS", "/usr/share/xdg:xdg:/opt/xdg")]),
+        [
+            PathBuf::from("/cfg/dmx.toml"),
+            PathBuf::from("/usr/share/xdg/dmx.toml"),
+            PathBuf::from("/opt/xdg/dmx.toml"),
+        ]
+    );
+    assert_eq!(
+        files(&[("XDG_CONFIG_HOME", ""), ("XDG_CONFIG_DIRS", "")]),
+        [PathBuf::from("/etc/xdg/dmx.toml")]
+    );
+}
+
+#[cfg(feature = "config")]
+#[test]
+fn app_sections() {
+    let dir = std::env::temp_dir().join(format!("dmx-apps-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("base.toml"), "max_lines = 5\n\n[app.emoji]\nmax_lines = 8\n").unwrap();
+    std::fs::write(
+        dir.join("dmx.toml"),
+        "include = \"base.toml\"\nfont = \"Terminus-12\"\n\n[app.emoji]\nfont = \"Noto Color Emoji-16\"\n",
+    )
+    .unwrap();
+    let emoji = Dmx::from_file_for(&dir.join("dmx.toml"), Some("emoji")).unwrap();
+    assert_eq!(emoji.font.name(), "Noto Color Emoji-16");
+    assert_eq!(emoji.max_lines, Some(8));
+    let other = Dmx::from_file_for(&dir.join("dmx.toml"), Some("launcher")).unwrap();
+    assert_eq!(other.font.name(), "Terminus-12");
+    assert_eq!(other.max_lines, Some(5));
+    assert_eq!(Dmx::from_file(dir.join("dmx.toml")).unwrap().max_lines, Some(5));
+
+    std::fs::write(dir.join("dmx.ini"), "font = Terminus-12\n\n[app.emoji]\nbackends = rofi, dmenu\n").unwrap();
+    let emoji = Dmx::from_file_for(&dir.join("dmx.ini"), Some("emoji")).unwrap();
+    assert_eq!(emoji.backends, vec![Backend::Rofi, Backend::Dmenu]);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(feature = "config")]
+#[test]
+fn pinned_layers() {
+    let dir = std::env::temp_dir().join(format!("dmx-pinned-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("base.toml"), "dmenu = \"no-such-dmenu\"\npin_binary = true\n").unwrap();
+    std::fs::write(dir.join("dmx.toml"), "include = \"base.toml\"\ndmenu = \"sh\"\n").unwrap();
+    let dmx = Dmx::from_file(dir.join("dmx.toml")).unwrap();
+    assert!(dmx.dmenu.is_absolute());
+    assert!(dmx.dmenu.ends_with("sh"));
+    assert!(dmx.absolute_binary);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(feature = "config")]
+#[test]
+fn checked_font_layers() {
+    let dir = std::env::temp_dir().join(format!("dmx-fonts-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("base.toml"), "check_font = true\n").unwrap();
+    std::fs::write(
+        dir.join("dmx.toml"),
+        "include = \"base.toml\"\nfont = \"No Such Font Anywhere-9\"\n",
+    )
+    .unwrap();
+    let dmx = Dmx::from_file(dir.join("dmx.toml")).unwrap();
+    // Whether or not fc-match is installed, this font isn't.
+    if Font::new("No Such Font Anywhere-9").is_installed() == Some(false) {
+        assert_eq!(dmx.font.name(), "monospace-9");
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn layered_config() {
+    let path = std::env::temp_dir().join(format!("dmx-layers-{}.toml", std::process::id()));
+    // Two system-wide directories, the first counting most.
+    let system = [path.with_extension("d"), path.with_extension("d2")];
+    #[cfg(feature = "config")]
+    {
+        std::fs::write(&path, "font = \"Terminus-12\"\nselect_bg = \"#005577\"\n").unwrap();
+        for (dir, text) in system.iter().zip([
+            "select_bg = \"#000000\"\nselect_fg = \"#ffffff\"\n",
+            "select_fg = \"#000000\"\nmax_lines = 7\n",
+        ]) {
+            std::fs::create_dir_all(dir).unwrap();
+            std::fs::write(dir.join("dmx.toml"), text).unwrap();
+        }
+    }
+    // Nothing but these files and $DMX_FONT.
+    let env = |var: &str| -> Option<std::ffi::OsString> {
+        match var {
+            "DMX_CONFIG" => Some(path.clone().into()),
+            "XDG_CONFIG_DIRS" => Some(std::env::join_paths(&system).unwrap()),
+            "DMX_FONT" => Some("Iosevka-10".into()),
+            _ => None,
+        }
+    };
+
+    let dmx = Dmx::load_in(None, env).unwrap();
+    assert_eq!(dmx.font.name(), "Iosevka-10");
+    #[cfg(feature = "config")]
+    {
+        assert_eq!(dmx.select_bg, Color::Rgb(0x00, 0x55, 0x77));
+        assert_eq!(dmx.select_fg, Color::Rgb(0xff, 0xff, 0xff));
+        assert_eq!(dmx.max_lines, Some(7));
+
+        let system_files = system.iter().map(|dir| dir.join("dmx.toml")).collect();
+        let (dmx, tried) = Dmx::first_config(vec![path.clone()], system_files);
+        assert_eq!(dmx.font.name(), "Terminus-12");
+        assert_eq!((dmx.select_fg, dmx.max_lines), (Color::Rgb(0xff, 0xff, 0xff), Some(7)));
+        assert_eq!(tried.len(), 3);
+        assert!(tried.iter().all(|(_, outcome)| matches!(outcome, ConfigOutcome::Used)));
+
+        std::fs::remove_file(&path).unwrap();
+        for dir in system.iter() {
+            std::fs::remove_dir_all(dir).unwrap();
+        }
+    }
+}
+
+#[test]
+fn xresources() {
+    let resources = xresources::parse(
+        "! comment\n\
+         *.background:\t#101010\n\
+         *foreground: #d0d0d0\n\
+         *.color4: #335577\n\
+         URxvt.font: xft:Iosevka:size=11\n\
+         dmenu*selfgcolor: #ffffff\n\
+         dmenu.font: Terminus-12\n",
+    );
+    assert_eq!(resources["background"], "#101010");
+    assert_eq!(resources["dmenu.selfgcolor"], "#ffffff");
+
+    let mut dmx = Dmx::default();
+    xresources::apply(&mut dmx, &resources).unwrap();
+    assert_eq!(dmx.font.name(), "Terminus-12");
+    assert_eq!(dmx.normal_bg, Color::Rgb(0x10, 0x10, 0x10));
+    assert_eq!(dmx.normal_fg, Color::Rgb(0xd0, 0xd0, 0xd0));
+    assert_eq!(dmx.select_bg, Color::Rgb(0x33, 0x55, 0x77));
+    assert_eq!(dmx.select_fg, Color::Rgb(0xff, 0xff, 0xff));
+
+    let bad = xresources::parse("*.background: #12\n");
+    assert!(xresources::apply(&mut dmx, &bad).is_err());
+
+    let defined = xresources::parse(
+        "#define BG #1d2021\n\
+         #define ACCENT BG\n\
+         #  define ff 00\n\
+         #define COLOR(x) x\n\
+         #ifdef BG\n\
+         *.background: BG\n\
+         *.color4: ACCENT\n\
+         *.foreground: #ffff00\n\
+         dmenu.font: COLOR(Terminus)\n",
+    );
+    assert_eq!(defined["background"], "#1d2021");
+    assert_eq!(defined["color4"], "#1d2021");
+    assert_eq!(defined["foreground"], "#ffff00");
+    assert_eq!(defined["dmenu.font"], "COLOR(Terminus)");
+}
+
+#[test]
+fn fonts() {
+    for (name, family, size) in [
+        ("Terminus-12", "Terminus", Some("12")),
+        ("DejaVu Sans Mono:size=9:antialias=false", "DejaVu Sans Mono", Some("9")),
+        ("DejaVu Sans Mono 10", "DejaVu Sans Mono", Some("10")),
+        ("Noto Sans:bold", "Noto Sans", None),
+    ] {
+        assert_eq!(Font::new(name).family_and_size(), (family, size));
+    }
+    assert_eq!(Font::new("Mono Space-9").is_installed(), Some(true));
+    assert_eq!(Font::new("-*-terminus-*-r-*-*-*-120-*-*-*-*-*-*").is_installed(), None);
+    // Whether or not fc-match is installed, this font isn't.
+    let missing = Font::new("No Such Font Anywhere-9");
+    assert_ne!(missing.is_installed(), Some(true));
+    if missing.is_installed() == Some(false) {
+        assert_eq!(missing.fallback(), Some(Font::new("monospace-9")));
+        assert_eq!(missing.or_fallback().name(), "monospace-9");
+    }
+    assert_eq!(Font::new("monospace-9").fallback(), None);
+}
+
+#[test]
+fn colors() {
+    for (text, color) in [
+        ("#abc", Color::Rgb(0xaa, 0xbb, 0xcc)),
+        ("#0A1b2C", Color::Rgb(0x0a, 0x1b, 0x2c)),
+        ("#ffff80000000", Color::Rgb(0xff, 0x80, 0x00)),
+        ("SteelBlue", Color::Named("SteelBlue".to_owned())),
+    ] {
+        assert_eq!(text.parse::<Color>().unwrap(), color);
+    }
+    for text in ["", "#", "#12", "#12345g", "#1234567", "rgb:ff/00/00", "#ä1"] {
+        assert!(text.parse::<Color>().is_err(), "{:?}", text);
+    }
+    assert_eq!(Color::Rgb(1, 2, 255).to_string(), "#0102ff");
+
+    #[cfg(feature = "config")]
+    {
+        let dmx = Dmx::from_bytes(b"normal_bg = \"#123\"\n").unwrap();
+        assert_eq!(dmx.normal_bg, Color::Rgb(0x11, 0x22, 0x33));
+        assert!(Dmx::from_bytes(b"normal_bg = \"#1234\"\n").is_err());
+    }
+}
+
+#[test]
+fn monitor() {
+    let dmx = Dmx {
+        dmenu: fake_dmenu("monitor", "usage: dmenu [-bfiv] [-l lines] [-m monitor]"),
+        monitor: Some(1),
+        ..Default::default()
+    };
+    let args = |backend: Backend| -> Vec<String> {
+        dmx.cmd(&backend, "where:", 5)
+            .get_args()
+            .map(|a| a.to_string_lossy().into_owned())
+            .collect()
+    };
+    assert!(args(Backend::Dmenu).windows(2).any(|w| w == ["-m", "1"]));
+    assert!(args(Backend::Rofi).windows(2).any(|w| w == ["-monitor", "1"]));
+    assert!(args(Backend::Bemenu).windows(2).any(|w| w == ["-m", "1"]));
+    std::fs::remove_file(&dmx.dmenu).unwrap();
+}
+
+#[test]
+fn child_env() {
+    let mut dmx = Dmx {
+        dmenu: fake_dmenu("child-env", "usage: dmenu [-bfiv] [-l lines]"),
+        env_remove: vec!["WAYLAND_DISPLAY".to_owned()],
+        ..Default::default()
+    };
+    dmx.env.insert("DISPLAY".to_owned(), ":7".to_owned());
+    assert_eq!(dmx.child_var("DISPLAY"), Some(":7".into()));
+    assert_eq!(dmx.child_var("WAYLAND_DISPLAY"), None);
+
+    let cmd = dmx.cmd(&Backend::Dmenu, "env:", 1);
+    let envs: Vec<_> = cmd.get_envs().collect();
+    assert!(envs.contains(&(std::ffi::OsStr::new("DISPLAY"), Some(std::ffi::OsStr::new(":7")))));
+    assert!(envs.contains(&(std::ffi::OsStr::new("WAYLAND_DISPLAY"), None)));
+    std::fs::remove_file(&dmx.dmenu).unwrap();
+
+    // The menu really does see it.
+    let dmx = Dmx {
+        command: Some(vec![
+            "sh".to_owned(),
+            "-c".to_owned(),
+            "cat >/dev/null; echo \"$DMX_TEST_VAR\"".to_owned(),
+        ]),
+        env: [("DMX_TEST_VAR".to_owned(), "toads".to_owned())].into(),
+        ..Default::default()
+    };
+    assert_eq!(dmx.select("env:", &["frogs", "toads"]).unwrap(), Some(1));
+}
+
+#[test]
+fn command_hook() {
+    // The hook's argument picks the line.
+    let dmx = Dmx {
+        command: Some(vec!["sed".to_owned(), "-n".to_owned()]),
+        ..Default::default()
+    }
+    .with_command_hook(|cmd| {
+        cmd.arg("2p");
+    });
+    assert_eq!(dmx.select("hook:", &["frogs", "toads"]).unwrap(), Some(1));
+    assert_eq!(dmx.clone(), dmx);
+    assert_ne!(dmx, dmx.clone().with_command_hook(|_| {}));
+
+    // A hook can't get a bare program name past `absolute_binary`.
+    let dmx = Dmx {
+        command: Some(vec!["/bin/cat".to_owned()]),
+        absolute_binary: true,
+        ..Default::default()
+    }
+    .with_command_hook(|cmd| {
+        *cmd = Command::new("cat");
+    });
+    assert!(dmx.select("hook:", &["frogs"]).is_err());
+}
+
+#[test]
+fn extra_args() {
+    #[cfg(feature = "config")]
+    {
+        let dmx = Dmx::from_bytes(b"extra_args = [\"-y\", \"40\"]").unwrap();
+        assert_eq!(dmx.extra_args, vec!["-y".to_owned(), "40".to_owned()]);
+    }
+
+    let dmx = Dmx {
+        extra_args: vec!["-x".to_owned(), "100".to_owned()],
+        ..Default::default()
+    };
+
+    for backend in [Backend::Dmenu, Backend::DmenuWl] {
+        let args: Vec<String> = backend
+            .command(&dmx, "extra:", 3)
+            .get_args()
+            .map(|a| a.to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(&args[args.len() - 2..], &["-x", "100"]);
+    }
+    let fzf = Backend::Fzf.command(&dmx, "extra:", 3);
+    assert!(!fzf.get_args().any(|a| a == "-x"));
+}
+
+#[test]
+fn rofi_script() {
+    use rofi_script::{Call, Event, Reply, Row};
+
+    let call = Call::new(Some("1"), Some("green".to_owned()), Some("1".to_owned()), None);
+    assert_eq!(call.event, Event::Selected);
+    assert_eq!(call.index(), Some(1));
+    assert_eq!(Call::new(None, None, None, None).event, Event::Start);
+    assert_eq!(Call::new(Some("12"), None, None, None).event, Event::Key(3));
+
+    let reply = Reply::new()
+        .prompt("color")
+        .no_custom()
+        .items(&["red", "green"])
+        .row(Row::new("two\nlines").icon("x\x1fy").urgent());
+    let mut out = Vec::new();
+    reply.write_to(&mut out).unwrap();
+    assert_eq!(
+        out,
+        b"\0prompt\x1fcolor\n\0no-custom\x1ftrue\n\
+          red\0info\x1f0\ngreen\0info\x1f1\n\
+          two lines\0icon\x1fx y\x1furgent\x1ftrue\n"
+    );
+}
+
+#[cfg(feature = "switcher")]
+#[test]
+fn switcher_windows() {
+    let wins = switcher::parse_wmctrl(
+        "0x03a00003  0 frog Inbox - Mail\n0x01e00006 -1 frog  Panel\n\nbogus\n",
+    );
+    assert_eq!(wins.len(), 2);
+    assert_eq!(wins[0].id, "0x03a00003");
+    assert_eq!(wins[0].desktop.as_deref(), Some("0"));
+    assert_eq!(wins[0].title, "Inbox - Mail");
+    assert_eq!(wins[1].desktop, None);
+    assert_eq!(wins[1].line(1), b"   Panel\n".to_vec());
+
+    let tree: serde_json::Value = serde_json::from_str(
+        r#"{"id": 1, "type": "root", "nodes": [
+            {"id": 2, "type": "output", "name": "eDP-1", "nodes": [
+                {"id": 3, "type": "workspace", "name": "2", "nodes": [
+                    {"id": 7, "type": "con", "name": "vim", "pid": 100, "nodes": []}
+                ], "floating_nodes": [
+                    {"id": 8, "type": "floating_con", "name": "calc", "pid": 101}
+                ]}
+            ]}
+        ]}"#,
+    )
+    .unwrap();
+    let wins = switcher::parse_sway(&tree);
+    let found: Vec<_> = wins
+        .iter()
+        .map(|w| (w.id.as_str(), w.desktop.as_deref(), w.title.as_str()))
+        .collect();
+    assert_eq!(found, vec![("7", Some("2"), "vim"), ("8", Some("2"), "calc")]);
+}
+
+#[test]
+fn path_executables() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let root = std::env::temp_dir().join(format!("dmx-path-{}", std::process::id()));
+    let (first, second) = (root.join("first"), root.join("second"));
+    let make = |path: std::path::PathBuf, mode: u32| {
+        std::fs::write(&path, "#!/bin/sh\n").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(mode)).unwrap();
+    };
+    std::fs::create_dir_all(&first).unwrap();
+    std::fs::create_dir_all(&second).unwrap();
+    make(first.join("toad"), 0o755);
+    make(first.join("notes.txt"), 0o644);
+    make(second.join("toad"), 0o755);
+    make(second.join("frog"), 0o700);
+    std::fs::create_dir_all(second.join("pond")).unwrap();
+
+    let dirs = vec![first.clone(), root.join("missing"), second.clone()];
+    let cache = root.join("cache");
+    let (found, written) = path_bin::load_dirs(&dirs, &cache, false);
+    written.unwrap();
+    let names: Vec<_> = found.iter().map(|e| e.name.as_str()).collect();
+    assert_eq!(names, vec!["frog", "toad"]);
+    assert_eq!(found[1].path, first.join("toad"));
+    assert_eq!(found[0].line(0), b"frog\n".to_vec());
+
+    // A change of permissions isn't noticed until the list is rebuilt...
+    let no_exec = std::fs::Permissions::from_mode(0o644);
+    std::fs::set_permissions(first.join("toad"), no_exec).unwrap();
+    let (cached, _) = path_bin::load_dirs(&dirs, &cache, false);
+    assert_eq!(cached, found);
+    let (rebuilt, _) = path_bin::load_dirs(&dirs, &cache, true);
+    assert_eq!(rebuilt[1].path, second.join("toad"));
+
+    // ...but a new program is, as soon as its directory has changed.
+    std::thread::sleep(std::time::Duration::from_millis(20));
+    make(second.join("newt"), 0o755);
+    let (found, _) = path_bin::load_dirs(&dirs, &cache, false);
+    assert!(found.iter().any(|e| e.name == "newt"));
+
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn desktop_argv() {
+    let entry = desktop::DesktopEntry {
+        id: "viewer.desktop".to_owned(),
+        path: "/usr/share/applications/viewer.desktop".into(),
+        name: "Viewer".to_owned(),
+        generic_name: None,
+        exec: r#"viewer %i --title="%c \"100%%\"" -q %U --conf=%k"#.to_owned(),
+        icon: Some("eye".to_owned()),
+        mime_types: Vec::new(),
+        no_display: false,
+    };
+    assert_eq!(
+        entry.argv(),
+        vec![
+            "viewer",
+            "--icon",
+            "eye",
+            "--title=Viewer \"100%\"",
+            "-q",
+            "--conf=/usr/share/applications/viewer.desktop",
+        ]
+    );
+
+    let entry = desktop::DesktopEntry {
+        exec: r#""/opt/my app/run" "" %f"#.to_owned(),
+        icon: None,
+        ..entry
+    };
+    assert_eq!(entry.argv(), vec!["/opt/my app/run", ""]);
+}
+
+#[test]
+fn typed_errors() {
+    let dmx = Dmx {
+        backends: vec![Backend::Command(vec!["/nonexistent/dmx-menu".to_owned()])],
+        ..Default::default()
+    };
+    match dmx.select("typed:", &["frogs"]).unwrap_err() {
+        DmxError::BinaryNotFound(path) => assert_eq!(path, Path::new("/nonexistent/dmx-menu")),
+        e => panic!("{:?}", e),
+    }
+
+    let dmx = Dmx {
+        backends: vec![
+            Backend::Command(vec!["/nonexistent/dmx-menu".to_owned()]),
+            Backend::Command(vec!["/nonexistent/dmx-menu-2".to_owned()]),
+        ],
+        ..Default::default()
+    };
+    match dmx.select("typed:", &["frogs"]).unwrap_err() {
+        DmxError::NoBackend(errors) => assert_eq!(errors.len(), 2),
+        e => panic!("{:?}", e),
+    }
+
+    let dmx = Dmx {
+        command: Some(vec![
+            "sh".to_owned(),
+            "-c".to_owned(),
+            "cat >/dev/null; echo 'it broke' >&2; exit 3".to_owned(),
+        ]),
+        stderr: StderrMode::Capture,
+        ..Default::default()
+    };
+    match dmx.select("typed:", &["frogs"]).unwrap_err() {
+        DmxError::MenuFailed { status, stderr, .. } => {
+            assert_eq!(status.code(), Some(3));
+            assert_eq!(stderr, "it broke");
+        }
+        e => panic!("{:?}", e),
+    }
+
+    let err = "Backend::from_str".parse::<Backend>().unwrap_err();
+    assert!(matches!(err, DmxError::ConfigParse(_)), "{:?}", err);
+
+    let dmx = Dmx {
+        command: Some(vec!["sed".to_owned(), "-n".to_owned(), "0~9p".to_owned()]),
+        ..Default::default()
+    };
+    let err = dmx.select("typed:", &["frogs"]).unwrap().ok_or(DmxError::Cancelled).unwrap_err();
+    assert_eq!(err.to_string(), "The menu was cancelled.");
+
+    let mut dmx = Dmx {
+        dmenu: "dmx-nonexistent-menu".into(),
+        ..Default::default()
+    };
+    match dmx.pin_binary().unwrap_err() {
+        DmxError::BinaryNotFound(path) => assert_eq!(path, Path::new("dmx-nonexistent-menu")),
+        e => panic!("{:?}", e),
+    }
+    let err = dmx.open_with(&[&["/nonexistent/dmx-opener"]], "mailto:x").unwrap_err();
+    assert!(matches!(err, DmxError::NoneInstalled(ref p) if p.len() == 1), "{:?}", err);
+    let err = dmx.open_with(&[&["sh", "-c", "exit 4"]], "mailto:x").unwrap_err();
+    assert!(matches!(err, DmxError::CommandFailed { status, .. } if status.code() == Some(4)));
+    let err = dmx.open_with(&[&["true"]], "/nonexistent/file").unwrap_err();
+    let missing = |source: &std::io::Error| source.kind() == std::io::ErrorKind::NotFound;
+    assert!(matches!(err, DmxError::Io { ref source, .. } if missing(source)), "{:?}", err);
+    let err = store::state_file("../up", "recent", |_| None).unwrap_err();
+    assert!(matches!(err, DmxError::InvalidArgument(_)), "{:?}", err);
+    let err = store::state_file("ok", "recent", |_| None).unwrap_err();
+    assert!(matches!(err, DmxError::EnvNotSet { ref var, .. } if var == "HOME"), "{:?}", err);
+}
+
+#[test]
+fn notify_errors() {
+    #[cfg(feature = "config")]
+    assert!(Dmx::from_bytes(b"notify_errors = true").unwrap().notify_errors);
+
+    // A stand-in for notify-send, found through the menu's $PATH, which
+    // writes down what it was asked to show.
+    use std::os::unix::fs::PermissionsExt;
+    let dir = std::env::temp_dir().join(format!("dmx-notify-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let shown = dir.join("shown");
+    let script = format!("#!/bin/sh\nprintf '%s\\n' \"$@\" > '{}'\n", shown.display());
+    std::fs::write(dir.join("notify-send"), script).unwrap();
+    std::fs::set_permissions(dir.join("notify-send"), std::fs::Permissions::from_mode(0o755))
+        .unwrap();
+
+    let dmx = Dmx {
+        backends: vec![Backend::Command(vec!["/nonexistent/dmx-menu".to_owned()])],
+        notify_errors: true,
+        env: [("PATH".to_owned(), dir.to_string_lossy().into_owned())].into(),
+        ..Default::default()
+    };
+    let err = dmx.select("notify:", &["frogs"]).unwrap_err();
+    assert!(err.to_string().contains("Unable to find"), "{}", err);
+    let shown = std::fs::read_to_string(&shown).unwrap();
+    assert!(shown.starts_with("--urgency=critical\n"), "{}", shown);
+    assert!(shown.ends_with(&format!("{}\n", err)), "{}", shown);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn copy_selection() {
+    let items = &[("frog", "green"), ("toad", "brown")];
+    assert_eq!(clipboard::line_text(&items[1], 4), "toad  brown");
+
+    // Nothing is copied if nothing's chosen.
+    let dmx = Dmx {
+        command: Some(vec!["sed".to_owned(), "-n".to_owned(), "0~9p".to_owned()]),
+        ..Default::default()
+    };
+    assert_eq!(dmx.select_and_copy("copy:", items).unwrap(), None);
+}
+
+#[test]
+fn confirm() {
+    let answer = |line: &str| Dmx {
+        command: Some(vec!["sed".to_owned(), "-n".to_owned(), format!("{}p", line)]),
+        ..Default::default()
+    };
+    assert!(answer("2").confirm("sure?").unwrap());
+    assert!(!answer("1").confirm("sure?").unwrap());
+    assert!(!answer("9").confirm("sure?").unwrap());
+}
+
+#[cfg(feature = "power")]
+#[test]
+fn power_menu() {
+    use power::Action;
+
+    assert_eq!(Action::Shutdown.argv(), vec!["systemctl", "poweroff"]);
+    assert!(Action::Reboot.needs_confirmation());
+    assert!(!Action::Lock.needs_confirmation());
+
+    // Only the choosing is tested; `power::menu()` would carry it out.
+    let answering = |pattern: &str| Dmx {
+        command: Some(vec![
+            "sh".to_owned(),
+            "-c".to_owned(),
+            format!("grep -x -m1 {}", pattern),
+        ]),
+        ..Default::default()
+    };
+    assert_eq!(power::choose(&answering("-e reboot -e no")).unwrap(), None);
+    assert_eq!(
+        power::choose(&answering("-e reboot -e yes")).unwrap(),
+        Some(Action::Reboot)
+    );
+    assert_eq!(power::choose(&answering("-e lock")).unwrap(), Some(Action::Lock));
+}
+
+#[test]
+fn builder() {
+    let dmx = DmxBuilder::new()
+        .dmenu_path(fake_dmenu("built", "usage: dmenu [-bfiv] [-l lines]"))
+        .colors("black", "gray50", "#3465a4", "#ffffffffffff")
+        .lines(3)
+        .build()
+        .unwrap();
+    let args: Vec<String> = dmx
+        .cmd(&Backend::Dmenu, "built:", 10)
+        .get_args()
+        .map(|a| a.to_string_lossy().into_owned())
+        .collect();
+    assert!(args.windows(2).any(|w| w[0] == "-l" && w[1] == "3"));
+    std::fs::remove_file(&dmx.dmenu).unwrap();
+
+    assert!(DmxBuilder::new().font("").build().is_err());
+    assert!(DmxBuilder::new().lines(0).build().is_err());
+    assert!(DmxBuilder::new().dmenu_path("").build().is_err());
+    assert!(DmxBuilder::new().backends(vec![]).build().is_err());
+    assert!(DmxBuilder::new().colors("#222", "#aaa", "#888", "#afg").build().is_err());
+    assert!(DmxBuilder::new().colors("red", "-fn", "#888", "#aff").build().is_err());
+}
+
+#[test]
+fn prompt_template() {
+    use std::cell::RefCell;
+
+    // Picks the first line, and remembers the prompt it was given.
+    struct Spy(RefCell<String>);
+    impl MenuBackend for Spy {
+        type Session = Vec<Vec<u8>>;
+        fn spawn(&self, prompt: &str, _: usize) -> Result<Self::Session, DmxError> {
+            *self.0.borrow_mut() = prompt.to_owned();
+            Ok(Vec::new())
+        }
+        fn feed(&self, session: &mut Self::Session, input: &[Vec<u8>]) -> Result<(), DmxError> {
+            session.extend_from_slice(input);
+            Ok(())
+        }
+        fn read_selection(&self, session: Self::Session) -> Result<Option<Vec<u8>>, DmxError> {
+            Ok(session.first().cloned())
+        }
+    }
+
+    let spy = Spy(RefCell::new(String::new()));
+    let menu = Menu::new("{count} {{colors}}:").items(STR_CHOICES);
+    assert_eq!(menu.show_with(&spy).unwrap(), Some(0));
+    // Prompts are left alone unless they're templates.
+    assert_eq!(&*spy.0.borrow(), "{count} {{colors}}:");
+    assert_eq!(menu.template(true).show_with(&spy).unwrap(), Some(0));
+    assert_eq!(&*spy.0.borrow(), &format!("{} {{colors}}:", STR_CHOICES.len()));
+
+    let mut crumbs = Breadcrumbs::new("go:").template("{title} ({count}) in {path} {{path}}");
+    assert_eq!(&crumbs.to_string(), "go: ({count}) in go: {{path}}");
+    crumbs.push("ssh");
+    crumbs.push("work");
+    let menu = Menu::new(crumbs.to_string()).items(&["a", "b"]).template(true);
+    menu.show_with(&spy).unwrap();
+    assert_eq!(&*spy.0.borrow(), "work (2) in go:ssh/work/ {path}");
+
+    // Only the path is shortened.
+    let mut crumbs = Breadcrumbs::new("go:").template("{path}!").max_width(5);
+    crumbs.push("ssh");
+    assert_eq!(&crumbs.to_string(), "…ssh/!");
+}
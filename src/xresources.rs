@@ -0,0 +1,178 @@
+/*!
+Taking the menu's font and colors from X resources, where classic X11
+setups keep the theme every program shares.
+*/
+
+use std::collections::HashMap;
+use std::process::{Command, Stdio};
+
+use crate::{Color, Dmx, DmxError, Font};
+
+/*
+The resources to look for, for each setting, in order of preference:
+first the names the `dmenu` xresources patch reads, then more generic
+ones.
+*/
+const FONT: &[&str] = &["dmenu.font"];
+const NORMAL_BG: &[&str] = &["dmenu.normbgcolor", "dmenu.background", "background"];
+const NORMAL_FG: &[&str] = &["dmenu.normfgcolor", "dmenu.foreground", "foreground"];
+const SELECT_BG: &[&str] = &["dmenu.selbgcolor", "dmenu.selbackground", "color4"];
+const SELECT_FG: &[&str] = &["dmenu.selfgcolor", "dmenu.selforeground", "foreground"];
+
+/*
+Parse resource lines, like `dmenu.font: Terminus-12`, into a map from
+resource names to values. Wildcards are dropped from the names, so that
+`*.background`, `*background`, and `background` all come out as
+`background`, and `dmenu*font` as `dmenu.font`.
+
+Object-like `#define`s are expanded in the lines after them, as `xrdb`
+has the C preprocessor do; other directives (`#include`, `#ifdef`, ...)
+are ignored.
+*/
+pub(crate) fn parse(text: &str) -> HashMap<String, String> {
+    let mut macros = HashMap::new();
+    let mut resources = HashMap::new();
+    for line in text.lines().map(|line| line.trim()) {
+        if let Some(directive) = line.strip_prefix('#') {
+            if let Some(define) = directive.trim_start().strip_prefix("define") {
+                define_macro(&mut macros, define);
+            }
+            continue;
+        }
+        if line.starts_with('!') {
+            continue;
+        }
+        let line = expand(line, &macros);
+        if let Some((name, value)) = line.split_once(':') {
+            let name: Vec<&str> = name
+                .trim()
+                .split(['.', '*'])
+                .filter(|part| !part.is_empty())
+                .collect();
+            resources.insert(name.join("."), value.trim().to_owned());
+        }
+    }
+    resources
+}
+
+/*
+Add the macro in `define`, the rest of a `#define NAME value` line, to
+`macros`, with the macros already defined expanded in its value. Macros
+with parameters are skipped.
+*/
+fn define_macro(macros: &mut HashMap<String, String>, define: &str) {
+    if !define.starts_with([' ', '\t']) {
+        return;
+    }
+    let define = define.trim_start();
+    let end = define
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .unwrap_or(define.len());
+    let (name, value) = define.split_at(end);
+    if name.is_empty() || value.starts_with('(') {
+        return;
+    }
+    let value = expand(value.trim(), macros);
+    macros.insert(name.to_owned(), value);
+}
+
+/*
+`line`, with each identifier that's the name of one of the `macros`
+replaced by its value. Like the C preprocessor, this leaves alone letters
+that are part of a number, like the `ff` in `#00ff00`.
+*/
+fn expand(line: &str, macros: &HashMap<String, String>) -> String {
+    if macros.is_empty() {
+        return line.to_owned();
+    }
+    let word = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    let mut expanded = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(c) = rest.chars().next() {
+        if word(c) {
+            let end = rest.find(|c: char| !word(c)).unwrap_or(rest.len());
+            let (token, after) = rest.split_at(end);
+            match macros.get(token) {
+                Some(value) if !c.is_ascii_digit() => expanded.push_str(value),
+                _ => expanded.push_str(token),
+            }
+            rest = after;
+        } else {
+            expanded.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    expanded
+}
+
+/*
+Set the font and colors in `dmx` from the `resources` there are values
+for.
+*/
+pub(crate) fn apply(dmx: &mut Dmx, resources: &HashMap<String, String>) -> Result<(), DmxError> {
+    let find = |names: &[&str]| names.iter().find_map(|n| resources.get(*n));
+
+    if let Some(font) = find(FONT) {
+        dmx.font = Font::new(font.as_str());
+    }
+    for (names, color) in [
+        (NORMAL_BG, &mut dmx.normal_bg),
+        (NORMAL_FG, &mut dmx.normal_fg),
+        (SELECT_BG, &mut dmx.select_bg),
+        (SELECT_FG, &mut dmx.select_fg),
+    ] {
+        if let Some(value) = find(names) {
+            *color = value.parse::<Color>()?;
+        }
+    }
+    Ok(())
+}
+
+impl Dmx {
+    /**
+    Return a `Dmx` with the default settings, except for the font and
+    colors, which are taken from the X resources (as `xrdb -query`
+    lists them, or if it can't be run, as `~/.Xresources` sets them, with
+    its `#define`s expanded).
+
+    The resources the `dmenu` xresources patch reads are used first:
+
+    ```text
+    dmenu.font:        Terminus-12
+    dmenu.normbgcolor: #222222
+    dmenu.normfgcolor: #bbbbbb
+    dmenu.selbgcolor:  #005577
+    dmenu.selfgcolor:  #eeeeee
+    ```
+
+    Without them, the colors come from the usual terminal palette: the
+    `*.background` and `*.foreground` for normal items, and `*.color4`
+    behind the selected one.
+    */
+    pub fn from_xresources() -> Result<Dmx, DmxError> {
+        let queried = Command::new("xrdb")
+            .arg("-query")
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+            .ok()
+            .filter(|o| o.status.success())
+            .map(|o| String::from_utf8_lossy(&o.stdout).into_owned());
+        let text = match queried {
+            Some(text) => text,
+            None => {
+                let home = std::env::var_os("HOME").ok_or_else(|| DmxError::EnvNotSet {
+                    var: "HOME".to_owned(),
+                    what: "~/.Xresources".to_owned(),
+                })?;
+                let path = std::path::Path::new(&home).join(".Xresources");
+                std::fs::read_to_string(&path)
+                    .map_err(|e| DmxError::io(format!("Error reading {}", path.display()), e))?
+            }
+        };
+
+        let mut dmx = Dmx::default();
+        apply(&mut dmx, &parse(&text))?;
+        Ok(dmx)
+    }
+}